@@ -0,0 +1,68 @@
+/* C API for resize-xcursor, enabled by the `ffi` Cargo feature. */
+
+#ifndef RESIZE_XCURSOR_H
+#define RESIZE_XCURSOR_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RESIZE_XCURSOR_OK 0
+#define RESIZE_XCURSOR_ERROR_PARSE 1
+#define RESIZE_XCURSOR_ERROR_TOO_LARGE 2
+#define RESIZE_XCURSOR_ERROR_NULL 3
+#define RESIZE_XCURSOR_ERROR_OTHER 4
+
+typedef struct ResizeXcursorImageInfo {
+    uint32_t size;
+    uint32_t width;
+    uint32_t height;
+    uint32_t xhot;
+    uint32_t yhot;
+    uint32_t delay;
+    /* The offset of this image's chunk from the start of the file. */
+    uint32_t position;
+} ResizeXcursorImageInfo;
+
+/*
+ * Resizes an Xcursor file. On success, `*output` and `*output_len` are set
+ * to a buffer containing the resized file, which must be freed with
+ * `resize_xcursor_free`.
+ */
+int32_t resize_xcursor_resize(
+    const uint8_t *input,
+    size_t input_len,
+    uint32_t scale,
+    uint8_t **output,
+    size_t *output_len
+);
+
+/* Frees a buffer returned by `resize_xcursor_resize`. */
+void resize_xcursor_free(uint8_t *buffer, size_t len);
+
+/*
+ * Reads the fields of each image in an Xcursor file. Up to `capacity` images
+ * are written to `images`, and `*count` is set to the total number of images.
+ */
+int32_t resize_xcursor_images(
+    const uint8_t *input,
+    size_t input_len,
+    ResizeXcursorImageInfo *images,
+    size_t capacity,
+    size_t *count
+);
+
+/*
+ * Returns a description of the last error on this thread, or NULL.
+ * The string is valid until the next call into this library on this thread.
+ */
+const char *resize_xcursor_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RESIZE_XCURSOR_H */
@@ -0,0 +1,453 @@
+//! Facilities for editing the timing of animated cursors, and for generating
+//! animations from static ones.
+
+use crate::{
+    filters::Opacity, transform::Transform, Chunk, Error, Image, ImageLayout, ParsedImage, Result,
+};
+use std::collections::HashMap;
+
+/// A [`Transform`] that multiplies every image's delay by a factor, so that
+/// a factor of 2 makes an animation twice as slow.
+///
+/// ```no_run
+/// use resize_xcursor::{animation::ScaleDelays, transform::Pipeline, ResizeOptions};
+///
+/// // Twice as large, and half as fast
+/// let mut pipeline = Pipeline::new();
+/// pipeline
+///     .push(ResizeOptions::new(2))
+///     .push(ScaleDelays::new(2.0));
+/// ```
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct ScaleDelays {
+    /// The factor to multiply each delay by. Delays are rounded to the
+    /// nearest millisecond.
+    pub factor: f64,
+}
+
+impl ScaleDelays {
+    pub fn new(factor: f64) -> Self {
+        Self { factor }
+    }
+}
+
+impl Transform for ScaleDelays {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        let delay = image.delay();
+        let factor = self.factor;
+
+        image.set_delay(scale_delay(delay, factor).ok_or(Error::DelayOverflow { delay, factor })?);
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that keeps every image's delay between a minimum and a
+/// maximum, such as to stop animations with 0ms delays from running as fast
+/// as the compositor can draw them.
+///
+/// ```
+/// use resize_xcursor::{animation::ClampDelays, Image, Transform};
+///
+/// let image = Image::new(1, 1, 1, 0, 0, 0, vec![0])?;
+/// assert_eq!(ClampDelays::new(20, 1000).apply(image)?.delay(), 20);
+/// # Ok::<(), resize_xcursor::Error>(())
+/// ```
+///
+/// The delays of cursors that aren't animated are clamped too, which is
+/// harmless since they're never used.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct ClampDelays {
+    /// The shortest delay to allow, in milliseconds.
+    pub min: u32,
+    /// The longest delay to allow, in milliseconds. If this is less than
+    /// `min`, every delay becomes `min`.
+    pub max: u32,
+}
+
+impl ClampDelays {
+    pub fn new(min: u32, max: u32) -> Self {
+        Self { min, max }
+    }
+}
+
+impl Transform for ClampDelays {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        image.set_delay(image.delay().min(self.max).max(self.min));
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// Multiplies a frame delay (in milliseconds) by `factor`, rounding to the
+/// nearest millisecond.
+///
+/// Returns `None` if `factor` is negative or not a number, or if the
+/// new delay wouldn't fit in a `u32`.
+///
+/// ```
+/// use resize_xcursor::animation::scale_delay;
+///
+/// assert_eq!(scale_delay(50, 1.5), Some(75));
+/// assert_eq!(scale_delay(50, -1.0), None);
+/// ```
+pub fn scale_delay(delay: u32, factor: f64) -> Option<u32> {
+    if factor.is_nan() || factor < 0.0 {
+        return None;
+    }
+
+    let delay = (f64::from(delay) * factor).round();
+
+    // This also rejects infinite delays
+    if delay > f64::from(u32::MAX) {
+        return None;
+    }
+
+    Some(delay as u32)
+}
+
+/// Resamples an animation to `count` frames, dropping or duplicating frames
+/// evenly.
+///
+/// The animation's total duration is kept, spread evenly across the new
+/// frames, and each new frame shows whichever original frame would have been
+/// visible when it starts, so the first frame is always kept. If `frames`
+/// already has `count` frames, it's returned unchanged, and if `count` is 0,
+/// no frames are returned.
+///
+/// ```
+/// use resize_xcursor::{animation::retarget_frames, Image};
+///
+/// let frames = (0..4)
+///     .map(|index| Image::new(1, 1, 1, 0, 0, 50, vec![index]))
+///     .collect::<Result<Vec<_>, _>>()?;
+///
+/// let retargeted = retarget_frames(frames, 2);
+/// assert_eq!(retargeted.len(), 2);
+/// assert_eq!(retargeted[0].delay(), 100);
+/// assert_eq!(retargeted[1].pixels(), [2]);
+/// assert!(retarget_frames(retargeted, 0).is_empty());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn retarget_frames(frames: Vec<Image>, count: usize) -> Vec<Image> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    if frames.len() == count || frames.is_empty() {
+        return frames;
+    }
+
+    // When each original frame stops being shown, in milliseconds
+    let ends = frames
+        .iter()
+        .scan(0, |time, frame| {
+            *time += u64::from(frame.delay());
+            Some(*time)
+        })
+        .collect::<Vec<_>>();
+    let duration = ends[ends.len() - 1];
+
+    // When each new frame starts being shown, which is never later than
+    // `duration`
+    let start = |index: usize| {
+        let time = (index as u128 * u128::from(duration) + count as u128 / 2) / count as u128;
+        time as u64
+    };
+
+    let sources = (0..count)
+        .map(|index| {
+            if duration == 0 {
+                return index * frames.len() / count;
+            }
+
+            let time = start(index);
+            ends.partition_point(|&end| end <= time)
+                .min(frames.len() - 1)
+        })
+        .collect::<Vec<_>>();
+
+    let mut frames = frames.into_iter().map(Some).collect::<Vec<_>>();
+    let mut res = Vec::with_capacity(count);
+
+    for (index, &source) in sources.iter().enumerate() {
+        // Frames are only cloned if they're used again
+        let mut frame = match sources.get(index + 1) == Some(&source) {
+            true => frames[source].clone(),
+            false => frames[source].take(),
+        }
+        .expect("frames should be used in order");
+
+        let delay = start(index + 1) - start(index);
+        frame.set_delay(u32::try_from(delay).unwrap_or(u32::MAX));
+        res.push(frame);
+    }
+
+    res
+}
+
+/// Merges runs of identical frames in an animation into a single frame,
+/// whose delay is the sum of theirs. This makes the file smaller without
+/// changing how it looks.
+///
+/// Frames are only merged if their pixels, dimensions, and hotspots are
+/// all the same.
+///
+/// ```
+/// use resize_xcursor::{animation::merge_identical_frames, Image};
+///
+/// let frames = [0, 0, 1, 0]
+///     .into_iter()
+///     .map(|pixel| Image::new(1, 1, 1, 0, 0, 50, vec![pixel]))
+///     .collect::<Result<Vec<_>, _>>()?;
+///
+/// let merged = merge_identical_frames(frames);
+/// let delays = merged.iter().map(Image::delay).collect::<Vec<_>>();
+/// assert_eq!(delays, [100, 50, 50]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn merge_identical_frames(frames: Vec<Image>) -> Vec<Image> {
+    let mut res = Vec::<Image>::with_capacity(frames.len());
+
+    for frame in frames {
+        match res.last_mut() {
+            Some(last) if same_image(last, &frame) => {
+                last.set_delay(last.delay().saturating_add(frame.delay()));
+            }
+
+            _ => res.push(frame),
+        }
+    }
+
+    res
+}
+
+/// Like [`merge_identical_frames`], but for every animation in a parsed
+/// Xcursor at once. Each nominal size is treated as a separate animation,
+/// even if its frames are interleaved with other sizes. Other chunks are
+/// kept as they are.
+///
+/// Since this only compares the images' pixels without copying them, it's
+/// much cheaper to merge frames this way before resizing them.
+pub fn merge_identical_chunks<'a>(
+    cursor_chunks: impl IntoIterator<Item = Chunk<'a>>,
+) -> Vec<Chunk<'a>> {
+    let mut res = Vec::new();
+    // The index in `res` of the last frame of each nominal size
+    let mut last_frames = HashMap::new();
+
+    for chunk in cursor_chunks {
+        let Chunk::Image(image) = chunk else {
+            res.push(chunk);
+            continue;
+        };
+
+        if let Some(&index) = last_frames.get(&image.info.size()) {
+            if let Chunk::Image(last) = &mut res[index] {
+                if same_parsed_image(last, &image) {
+                    last.info.delay = last.info.delay.saturating_add(image.info.delay);
+                    continue;
+                }
+            }
+        }
+
+        last_frames.insert(image.info.size(), res.len());
+        res.push(Chunk::Image(image));
+    }
+
+    res
+}
+
+/// Returns whether two frames would look the same.
+fn same_image(a: &Image, b: &Image) -> bool {
+    a.layout() == b.layout()
+        && (a.xhot(), a.yhot()) == (b.xhot(), b.yhot())
+        && a.pixels() == b.pixels()
+}
+
+/// Returns whether two parsed frames would look the same.
+fn same_parsed_image(a: &ParsedImage, b: &ParsedImage) -> bool {
+    a.info.layout() == b.info.layout()
+        && (a.info.xhot, a.info.yhot) == (b.info.xhot, b.info.yhot)
+        && a.pixel_bytes == b.pixel_bytes
+}
+
+/// Turns an animation into a ping-pong loop, which plays forwards and then
+/// backwards. Each frame keeps its delay.
+///
+/// The first and last frames aren't repeated, so that they aren't shown
+/// for twice as long when the animation loops.
+///
+/// ```
+/// use resize_xcursor::{animation::ping_pong_frames, Image};
+///
+/// let frames = (0..4)
+///     .map(|index| Image::new(1, 1, 1, 0, 0, 50, vec![index]))
+///     .collect::<Result<Vec<_>, _>>()?;
+///
+/// let ping_pong = ping_pong_frames(frames);
+/// let pixels = ping_pong.iter().map(|frame| frame.pixels()[0]).collect::<Vec<_>>();
+/// assert_eq!(pixels, [0, 1, 2, 3, 2, 1]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn ping_pong_frames(mut frames: Vec<Image>) -> Vec<Image> {
+    if frames.len() <= 2 {
+        return frames;
+    }
+
+    let mirrored = frames[1..frames.len() - 1]
+        .iter()
+        .rev()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    frames.extend(mirrored);
+    frames
+}
+
+/// How [`pulse_frames`] changes an image over the course of its animation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Pulse {
+    /// Fades the image out and back in.
+    Fade,
+    /// Shrinks the image towards its hotspot and grows it back, without
+    /// changing its dimensions.
+    Scale,
+}
+
+/// Generates a looping animation from a single image, by fading or shrinking
+/// it and then bringing it back over `count` frames.
+///
+/// `depth` is how far the image fades or shrinks at the middle of the loop,
+/// from 0 (not at all) to 1 (until it disappears). The first frame is always
+/// the original image, and every frame keeps its delay.
+///
+/// ```
+/// use resize_xcursor::{
+///     animation::{pulse_frames, Pulse},
+///     Image,
+/// };
+///
+/// let image = Image::new(1, 1, 1, 0, 0, 50, vec![0xFF00_0000])?;
+/// let frames = pulse_frames(&image, 4, 1.0, Pulse::Fade)?;
+///
+/// let pixels = frames.iter().map(|frame| frame.pixels()[0]).collect::<Vec<_>>();
+/// assert_eq!(pixels, [0xFF00_0000, 0x8000_0000, 0, 0x8000_0000]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn pulse_frames(image: &Image, count: usize, depth: f32, pulse: Pulse) -> Result<Vec<Image>> {
+    let depth = depth.clamp(0.0, 1.0);
+
+    (0..count)
+        .map(|index| {
+            // Eases from 0 at the start of the loop to 1 in the middle and
+            // back, so the animation loops smoothly
+            let phase = index as f32 / count as f32 * std::f32::consts::TAU;
+            let factor = 1.0 - depth * (1.0 - phase.cos()) / 2.0;
+
+            match pulse {
+                Pulse::Fade => Opacity::new(factor).apply(image.clone()),
+                Pulse::Scale => Ok(scale_around_hotspot(image, factor)),
+            }
+        })
+        .collect()
+}
+
+/// Scales an image's contents by `factor` with nearest-neighbor sampling,
+/// keeping its hotspot in place and its dimensions the same.
+fn scale_around_hotspot(image: &Image, factor: f32) -> Image {
+    let mut res = image.clone();
+
+    if factor >= 1.0 {
+        return res;
+    }
+
+    let (width, height) = (image.width(), image.height());
+
+    // Measured from pixel centers, so that the hotspot's pixel stays put
+    let source = |coordinate: u32, hot: u32, length: u32| {
+        let hot = hot as f32 + 0.5;
+        let position = hot + (coordinate as f32 + 0.5 - hot) / factor;
+        (position >= 0.0 && position < length as f32).then_some(position as u32)
+    };
+
+    for y in 0..height {
+        let source_y = source(y, image.yhot(), height);
+
+        for x in 0..width {
+            let pixel = match (source(x, image.xhot(), width), source_y) {
+                (Some(source_x), Some(source_y)) => {
+                    image.pixels()[(source_y * width + source_x) as usize]
+                }
+                _ => 0,
+            };
+
+            res.pixels_mut()[(y * width + x) as usize] = pixel;
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames whose only pixel is their index, so that the frames picked by
+    /// [`retarget_frames`] can be told apart.
+    fn frames(delays: &[u32]) -> Vec<Image> {
+        delays
+            .iter()
+            .zip(0..)
+            .map(|(&delay, index)| Image::new(1, 1, 1, 0, 0, delay, vec![index]).unwrap())
+            .collect()
+    }
+
+    /// The original index and delay of each retargeted frame.
+    fn retargeted(delays: &[u32], count: usize) -> Vec<(u32, u32)> {
+        retarget_frames(frames(delays), count)
+            .iter()
+            .map(|frame| (frame.pixels()[0], frame.delay()))
+            .collect()
+    }
+
+    #[test]
+    fn retarget_picks_frame_shown_at_start() {
+        assert_eq!(retargeted(&[50; 6], 3), [(0, 100), (2, 100), (4, 100)]);
+        assert_eq!(
+            retargeted(&[50; 2], 5),
+            [(0, 20), (0, 20), (0, 20), (1, 20), (1, 20)]
+        );
+
+        // Long frames are picked more often than short ones, which can be
+        // dropped entirely
+        assert_eq!(retargeted(&[10, 10, 80], 2), [(0, 50), (2, 50)]);
+        assert_eq!(retargeted(&[80, 10, 10], 2), [(0, 50), (0, 50)]);
+        assert_eq!(
+            retargeted(&[30, 30, 240], 4),
+            [(0, 75), (2, 75), (2, 75), (2, 75)]
+        );
+    }
+
+    #[test]
+    fn retarget_without_delays() {
+        // Frames are picked evenly by index instead
+        assert_eq!(retargeted(&[0; 4], 2), [(0, 0), (2, 0)]);
+        assert_eq!(retargeted(&[0; 2], 4), [(0, 0), (0, 0), (1, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn retarget_to_same_count() {
+        assert_eq!(retargeted(&[10, 20, 30], 3), [(0, 10), (1, 20), (2, 30)]);
+        assert_eq!(retargeted(&[10, 20], 0), []);
+    }
+}
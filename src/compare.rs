@@ -0,0 +1,203 @@
+//! Implementation of `compare`.
+
+use crate::edit::read_cursor;
+use anyhow::bail;
+use clap::Args;
+use resize_xcursor::{Image, XcursorBuilder};
+use std::{collections::BTreeSet, path::PathBuf};
+
+#[derive(Args)]
+pub(crate) struct CompareArgs {
+    /// The first Xcursor file to compare.
+    #[clap(value_parser)]
+    a: PathBuf,
+
+    /// The second Xcursor file to compare.
+    #[clap(value_parser)]
+    b: PathBuf,
+
+    /// Writes a PNG to this directory for each frame whose pixels differ,
+    /// named after its nominal size and frame index (such as `24-0.png`).
+    ///
+    /// Pixels that differ are shown in magenta, on top of a faded copy of
+    /// the first cursor's frame.
+    #[cfg(feature = "png")]
+    #[clap(long, value_name = "DIR", value_parser, verbatim_doc_comment)]
+    diff_dir: Option<PathBuf>,
+}
+
+/// How much two frames' pixels differ.
+struct PixelDiff {
+    /// The number of pixels that aren't identical.
+    differing: usize,
+    /// The largest difference between any channel of two pixels.
+    max: u8,
+    /// The average difference between each channel of two pixels.
+    mean: f64,
+}
+
+pub(crate) fn run(args: CompareArgs) -> anyhow::Result<()> {
+    let a = read_cursor(&args.a)?;
+    let b = read_cursor(&args.b)?;
+    let mut differs = false;
+
+    let sizes = a
+        .sizes()
+        .into_iter()
+        .chain(b.sizes())
+        .collect::<BTreeSet<_>>();
+
+    for size in sizes {
+        let frames = |cursor: &XcursorBuilder| {
+            cursor
+                .images()
+                .iter()
+                .filter(|image| image.size() == size)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let (a_frames, b_frames) = (frames(&a), frames(&b));
+
+        if a_frames.len() != b_frames.len() {
+            println!(
+                "size {size}: {} frames in {}, {} in {}",
+                a_frames.len(),
+                args.a.display(),
+                b_frames.len(),
+                args.b.display()
+            );
+            differs = true;
+        }
+
+        // Frames are matched up by their index in each animation
+        for (index, (a_frame, b_frame)) in a_frames.iter().zip(&b_frames).enumerate() {
+            let mut difference = |what: &str, a_value: String, b_value: String| {
+                println!(
+                    "size {size}, frame {index}: {what} is {a_value} in {}, {b_value} in {}",
+                    args.a.display(),
+                    args.b.display()
+                );
+                differs = true;
+            };
+
+            let dimensions = |frame: &Image| format!("{}x{}", frame.width(), frame.height());
+            let hotspot = |frame: &Image| format!("{},{}", frame.xhot(), frame.yhot());
+
+            if dimensions(a_frame) != dimensions(b_frame) {
+                difference("dimensions", dimensions(a_frame), dimensions(b_frame));
+            }
+
+            if hotspot(a_frame) != hotspot(b_frame) {
+                difference("hotspot", hotspot(a_frame), hotspot(b_frame));
+            }
+
+            if a_frame.delay() != b_frame.delay() {
+                let delay = |frame: &Image| format!("{}ms", frame.delay());
+                difference("delay", delay(a_frame), delay(b_frame));
+            }
+
+            // Pixels can only be compared one to one if the frames are the
+            // same size
+            if a_frame.layout() != b_frame.layout() {
+                continue;
+            }
+
+            let diff = pixel_diff(a_frame, b_frame);
+
+            if diff.differing == 0 {
+                continue;
+            }
+
+            println!(
+                "size {size}, frame {index}: {} of {} pixels differ (largest difference {}, average {:.3})",
+                diff.differing,
+                a_frame.pixels().len(),
+                diff.max,
+                diff.mean
+            );
+            differs = true;
+
+            #[cfg(feature = "png")]
+            if let Some(diff_dir) = &args.diff_dir {
+                write_diff(
+                    &diff_dir.join(format!("{size}-{index}.png")),
+                    a_frame,
+                    b_frame,
+                )?;
+            }
+        }
+    }
+
+    if a.raw_chunks() != b.raw_chunks() {
+        println!("comments or other chunks differ");
+        differs = true;
+    }
+
+    if differs {
+        bail!("the cursors differ");
+    }
+
+    println!("the cursors are identical");
+    Ok(())
+}
+
+/// Compares the pixels of two frames with the same dimensions.
+fn pixel_diff(a: &Image, b: &Image) -> PixelDiff {
+    let mut diff = PixelDiff {
+        differing: 0,
+        max: 0,
+        mean: 0.0,
+    };
+    let mut total = 0u64;
+
+    for (&a_pixel, &b_pixel) in a.pixels().iter().zip(b.pixels()) {
+        if a_pixel == b_pixel {
+            continue;
+        }
+
+        diff.differing += 1;
+
+        for (a_channel, b_channel) in a_pixel.to_le_bytes().into_iter().zip(b_pixel.to_le_bytes()) {
+            let channel_diff = a_channel.abs_diff(b_channel);
+            diff.max = diff.max.max(channel_diff);
+            total += u64::from(channel_diff);
+        }
+    }
+
+    let channels = a.pixels().len() * 4;
+
+    if channels > 0 {
+        diff.mean = total as f64 / channels as f64;
+    }
+
+    diff
+}
+
+/// Writes a PNG highlighting the pixels that differ between two frames.
+#[cfg(feature = "png")]
+fn write_diff(path: &std::path::Path, a: &Image, b: &Image) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    const HIGHLIGHT: u32 = 0xFFFF00FF;
+
+    let pixels = a
+        .pixels()
+        .iter()
+        .zip(b.pixels())
+        .map(|(&a_pixel, &b_pixel)| match a_pixel == b_pixel {
+            // Fading a premultiplied pixel means scaling every channel
+            true => u32::from_le_bytes(a_pixel.to_le_bytes().map(|channel| channel / 4)),
+            false => HIGHLIGHT,
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("couldn't create {}", parent.display()))?;
+    }
+
+    let png = crate::png::encode(a.width(), a.height(), &pixels)?;
+    crate::output::write_bytes(path, png)
+        .with_context(|| format!("couldn't write {}", path.display()))
+}
@@ -0,0 +1,421 @@
+//! Reading the kind and details of a lint finding out of a problem's
+//! `Serialize` impl, so that reports use the same names as every other
+//! serialized form of it.
+//!
+//! Problems are flattened: the name of the enum variant they hold (such as
+//! a [`Violation`](resize_xcursor::validation::Violation)'s `kind`) becomes
+//! the kind, and every number and string in them becomes a detail, named
+//! after its field. The value of a newtype variant, such as
+//! `trailing_bytes(3)`, is named `value`.
+
+use serde::{
+    ser::{self, Impossible},
+    Serialize,
+};
+use std::fmt::{self, Display};
+
+/// A value in a finding's details.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Detail {
+    Number(u64),
+    String(String),
+}
+
+/// The fields of a finding's details, in the order they're written.
+pub(crate) type Details = Vec<(&'static str, Detail)>;
+
+/// Why a problem couldn't be described.
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Returns the name of the variant that `problem` holds, and its details.
+///
+/// This fails if `problem` doesn't hold exactly one enum variant, if it has
+/// two details with the same name, or if it holds anything other than
+/// numbers, strings, structs, and enums.
+pub(crate) fn describe(problem: &impl Serialize) -> Result<(&'static str, Details), Error> {
+    let mut collector = Collector::default();
+    problem.serialize(ValueSerializer {
+        collector: &mut collector,
+        key: "value",
+    })?;
+
+    let kind = collector
+        .kind
+        .ok_or_else(|| Error("problem doesn't hold an enum variant".to_owned()))?;
+    Ok((kind, collector.details))
+}
+
+#[derive(Default)]
+struct Collector {
+    kind: Option<&'static str>,
+    details: Details,
+}
+
+impl Collector {
+    fn set_kind(&mut self, variant: &'static str) -> Result<(), Error> {
+        match self.kind.replace(variant) {
+            Some(kind) => Err(Error(format!(
+                "problem holds both `{kind}` and `{variant}`"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn push(&mut self, key: &'static str, detail: Detail) -> Result<(), Error> {
+        if self.details.iter().any(|&(other, _)| other == key) {
+            return Err(Error(format!("problem has more than one `{key}`")));
+        }
+
+        self.details.push((key, detail));
+        Ok(())
+    }
+}
+
+/// Adds a single value to a [`Collector`], as the detail named `key` if it's
+/// a number or a string.
+struct ValueSerializer<'a> {
+    collector: &'a mut Collector,
+    key: &'static str,
+}
+
+impl ValueSerializer<'_> {
+    fn unsupported<T>(what: &str) -> Result<T, Error> {
+        Err(Error(format!("problems can't hold {what}")))
+    }
+}
+
+impl<'a> ser::Serializer for ValueSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = StructSerializer<'a>;
+    type SerializeStructVariant = StructSerializer<'a>;
+
+    fn serialize_bool(self, _: bool) -> Result<(), Error> {
+        Self::unsupported("booleans")
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<(), Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<(), Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<(), Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<(), Error> {
+        match u64::try_from(value) {
+            Ok(value) => self.serialize_u64(value),
+            Err(_) => Self::unsupported("negative numbers"),
+        }
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<(), Error> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<(), Error> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<(), Error> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<(), Error> {
+        self.collector.push(self.key, Detail::Number(value))
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<(), Error> {
+        Self::unsupported("floating-point numbers")
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<(), Error> {
+        Self::unsupported("floating-point numbers")
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), Error> {
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), Error> {
+        self.collector
+            .push(self.key, Detail::String(value.to_owned()))
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), Error> {
+        Self::unsupported("bytes")
+    }
+
+    // Missing values are left out
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.collector.set_kind(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.collector.set_kind(variant)?;
+        value.serialize(ValueSerializer {
+            collector: self.collector,
+            key: "value",
+        })
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Self::unsupported("sequences")
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+        Self::unsupported("tuples")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Self::unsupported("tuples")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Self::unsupported("tuples")
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Self::unsupported("maps")
+    }
+
+    // The fields of structs are added as if they were the fields of
+    // whatever holds them
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Error> {
+        Ok(StructSerializer {
+            collector: self.collector,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        self.collector.set_kind(variant)?;
+        Ok(StructSerializer {
+            collector: self.collector,
+        })
+    }
+}
+
+/// Adds each field of a struct to a [`Collector`].
+struct StructSerializer<'a> {
+    collector: &'a mut Collector,
+}
+
+impl ser::SerializeStruct for StructSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(ValueSerializer {
+            collector: self.collector,
+            key,
+        })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resize_xcursor::{
+        validation::{SizeMismatch, Violation, ViolationKind},
+        ParseErrorKind,
+    };
+
+    fn violation(kind: ViolationKind) -> (&'static str, Details) {
+        describe(&Violation { offset: 16, kind }).unwrap()
+    }
+
+    #[test]
+    fn unit_variant() {
+        assert_eq!(
+            violation(ViolationKind::BadMagic),
+            ("bad_magic", vec![("offset", Detail::Number(16))])
+        );
+    }
+
+    #[test]
+    fn newtype_variant() {
+        assert_eq!(
+            violation(ViolationKind::TrailingBytes(3)),
+            (
+                "trailing_bytes",
+                vec![("offset", Detail::Number(16)), ("value", Detail::Number(3))]
+            )
+        );
+
+        // Structs in newtype variants are flattened
+        let mismatch = SizeMismatch {
+            size: 24,
+            actual_size: 32,
+        };
+        assert_eq!(
+            violation(ViolationKind::SizeMismatch(mismatch)),
+            (
+                "size_mismatch",
+                vec![
+                    ("offset", Detail::Number(16)),
+                    ("size", Detail::Number(24)),
+                    ("actual_size", Detail::Number(32)),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn struct_variant() {
+        assert_eq!(
+            violation(ViolationKind::ChunksOverlap { index: 0, other: 1 }),
+            (
+                "chunks_overlap",
+                vec![
+                    ("offset", Detail::Number(16)),
+                    ("index", Detail::Number(0)),
+                    ("other", Detail::Number(1)),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            describe(&ParseErrorKind::UnexpectedEof("an image")).unwrap(),
+            (
+                "unexpected_eof",
+                vec![("value", Detail::String("an image".to_owned()))]
+            )
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        #[derive(Serialize)]
+        struct NoKind {
+            offset: u64,
+        }
+
+        #[derive(Serialize)]
+        enum Kind {
+            A,
+            B,
+        }
+
+        #[derive(Serialize)]
+        struct TwoKinds {
+            first: Kind,
+            second: Kind,
+        }
+
+        #[derive(Serialize)]
+        enum Sequence {
+            Sizes(Vec<u32>),
+        }
+
+        assert!(describe(&NoKind { offset: 0 }).is_err());
+        assert!(describe(&TwoKinds {
+            first: Kind::A,
+            second: Kind::B,
+        })
+        .is_err());
+        assert!(describe(&Sequence::Sizes(vec![1])).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Implementation of `edit add-size`.
+
+use super::{check_hotspot, edit_cursor, parse_pair, rescale};
+use crate::png;
+use anyhow::{bail, Context};
+use clap::Args;
+use resize_xcursor::Image;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub(crate) struct AddSizeArgs {
+    /// The Xcursor file to edit.
+    #[clap(value_parser)]
+    cursor: PathBuf,
+
+    /// The PNG to add.
+    #[clap(long, value_parser)]
+    png: PathBuf,
+
+    /// The nominal size of the new image.
+    #[clap(long)]
+    size: u32,
+
+    /// The new image's hotspot, as `X,Y`.
+    #[clap(long, value_name = "X,Y", value_parser = parse_pair::<u32>)]
+    hotspot: Option<(u32, u32)>,
+
+    /// The number of milliseconds to show the new image for, if the cursor
+    /// is animated.
+    #[clap(long, value_name = "MS", default_value_t = 0, verbatim_doc_comment)]
+    delay: u32,
+
+    /// Where to write the edited cursor, instead of replacing the original.
+    /// `-` writes it to stdout.
+    #[clap(short, long, value_parser, verbatim_doc_comment)]
+    output: Option<PathBuf>,
+}
+
+pub(super) fn run(args: AddSizeArgs) -> anyhow::Result<()> {
+    let png = png::read(&args.png)?;
+
+    edit_cursor(&args.cursor, args.output.as_deref(), &[], |cursor| {
+        if cursor.sizes().contains(&args.size) {
+            bail!(
+                "{} already has images with a nominal size of {}",
+                args.cursor.display(),
+                args.size
+            );
+        }
+
+        let (xhot, yhot) = match args.hotspot {
+            Some(hotspot) => hotspot,
+            None => {
+                let closest = cursor
+                    .images()
+                    .iter()
+                    .min_by_key(|image| image.size().abs_diff(args.size))
+                    .context("the cursor has no images to take a hotspot from (use `--hotspot`)")?;
+
+                (
+                    rescale(closest.xhot(), closest.width(), png.width),
+                    rescale(closest.yhot(), closest.height(), png.height),
+                )
+            }
+        };
+
+        let image = Image::new(
+            args.size, png.width, png.height, xhot, yhot, args.delay, png.pixels,
+        )?;
+        check_hotspot(&image)?;
+        cursor.add_image(image)?;
+        Ok(())
+    })
+}
@@ -0,0 +1,50 @@
+//! Implementation of `edit delay`.
+
+use super::EditFile;
+use clap::Args;
+use resize_xcursor::{animation::scale_delay, Error};
+
+#[derive(Args)]
+pub(crate) struct DelayArgs {
+    #[clap(flatten)]
+    file: EditFile,
+
+    /// The new delay for each frame, in milliseconds.
+    #[clap(
+        long,
+        value_name = "MS",
+        conflicts_with = "scale-delay",
+        required_unless_present = "scale-delay"
+    )]
+    set: Option<u32>,
+
+    /// Multiplies each frame's delay by this factor, so that `2` makes the
+    /// animation twice as slow and `0.5` makes it twice as fast.
+    #[clap(long, value_name = "FACTOR", value_parser = crate::parse_factor, verbatim_doc_comment)]
+    scale_delay: Option<f64>,
+}
+
+pub(super) fn run(args: DelayArgs) -> anyhow::Result<()> {
+    args.file.edit(|cursor| {
+        for image in cursor.images_mut() {
+            if !args.file.selects(image) {
+                continue;
+            }
+
+            let delay = match (args.set, args.scale_delay) {
+                (Some(delay), _) => delay,
+                (None, Some(factor)) => {
+                    scale_delay(image.delay(), factor).ok_or(Error::DelayOverflow {
+                        delay: image.delay(),
+                        factor,
+                    })?
+                }
+                (None, None) => unreachable!("`clap` requires `--set` or `--scale-delay`"),
+            };
+
+            image.set_delay(delay);
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,47 @@
+//! Implementation of `edit drop-size`.
+
+use super::edit_cursor;
+use anyhow::bail;
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub(crate) struct DropSizeArgs {
+    /// The Xcursor file to edit.
+    #[clap(value_parser)]
+    cursor: PathBuf,
+
+    /// The nominal sizes to remove, separated by commas (for example,
+    /// `64,96`).
+    #[clap(
+        value_name = "SIZES",
+        value_delimiter = ',',
+        required = true,
+        verbatim_doc_comment
+    )]
+    sizes: Vec<u32>,
+
+    /// Where to write the edited cursor, instead of replacing the original.
+    /// `-` writes it to stdout.
+    #[clap(short, long, value_parser, verbatim_doc_comment)]
+    output: Option<PathBuf>,
+}
+
+pub(super) fn run(args: DropSizeArgs) -> anyhow::Result<()> {
+    edit_cursor(
+        &args.cursor,
+        args.output.as_deref(),
+        &args.sizes,
+        |cursor| {
+            if cursor.sizes().iter().all(|size| args.sizes.contains(size)) {
+                bail!("this would remove every image in the cursor");
+            }
+
+            for &size in &args.sizes {
+                cursor.remove_size(size);
+            }
+
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,126 @@
+//! Implementation of `edit fix-size`.
+
+use super::{rescale, EditFile};
+use anyhow::bail;
+use clap::Args;
+use resize_xcursor::{validation::size_mismatches, Frame, Image, NearestNeighbor, Resampler};
+
+#[derive(Args)]
+pub(crate) struct FixSizeArgs {
+    #[clap(flatten)]
+    file: EditFile,
+
+    /// If given, scales each image's pixels to match its nominal size,
+    /// instead of changing its nominal size to match its pixels.
+    ///
+    /// Images are scaled with nearest-neighbor scaling, keeping their aspect
+    /// ratio, so that their larger dimension matches their nominal size.
+    #[clap(long, verbatim_doc_comment)]
+    scale_pixels: bool,
+}
+
+pub(super) fn run(args: FixSizeArgs) -> anyhow::Result<()> {
+    args.file.edit(|cursor| {
+        // Every frame of an animation gets the same nominal size, even if
+        // some are smaller than others
+        let fixes = size_mismatches(cursor.images().iter().map(Image::layout))
+            .into_iter()
+            .filter(|mismatch| args.file.selects_size(mismatch.size))
+            .map(|mismatch| (mismatch.size, mismatch.actual_size))
+            .collect::<Vec<_>>();
+
+        if !args.scale_pixels {
+            let fixed_size = |size| {
+                fixes
+                    .iter()
+                    .find(|&&(old_size, _)| old_size == size)
+                    .map_or(size, |&(_, actual_size)| actual_size)
+            };
+
+            for &(size, actual_size) in &fixes {
+                if let Some(other) = cursor
+                    .sizes()
+                    .into_iter()
+                    .find(|&other| other != size && fixed_size(other) == actual_size)
+                {
+                    bail!("the {size}px images are actually {actual_size}px, which would mix them up with the {other}px images");
+                }
+            }
+        }
+
+        // Fixed images could have the nominal size of images that haven't
+        // been fixed yet, so every image is fixed before any are replaced
+        let images = cursor
+            .images()
+            .iter()
+            .cloned()
+            .map(|image| {
+                let Some(&(_, actual_size)) =
+                    fixes.iter().find(|&&(size, _)| size == image.size())
+                else {
+                    return Ok(image);
+                };
+
+                if args.scale_pixels {
+                    scale_to(image, actual_size)
+                } else {
+                    with_size(image, actual_size)
+                }
+            })
+            .collect::<resize_xcursor::Result<Vec<_>>>()?;
+
+        for size in cursor.sizes() {
+            cursor.remove_size(size);
+        }
+
+        for image in images {
+            cursor.add_image(image)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Changes an image's nominal size.
+fn with_size(image: Image, size: u32) -> resize_xcursor::Result<Image> {
+    Image::new(
+        size,
+        image.width(),
+        image.height(),
+        image.xhot(),
+        image.yhot(),
+        image.delay(),
+        image.into_pixels(),
+    )
+}
+
+/// Scales an image whose larger dimension is `actual_size` pixels so that
+/// its larger dimension matches its nominal size instead, moving its hotspot
+/// to stay in the same relative position.
+fn scale_to(image: Image, actual_size: u32) -> resize_xcursor::Result<Image> {
+    let size = image.size();
+    let scale =
+        |length: u32| (u64::from(length) * u64::from(size) / u64::from(actual_size)).max(1) as u32;
+
+    let (width, height) = (scale(image.width()), scale(image.height()));
+
+    let pixels = NearestNeighbor.resample(
+        Frame {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.pixels(),
+        },
+        width,
+        height,
+    );
+
+    Image::new(
+        image.size(),
+        width,
+        height,
+        rescale(image.xhot(), image.width(), width),
+        rescale(image.yhot(), image.height(), height),
+        image.delay(),
+        pixels,
+    )
+}
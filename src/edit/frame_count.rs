@@ -0,0 +1,21 @@
+//! Implementation of `edit frame-count`.
+
+use super::EditFile;
+use clap::Args;
+use resize_xcursor::animation::retarget_frames;
+
+#[derive(Args)]
+pub(crate) struct FrameCountArgs {
+    #[clap(flatten)]
+    file: EditFile,
+
+    /// The number of frames each animation should have.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    count: u32,
+}
+
+pub(super) fn run(args: FrameCountArgs) -> anyhow::Result<()> {
+    let count = usize::try_from(args.count)?;
+    args.file
+        .edit_animations(|frames| Ok(retarget_frames(frames, count)))
+}
@@ -0,0 +1,63 @@
+//! Implementation of `edit hotspot`.
+
+use super::{check_hotspot, parse_pair, EditFile};
+use anyhow::bail;
+use clap::Args;
+
+#[derive(Args)]
+pub(crate) struct HotspotArgs {
+    #[clap(flatten)]
+    file: EditFile,
+
+    /// The new hotspot, as `X,Y`.
+    #[clap(
+        long,
+        value_name = "X,Y",
+        value_parser = parse_pair::<u32>,
+        conflicts_with = "offset",
+        required_unless_present = "offset"
+    )]
+    set: Option<(u32, u32)>,
+
+    /// How far to move the hotspot, as `DX,DY`. Either can be negative.
+    #[clap(
+        long,
+        value_name = "DX,DY",
+        value_parser = parse_pair::<i64>,
+        allow_hyphen_values = true
+    )]
+    offset: Option<(i64, i64)>,
+}
+
+pub(super) fn run(args: HotspotArgs) -> anyhow::Result<()> {
+    args.file.edit(|cursor| {
+        for image in cursor.images_mut() {
+            if !args.file.selects(image) {
+                continue;
+            }
+
+            let (xhot, yhot) = match (args.set, args.offset) {
+                (Some(hotspot), _) => hotspot,
+                (None, Some((dx, dy))) => {
+                    let x = i64::from(image.xhot()) + dx;
+                    let y = i64::from(image.yhot()) + dy;
+
+                    match (u32::try_from(x), u32::try_from(y)) {
+                        (Ok(x), Ok(y)) => (x, y),
+                        _ => bail!(
+                            "moving the hotspot of the {}px image would put it at {x},{y}, \
+                            outside of the image",
+                            image.size()
+                        ),
+                    }
+                }
+                (None, None) => unreachable!("`clap` requires `--set` or `--offset`"),
+            };
+
+            image.set_hotspot(xhot, yhot);
+            check_hotspot(image)?;
+        }
+
+        Ok(())
+    })
+}
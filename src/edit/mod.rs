@@ -0,0 +1,297 @@
+//! Subcommands that edit a single Xcursor file in place, without resizing it.
+
+#[cfg(feature = "png")]
+mod add_size;
+mod delay;
+mod drop_size;
+mod fix_size;
+mod frame_count;
+mod hotspot;
+mod optimize_frames;
+mod pulse;
+#[cfg(feature = "png")]
+mod replace;
+mod reverse;
+mod splice;
+mod take;
+
+use crate::{input, output};
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, Subcommand};
+use resize_xcursor::{Image, XcursorBuilder};
+use std::{
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+#[derive(Subcommand)]
+pub(crate) enum EditCommand {
+    /// Moves the hotspot of every image, or of the images with the given
+    /// nominal sizes.
+    ///
+    /// Either `--set` or `--offset` must be given. This fails if a hotspot
+    /// would end up outside of its image.
+    #[clap(verbatim_doc_comment)]
+    Hotspot(hotspot::HotspotArgs),
+
+    /// Changes the delay of every frame, or of the frames with the given
+    /// nominal sizes.
+    ///
+    /// Either `--set` or `--scale-delay` must be given.
+    #[clap(verbatim_doc_comment)]
+    Delay(delay::DelayArgs),
+
+    /// Resamples each animation to the given number of frames.
+    ///
+    /// Frames are dropped or duplicated evenly, and the animation's total
+    /// duration is kept by spreading it evenly across the new frames. Each
+    /// nominal size is resampled separately.
+    #[clap(verbatim_doc_comment)]
+    FrameCount(frame_count::FrameCountArgs),
+
+    /// Merges runs of identical frames in each animation into a single,
+    /// longer frame.
+    ///
+    /// This makes the file smaller without changing how it looks.
+    #[clap(verbatim_doc_comment)]
+    OptimizeFrames(optimize_frames::OptimizeFramesArgs),
+
+    /// Reverses the order of each animation's frames.
+    ///
+    /// Each frame keeps its delay.
+    #[clap(verbatim_doc_comment)]
+    Reverse(reverse::ReverseArgs),
+
+    /// Turns each animation into a loop that plays forwards, then backwards.
+    ///
+    /// The frames are played in reverse after the last frame, without
+    /// repeating the first or last frame. Each frame keeps its delay.
+    #[clap(verbatim_doc_comment)]
+    Pingpong(reverse::PingpongArgs),
+
+    /// Turns a static cursor into a looping animation that fades out and
+    /// back in, or shrinks and grows back, such as for a busy cursor.
+    ///
+    /// The image of each nominal size becomes the first frame of its
+    /// animation. Cursors that are already animated can't be pulsed.
+    #[clap(verbatim_doc_comment)]
+    Pulse(pulse::PulseArgs),
+
+    /// Inserts the frames of another cursor into each animation.
+    ///
+    /// Frames are inserted separately for each nominal size, so the other
+    /// cursor needs images with each nominal size being edited. They keep
+    /// their own delays and hotspots.
+    #[clap(verbatim_doc_comment)]
+    Splice(splice::SpliceArgs),
+
+    /// Writes a new cursor containing only the images with the given
+    /// nominal sizes, or only one frame of each animation.
+    ///
+    /// Comments and other chunks are kept. Since this throws away every
+    /// other image, an output filename has to be given.
+    #[clap(verbatim_doc_comment)]
+    Take(take::TakeArgs),
+
+    /// Removes every image with the given nominal sizes.
+    #[clap(verbatim_doc_comment)]
+    DropSize(drop_size::DropSizeArgs),
+
+    /// Changes the nominal size of images that don't match their
+    /// dimensions, such as 32x32 images that claim to be 24 pixels.
+    ///
+    /// Each image's nominal size is set to its larger dimension, unless
+    /// `--scale-pixels` is given. Every frame of an animation is given the
+    /// same nominal size.
+    #[clap(verbatim_doc_comment)]
+    FixSize(fix_size::FixSizeArgs),
+
+    /// Adds a new nominal size to a cursor, using an image from a PNG.
+    ///
+    /// Existing images and chunks are left as they are. If `--hotspot`
+    /// isn't given, the hotspot is placed in the same relative position as
+    /// in the closest existing size.
+    #[cfg(feature = "png")]
+    #[clap(verbatim_doc_comment)]
+    AddSize(add_size::AddSizeArgs),
+
+    /// Replaces the image of an existing nominal size with an image from
+    /// a PNG.
+    ///
+    /// The image keeps its delay and hotspot unless `--delay` or `--hotspot`
+    /// are given. If the PNG has different dimensions, the hotspot is moved
+    /// to stay in the same relative position. For animated cursors, `--frame`
+    /// picks which frame to replace.
+    #[cfg(feature = "png")]
+    #[clap(verbatim_doc_comment)]
+    Replace(replace::ReplaceArgs),
+}
+
+impl EditCommand {
+    pub(crate) fn run(self) -> anyhow::Result<()> {
+        match self {
+            Self::Hotspot(args) => hotspot::run(args),
+            Self::Delay(args) => delay::run(args),
+            Self::FrameCount(args) => frame_count::run(args),
+            Self::OptimizeFrames(args) => optimize_frames::run(args),
+            Self::Reverse(args) => reverse::run_reverse(args),
+            Self::Pingpong(args) => reverse::run_pingpong(args),
+            Self::Pulse(args) => pulse::run(args),
+            Self::Splice(args) => splice::run(args),
+            Self::Take(args) => take::run(args),
+            Self::DropSize(args) => drop_size::run(args),
+            Self::FixSize(args) => fix_size::run(args),
+            #[cfg(feature = "png")]
+            Self::AddSize(args) => add_size::run(args),
+            #[cfg(feature = "png")]
+            Self::Replace(args) => replace::run(args),
+        }
+    }
+}
+
+/// The file to edit, and which of its images to change.
+#[derive(Args)]
+struct EditFile {
+    /// The Xcursor file to edit.
+    #[clap(value_parser)]
+    cursor: PathBuf,
+
+    /// Where to write the edited cursor, instead of replacing the original.
+    /// `-` writes it to stdout.
+    #[clap(short, long, value_parser, verbatim_doc_comment)]
+    output: Option<PathBuf>,
+
+    /// Only edits the images with this nominal size. Can be given more than
+    /// once. By default, every image is edited.
+    #[clap(long = "size", value_name = "SIZE", verbatim_doc_comment)]
+    sizes: Vec<u32>,
+}
+
+impl EditFile {
+    /// Returns whether the image should be edited.
+    fn selects(&self, image: &Image) -> bool {
+        self.selects_size(image.size())
+    }
+
+    /// Returns whether images with the given nominal size should be edited.
+    fn selects_size(&self, size: u32) -> bool {
+        self.sizes.is_empty() || self.sizes.contains(&size)
+    }
+
+    /// Reads the cursor, lets `edit` change it, then writes it out again.
+    fn edit(
+        &self,
+        edit: impl FnOnce(&mut XcursorBuilder) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        edit_cursor(&self.cursor, self.output.as_deref(), &self.sizes, edit)
+    }
+
+    /// Like [`EditFile::edit`], but calls `edit` with the frames of each
+    /// selected nominal size in turn, replacing them with the frames it returns.
+    fn edit_animations(
+        &self,
+        mut edit: impl FnMut(Vec<Image>) -> anyhow::Result<Vec<Image>>,
+    ) -> anyhow::Result<()> {
+        self.edit(|cursor| {
+            for size in cursor.sizes() {
+                if !self.selects_size(size) {
+                    continue;
+                }
+
+                let frames = cursor
+                    .images()
+                    .iter()
+                    .filter(|image| image.size() == size)
+                    .cloned()
+                    .collect();
+
+                cursor.replace_size(size, edit(frames)?)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Reads `cursor`, lets `edit` change it, then writes it to `output` (or
+/// back to `cursor`). Fails without changing anything if `cursor` doesn't
+/// have images with each of the given nominal sizes.
+fn edit_cursor(
+    cursor_filename: &Path,
+    output: Option<&Path>,
+    sizes: &[u32],
+    edit: impl FnOnce(&mut XcursorBuilder) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut cursor = read_cursor(cursor_filename)?;
+
+    if let Some(&size) = sizes.iter().find(|size| !cursor.sizes().contains(size)) {
+        bail!(
+            "{} has no images with a nominal size of {size}",
+            cursor_filename.display()
+        );
+    }
+
+    edit(&mut cursor)?;
+
+    let output = output.unwrap_or(cursor_filename);
+
+    if output::is_stdout(output) && io::stdout().is_terminal() {
+        bail!("refusing to write a cursor to a terminal (redirect stdout to a file instead)");
+    }
+
+    output::write(output, |output| cursor.write_to(output))
+        .with_context(|| format!("couldn't write {}", output.display()))
+}
+
+/// Reads and parses an Xcursor file.
+pub(crate) fn read_cursor(cursor_filename: &Path) -> anyhow::Result<XcursorBuilder> {
+    let cursor_bytes = input::read(cursor_filename)
+        .with_context(|| format!("couldn't read {}", cursor_filename.display()))?;
+
+    XcursorBuilder::parse(&cursor_bytes).map_err(|err| {
+        anyhow!(
+            "{} isn't a valid Xcursor file: {err}",
+            cursor_filename.display()
+        )
+    })
+}
+
+/// Makes sure that an image's hotspot is inside of it.
+fn check_hotspot(image: &Image) -> anyhow::Result<()> {
+    if image.xhot() >= image.width() || image.yhot() >= image.height() {
+        bail!(
+            "{},{} is outside of the {}px image ({}x{})",
+            image.xhot(),
+            image.yhot(),
+            image.size(),
+            image.width(),
+            image.height()
+        );
+    }
+
+    Ok(())
+}
+
+/// Moves a hotspot coordinate so that it stays in the same relative position
+/// when an image is replaced with one of a different width or height.
+pub(crate) fn rescale(coordinate: u32, old_length: u32, new_length: u32) -> u32 {
+    match old_length {
+        0 => 0,
+        _ => (u64::from(coordinate) * u64::from(new_length) / u64::from(old_length)) as u32,
+    }
+}
+
+/// Parses a pair of numbers separated by a comma, such as `4,4`.
+pub(crate) fn parse_pair<T: FromStr>(s: &str) -> Result<(T, T), String> {
+    let parse = |part: &str| {
+        part.trim()
+            .parse()
+            .map_err(|_| format!("`{part}` isn't a valid number"))
+    };
+
+    match s.split_once(',') {
+        Some((x, y)) => Ok((parse(x)?, parse(y)?)),
+        None => Err("expected two numbers separated by a comma, like `4,4`".to_owned()),
+    }
+}
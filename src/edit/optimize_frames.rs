@@ -0,0 +1,16 @@
+//! Implementation of `edit optimize-frames`.
+
+use super::EditFile;
+use clap::Args;
+use resize_xcursor::animation::merge_identical_frames;
+
+#[derive(Args)]
+pub(crate) struct OptimizeFramesArgs {
+    #[clap(flatten)]
+    file: EditFile,
+}
+
+pub(super) fn run(args: OptimizeFramesArgs) -> anyhow::Result<()> {
+    args.file
+        .edit_animations(|frames| Ok(merge_identical_frames(frames)))
+}
@@ -0,0 +1,79 @@
+//! Implementation of `edit pulse`.
+
+use super::EditFile;
+use anyhow::bail;
+use clap::{Args, ValueEnum};
+use resize_xcursor::animation::{pulse_frames, Pulse};
+
+#[derive(Args)]
+pub(crate) struct PulseArgs {
+    #[clap(flatten)]
+    file: EditFile,
+
+    /// How to animate the cursor.
+    #[clap(long, value_enum, default_value = "fade")]
+    effect: PulseEffect,
+
+    /// The number of frames in each animation.
+    #[clap(
+        long,
+        value_name = "N",
+        default_value = "8",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    frames: u32,
+
+    /// The delay for each frame, in milliseconds.
+    #[clap(long, value_name = "MS", default_value = "100")]
+    delay: u32,
+
+    /// How far the cursor fades or shrinks in the middle of the animation,
+    /// from 0 (not at all) to 1 (until it disappears).
+    #[clap(
+        long,
+        value_name = "AMOUNT",
+        default_value = "0.5",
+        value_parser = crate::parse_amount,
+        verbatim_doc_comment
+    )]
+    depth: f32,
+}
+
+/// A [`Pulse`] that can be picked on the command line.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PulseEffect {
+    /// Fades the cursor out and back in.
+    Fade,
+    /// Shrinks the cursor towards its hotspot and grows it back.
+    Scale,
+}
+
+impl From<PulseEffect> for Pulse {
+    fn from(effect: PulseEffect) -> Self {
+        match effect {
+            PulseEffect::Fade => Pulse::Fade,
+            PulseEffect::Scale => Pulse::Scale,
+        }
+    }
+}
+
+pub(super) fn run(args: PulseArgs) -> anyhow::Result<()> {
+    let count = usize::try_from(args.frames)?;
+
+    args.file.edit_animations(|frames| {
+        let [image] = frames.as_slice() else {
+            bail!(
+                "the {}px images are already animated",
+                frames.first().map_or(0, |frame| frame.size())
+            );
+        };
+
+        let mut frames = pulse_frames(image, count, args.depth, args.effect.into())?;
+
+        for frame in &mut frames {
+            frame.set_delay(args.delay);
+        }
+
+        Ok(frames)
+    })
+}
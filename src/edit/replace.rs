@@ -0,0 +1,96 @@
+//! Implementation of `edit replace`.
+
+use super::{check_hotspot, edit_cursor, parse_pair, rescale};
+use crate::png;
+use anyhow::bail;
+use clap::Args;
+use resize_xcursor::Image;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub(crate) struct ReplaceArgs {
+    /// The Xcursor file to edit.
+    #[clap(value_parser)]
+    cursor: PathBuf,
+
+    /// The nominal size of the image to replace.
+    #[clap(long)]
+    size: u32,
+
+    /// The PNG to replace the image with.
+    #[clap(long, value_parser)]
+    png: PathBuf,
+
+    /// The frame to replace, counting from 0. This is only needed if the
+    /// cursor is animated.
+    #[clap(long, value_name = "INDEX", verbatim_doc_comment)]
+    frame: Option<usize>,
+
+    /// The new image's hotspot, as `X,Y`.
+    #[clap(long, value_name = "X,Y", value_parser = parse_pair::<u32>)]
+    hotspot: Option<(u32, u32)>,
+
+    /// The number of milliseconds to show the new image for.
+    #[clap(long, value_name = "MS")]
+    delay: Option<u32>,
+
+    /// Where to write the edited cursor, instead of replacing the original.
+    /// `-` writes it to stdout.
+    #[clap(short, long, value_parser, verbatim_doc_comment)]
+    output: Option<PathBuf>,
+}
+
+pub(super) fn run(args: ReplaceArgs) -> anyhow::Result<()> {
+    let png = png::read(&args.png)?;
+
+    edit_cursor(
+        &args.cursor,
+        args.output.as_deref(),
+        &[args.size],
+        |cursor| {
+            let mut frames = cursor
+                .images()
+                .iter()
+                .filter(|image| image.size() == args.size)
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let index = match args.frame {
+                Some(index) if index >= frames.len() => bail!(
+                    "the {}px animation only has {} frames, so it has no frame {index}",
+                    args.size,
+                    frames.len()
+                ),
+                Some(index) => index,
+                None if frames.len() > 1 => bail!(
+                    "the {}px images are animated, so `--frame` is needed to pick one",
+                    args.size
+                ),
+                None => 0,
+            };
+
+            let old = &frames[index];
+            let (xhot, yhot) = args.hotspot.unwrap_or_else(|| {
+                (
+                    rescale(old.xhot(), old.width(), png.width),
+                    rescale(old.yhot(), old.height(), png.height),
+                )
+            });
+
+            let image = Image::new(
+                args.size,
+                png.width,
+                png.height,
+                xhot,
+                yhot,
+                args.delay.unwrap_or(old.delay()),
+                png.pixels,
+            )?;
+            check_hotspot(&image)?;
+
+            frames[index] = image;
+            cursor.replace_size(args.size, frames)?;
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,29 @@
+//! Implementation of `edit reverse` and `edit pingpong`.
+
+use super::EditFile;
+use clap::Args;
+use resize_xcursor::animation::ping_pong_frames;
+
+#[derive(Args)]
+pub(crate) struct ReverseArgs {
+    #[clap(flatten)]
+    file: EditFile,
+}
+
+#[derive(Args)]
+pub(crate) struct PingpongArgs {
+    #[clap(flatten)]
+    file: EditFile,
+}
+
+pub(super) fn run_reverse(args: ReverseArgs) -> anyhow::Result<()> {
+    args.file.edit_animations(|mut frames| {
+        frames.reverse();
+        Ok(frames)
+    })
+}
+
+pub(super) fn run_pingpong(args: PingpongArgs) -> anyhow::Result<()> {
+    args.file
+        .edit_animations(|frames| Ok(ping_pong_frames(frames)))
+}
@@ -0,0 +1,53 @@
+//! Implementation of `edit splice`.
+
+use super::{read_cursor, EditFile};
+use anyhow::bail;
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub(crate) struct SpliceArgs {
+    #[clap(flatten)]
+    file: EditFile,
+
+    /// The cursor whose frames should be inserted.
+    #[clap(long, value_name = "CURSOR", value_parser)]
+    from: PathBuf,
+
+    /// Where to insert the frames in each animation, counting from 0. For
+    /// example, `--at 0` inserts them before the first frame. Defaults to
+    /// after the last frame.
+    #[clap(long, value_name = "INDEX", verbatim_doc_comment)]
+    at: Option<usize>,
+}
+
+pub(super) fn run(args: SpliceArgs) -> anyhow::Result<()> {
+    let from = read_cursor(&args.from)?;
+
+    args.file.edit_animations(|mut frames| {
+        let size = frames[0].size();
+        let frame_count = frames.len();
+        let index = args.at.unwrap_or(frame_count);
+
+        if index > frame_count {
+            bail!("the {size}px animation only has {frame_count} frames, so frames can't be inserted at {index}");
+        }
+
+        let inserted = from
+            .images()
+            .iter()
+            .filter(|image| image.size() == size)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if inserted.is_empty() {
+            bail!(
+                "{} has no images with a nominal size of {size}",
+                args.from.display()
+            );
+        }
+
+        frames.splice(index..index, inserted);
+        Ok(frames)
+    })
+}
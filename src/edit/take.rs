@@ -0,0 +1,45 @@
+//! Implementation of `edit take`.
+
+use super::EditFile;
+use anyhow::bail;
+use clap::Args;
+
+#[derive(Args)]
+pub(crate) struct TakeArgs {
+    #[clap(flatten)]
+    file: EditFile,
+
+    /// Only takes this frame of each animation, counting from 0.
+    #[clap(long, value_name = "INDEX")]
+    frame: Option<usize>,
+}
+
+pub(super) fn run(args: TakeArgs) -> anyhow::Result<()> {
+    if args.file.output.is_none() {
+        bail!("`edit take` needs an output filename (`-o`), so that the original isn't replaced");
+    }
+
+    args.file.edit(|cursor| {
+        for size in cursor.sizes() {
+            if !args.file.selects_size(size) {
+                cursor.remove_size(size);
+                continue;
+            }
+
+            let Some(index) = args.frame else {
+                continue;
+            };
+
+            let mut frames = cursor.images().iter().filter(|image| image.size() == size);
+            let frame_count = frames.clone().count();
+
+            let Some(frame) = frames.nth(index).cloned() else {
+                bail!("the {size}px animation only has {frame_count} frames, so it has no frame {index}");
+            };
+
+            cursor.replace_size(size, [frame])?;
+        }
+
+        Ok(())
+    })
+}
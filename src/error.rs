@@ -0,0 +1,195 @@
+//! The error type returned by this crate.
+
+use crate::Image;
+use serde::Serialize;
+use std::io;
+
+/// A specialized [`Result`](std::result::Result) type for this crate.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong when reading, resizing, or writing an Xcursor.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The input wasn't a valid Xcursor file.
+    #[error("{kind} (at byte {offset})")]
+    Parse {
+        /// The offset into the file where the problem was found.
+        offset: usize,
+        kind: ParseErrorKind,
+    },
+
+    /// An image's dimensions didn't match the number of pixels it was given.
+    #[error(
+        "image dimensions ({width}x{height} = {}) do not match the number of pixels given ({pixel_count})",
+        u64::from(*width) * u64::from(*height)
+    )]
+    DimensionMismatch {
+        width: u32,
+        height: u32,
+        pixel_count: usize,
+    },
+
+    /// An image was wider or taller than [`Image::MAX_DIMENSION`].
+    #[error(
+        "image dimensions ({width}x{height}) are larger than the maximum of {max}x{max}",
+        max = Image::MAX_DIMENSION
+    )]
+    ImageTooLarge { width: u32, height: u32 },
+
+    /// Scaling one of an image's fields would have overflowed.
+    #[error("{field} {value} is too large to scale by {scale}")]
+    Overflow {
+        /// A description of the field, such as `"width"`.
+        field: &'static str,
+        value: u32,
+        scale: u32,
+    },
+
+    /// Scaling an image's delay would have overflowed.
+    #[error("a delay of {delay}ms is too long to scale by {factor}")]
+    DelayOverflow { delay: u32, factor: f64 },
+
+    /// An image didn't fit on the canvas it was being placed on by a
+    /// [`Canvas`](crate::filters::Canvas).
+    #[error("a {width}x{height} image at {x},{y} doesn't fit on a {canvas_width}x{canvas_height} canvas")]
+    OutsideCanvas {
+        width: u32,
+        height: u32,
+        x: u32,
+        y: u32,
+        canvas_width: u32,
+        canvas_height: u32,
+    },
+
+    /// The cursor being written wouldn't fit in an Xcursor file.
+    #[error("cursor is too large to write (Xcursor files are limited to 4 GiB)")]
+    FileTooLarge,
+
+    /// An image had the same nominal size as an existing image, and
+    /// [`DuplicateSizePolicy::Reject`](crate::DuplicateSizePolicy::Reject)
+    /// was in use.
+    #[error("this cursor already has an image with a nominal size of {0}")]
+    DuplicateSize(u32),
+
+    /// A [`FileHeader`](crate::FileHeader) given to a writer was too short
+    /// to hold the header's fields.
+    #[error("file header size {0} is smaller than the minimum of 16 bytes")]
+    HeaderTooSmall(u32),
+
+    /// An image given to a [`StreamingWriter`](crate::StreamingWriter)
+    /// didn't match the layout it was created with.
+    #[error("image {index} doesn't match the layout given to the streaming writer")]
+    LayoutMismatch { index: usize },
+
+    /// A [`StreamingWriter`](crate::StreamingWriter) was finished before
+    /// every image was written.
+    #[error("only {written} of {expected} images were written")]
+    IncompleteStream { written: usize, expected: usize },
+
+    /// Something went wrong with one of a cursor's images while it was
+    /// being transformed or written.
+    #[error("image {index} (with a nominal size of {size}): {error}")]
+    InImage {
+        /// The image's index among the cursor's images, starting from 0.
+        index: usize,
+        size: u32,
+        error: Box<Error>,
+    },
+
+    /// A [`Script`](crate::script::Script) failed to compile or run,
+    /// or returned an invalid image.
+    #[cfg(feature = "scripting")]
+    #[error("script error: {0}")]
+    Script(String),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl Error {
+    /// Wraps an error caused by one of a cursor's images.
+    pub(crate) fn in_image(index: usize, size: u32, error: Self) -> Self {
+        Self::InImage {
+            index,
+            size,
+            error: Box::new(error),
+        }
+    }
+
+    /// Returns whether this error came from an image or a cursor that was,
+    /// or would have become, too large to represent.
+    pub fn is_too_large(&self) -> bool {
+        match self {
+            Self::InImage { error, .. } => error.is_too_large(),
+            Self::ImageTooLarge { .. } | Self::Overflow { .. } | Self::FileTooLarge => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether this error came from a file exceeding one of the
+    /// [`Limits`](crate::Limits) it was read with.
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(
+            self,
+            Self::Parse {
+                kind: ParseErrorKind::TooManyChunks { .. }
+                    | ParseErrorKind::ImageDimensionsExceeded { .. }
+                    | ParseErrorKind::TotalPixelsExceeded { .. },
+                ..
+            }
+        )
+    }
+}
+
+/// The reason that an Xcursor file couldn't be parsed.
+///
+/// This implements `Serialize`, so that parse failures can be reported as
+/// JSON along with their offset.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    #[error("file doesn't start with `Xcur`")]
+    BadMagic,
+
+    #[error("file header size is {0}, which is smaller than the minimum of 16")]
+    BadFileHeaderSize(u32),
+
+    #[error("file ended unexpectedly while reading {0}")]
+    UnexpectedEof(&'static str),
+
+    #[error("table of contents entry {index} points past the end of the file")]
+    ChunkOutOfBounds { index: usize },
+
+    #[error("couldn't find a chunk matching table of contents entry {index} anywhere in the file")]
+    ChunkNotFound { index: usize },
+
+    #[error(
+        "chunk {index} has type {found:#010x} and subtype {found_subtype}, \
+        but its table of contents entry says type {expected:#010x} and subtype {expected_subtype}"
+    )]
+    ChunkMismatch {
+        index: usize,
+        expected: u32,
+        expected_subtype: u32,
+        found: u32,
+        found_subtype: u32,
+    },
+
+    #[error("table of contents has {count} entries, more than the limit of {max}")]
+    TooManyChunks { count: u32, max: u32 },
+
+    #[error(
+        "image chunk {index} is {width}x{height}, which is larger than the limit of {max}x{max}"
+    )]
+    ImageDimensionsExceeded {
+        index: usize,
+        width: u32,
+        height: u32,
+        max: u32,
+    },
+
+    #[error("images have more than the limit of {max} pixels in total")]
+    TotalPixelsExceeded { max: u64 },
+}
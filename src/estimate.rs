@@ -0,0 +1,228 @@
+//! Predictions of how large resized cursors will be, for `--dry-run` and
+//! `info --estimate`.
+
+use crate::{input, interrupt, parse_file, salvage_damaged, ParseOptions};
+use anyhow::{bail, Context};
+use resize_xcursor::{estimate, Chunk, Estimate, FileHeader, Pipeline};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// The largest file that an Xcursor file can be, in bytes.
+const MAX_FILE_BYTES: u64 = u32::MAX as u64;
+
+/// How much resizing a single file may take, for `--max-memory` and
+/// `--max-output-bytes`.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Budget {
+    /// The most memory that resizing a file may take, including the input
+    /// file itself, in bytes.
+    pub(crate) memory: Option<u64>,
+    /// The largest that a resized file may be, in bytes.
+    pub(crate) output_bytes: Option<u64>,
+}
+
+impl Budget {
+    /// Fails before anything is resized if resizing a file would go over
+    /// this budget, suggesting ways to make it fit.
+    pub(crate) fn check(
+        &self,
+        input_filename: &Path,
+        input_bytes: u64,
+        header: FileHeader,
+        cursor_chunks: &[Chunk],
+        pipeline: &Pipeline,
+        optimize_frames: bool,
+    ) -> anyhow::Result<()> {
+        if self.memory.is_none() && self.output_bytes.is_none() {
+            return Ok(());
+        }
+
+        let estimate = estimate(header, cursor_chunks, pipeline).with_context(|| {
+            format!("couldn't estimate the size of {}", input_filename.display())
+        })?;
+
+        let mut suggestions = vec!["--only-size or --max-size to leave out some of its images"];
+
+        // Frames can only be merged if there's more than one of a size
+        let mut sizes = cursor_chunks
+            .iter()
+            .filter_map(|chunk| match chunk {
+                Chunk::Image(image) => Some(image.info.size()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let image_count = sizes.len();
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        if !optimize_frames && sizes.len() < image_count {
+            suggestions.push("--optimize-frames to merge its identical frames");
+        }
+
+        let memory = input_bytes + estimate.peak_memory;
+
+        if let Some(budget) = self.memory.filter(|&budget| memory > budget) {
+            suggestions.push("--low-memory to resize one image at a time");
+
+            bail!(
+                "resizing {} would take about {} of memory, more than --max-memory allows ({})\n\
+                (use {})",
+                input_filename.display(),
+                format_bytes(memory),
+                format_bytes(budget),
+                suggestions.join(", or ")
+            );
+        }
+
+        if let Some(budget) = self
+            .output_bytes
+            .filter(|&budget| estimate.output_bytes > budget)
+        {
+            bail!(
+                "{} would be about {} once resized, more than --max-output-bytes allows ({})\n\
+                (use {})",
+                input_filename.display(),
+                format_bytes(estimate.output_bytes),
+                format_bytes(budget),
+                suggestions.join(", or ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a number of bytes, optionally followed by a binary unit such as
+/// `512MiB` or `2G`.
+pub(crate) fn parse_byte_count(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| format!("`{s}` doesn't start with a number"))?;
+
+    let shift = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kib" => 10,
+        "m" | "mib" => 20,
+        "g" | "gib" => 30,
+        "t" | "tib" => 40,
+        _ => {
+            return Err(format!(
+                "`{unit}` isn't a unit of bytes (such as KiB, MiB, or GiB)"
+            ))
+        }
+    };
+
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("`{s}` is too many bytes"))
+}
+
+/// Prints an estimate for every file instead of resizing them, for
+/// `--dry-run`.
+pub(crate) fn dry_run(
+    input_filenames: &[PathBuf],
+    pipeline: &Pipeline,
+    parse_options: ParseOptions<'_>,
+) -> anyhow::Result<()> {
+    let mut total_input = 0;
+    let mut total_output = 0;
+
+    for (index, input_filename) in input_filenames.iter().enumerate() {
+        if interrupt::interrupted() {
+            bail!(
+                "interrupted after estimating {index} of {} files",
+                input_filenames.len()
+            );
+        }
+
+        let cursor_bytes = input::read(input_filename)
+            .with_context(|| format!("couldn't read {}", input_filename.display()))?;
+        let cursor_bytes = salvage_damaged(input_filename, &cursor_bytes, parse_options);
+
+        let Some((header, cursor_chunks)) =
+            parse_file(input_filename, &cursor_bytes, parse_options)?
+        else {
+            info!(
+                "{} isn't an Xcursor file, skipping",
+                input_filename.display()
+            );
+            continue;
+        };
+
+        let estimate = estimate(header, &cursor_chunks, pipeline).with_context(|| {
+            format!("couldn't estimate the size of {}", input_filename.display())
+        })?;
+
+        println!(
+            "{}: {}",
+            input_filename.display(),
+            describe(cursor_bytes.len() as u64, estimate)
+        );
+        total_input += cursor_bytes.len() as u64;
+        total_output += estimate.output_bytes;
+    }
+
+    if input_filenames.len() > 1 {
+        println!(
+            "total: {} -> {} ({})",
+            format_bytes(total_input),
+            format_bytes(total_output),
+            format_ratio(total_input, total_output)
+        );
+    }
+
+    Ok(())
+}
+
+/// Describes an estimate for a single file, which is `input_bytes` long.
+pub(crate) fn describe(input_bytes: u64, estimate: Estimate) -> String {
+    let mut notes = String::new();
+
+    if estimate.output_bytes > MAX_FILE_BYTES {
+        notes.push_str(", too large to write");
+    }
+
+    if !estimate.exact {
+        notes.push_str(", approximate since some filters depend on pixels");
+    }
+
+    format!(
+        "{} -> {} ({}), peak memory about {}{notes}",
+        format_bytes(input_bytes),
+        format_bytes(estimate.output_bytes),
+        format_ratio(input_bytes, estimate.output_bytes),
+        // The input file is held in memory the whole time
+        format_bytes(input_bytes + estimate.peak_memory)
+    )
+}
+
+/// Formats a number of bytes with a binary unit, such as `1.5 MiB`.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Formats how many times larger `output` is than `input`, such as `9.0x`.
+fn format_ratio(input: u64, output: u64) -> String {
+    match input {
+        0 => "empty input".to_owned(),
+        _ => format!("{:.1}x", output as f64 / input as f64),
+    }
+}
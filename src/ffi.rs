@@ -0,0 +1,167 @@
+//! A C API, enabled by the `ffi` feature.
+//!
+//! See `include/resize_xcursor.h` for the corresponding header.
+
+use crate::{Error, ResizeOptions, XcursorReader};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    ptr, slice,
+};
+
+/// The call succeeded.
+pub const RESIZE_XCURSOR_OK: i32 = 0;
+/// The input wasn't a valid Xcursor file.
+pub const RESIZE_XCURSOR_ERROR_PARSE: i32 = 1;
+/// The resized cursor would have been too large.
+pub const RESIZE_XCURSOR_ERROR_TOO_LARGE: i32 = 2;
+/// A required pointer was null.
+pub const RESIZE_XCURSOR_ERROR_NULL: i32 = 3;
+/// Anything else went wrong.
+pub const RESIZE_XCURSOR_ERROR_OTHER: i32 = 4;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The fields of an image, as returned by [`resize_xcursor_images`].
+#[repr(C)]
+pub struct ResizeXcursorImageInfo {
+    pub size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    pub delay: u32,
+    /// The offset of this image's chunk from the start of the file.
+    pub position: u32,
+}
+
+/// Resizes an Xcursor file.
+///
+/// On success, `*output` and `*output_len` are set to a buffer containing
+/// the resized file, which must be freed with [`resize_xcursor_free`].
+///
+/// # Safety
+/// `input` must point to `input_len` readable bytes, and `output` and
+/// `output_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn resize_xcursor_resize(
+    input: *const u8,
+    input_len: usize,
+    scale: u32,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> i32 {
+    if input.is_null() || output.is_null() || output_len.is_null() {
+        return null_error();
+    }
+
+    let input = slice::from_raw_parts(input, input_len);
+
+    let mut res = Vec::new();
+    let result = crate::resize(input, &ResizeOptions::new(scale))
+        .and_then(|cursor| cursor.write_to(&mut res));
+
+    if let Err(err) = result {
+        return set_error(err);
+    }
+
+    let res = res.into_boxed_slice();
+    *output_len = res.len();
+    *output = Box::into_raw(res).cast();
+    RESIZE_XCURSOR_OK
+}
+
+/// Frees a buffer returned by [`resize_xcursor_resize`].
+///
+/// # Safety
+/// `buffer` and `len` must have come from the same call to
+/// [`resize_xcursor_resize`], and the buffer mustn't have been freed already.
+/// Passing a null `buffer` does nothing.
+#[no_mangle]
+pub unsafe extern "C" fn resize_xcursor_free(buffer: *mut u8, len: usize) {
+    if !buffer.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, len)));
+    }
+}
+
+/// Reads the fields of each image in an Xcursor file.
+///
+/// Up to `capacity` images are written to `images`, and `*count` is set to
+/// the total number of images in the file. Passing a `capacity` of 0 (and a
+/// null `images`) can be used to find out how many images there are.
+///
+/// # Safety
+/// `input` must point to `input_len` readable bytes, `images` must be valid
+/// for writing `capacity` elements, and `count` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn resize_xcursor_images(
+    input: *const u8,
+    input_len: usize,
+    images: *mut ResizeXcursorImageInfo,
+    capacity: usize,
+    count: *mut usize,
+) -> i32 {
+    if input.is_null() || count.is_null() || (images.is_null() && capacity > 0) {
+        return null_error();
+    }
+
+    let input = slice::from_raw_parts(input, input_len);
+
+    let infos = match XcursorReader::new(input).and_then(|reader| reader.info()) {
+        Ok(info) => info.images,
+        Err(err) => return set_error(err),
+    };
+
+    for (index, info) in infos.iter().take(capacity).enumerate() {
+        images.add(index).write(ResizeXcursorImageInfo {
+            size: info.size(),
+            width: info.width,
+            height: info.height,
+            xhot: info.xhot,
+            yhot: info.yhot,
+            delay: info.delay,
+            position: info.position,
+        });
+    }
+
+    *count = infos.len();
+    RESIZE_XCURSOR_OK
+}
+
+/// Returns a description of the last error that happened on this thread,
+/// or null if there hasn't been one.
+///
+/// The string is valid until the next call into this library on this thread.
+#[no_mangle]
+pub extern "C" fn resize_xcursor_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+fn set_error(err: Error) -> i32 {
+    let code = match err {
+        Error::Parse { .. } => RESIZE_XCURSOR_ERROR_PARSE,
+        _ if err.is_too_large() => RESIZE_XCURSOR_ERROR_TOO_LARGE,
+        _ => RESIZE_XCURSOR_ERROR_OTHER,
+    };
+
+    set_error_message(err.to_string());
+    code
+}
+
+fn null_error() -> i32 {
+    set_error_message("a required pointer was null".to_owned());
+    RESIZE_XCURSOR_ERROR_NULL
+}
+
+fn set_error_message(message: String) {
+    // Error messages never contain NUL bytes, but avoid panicking just in case
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
@@ -0,0 +1,607 @@
+//! Transforms that change the colors of an image's pixels.
+
+use crate::{Image, ImageLayout, Result, Transform};
+
+/// A color with straight (not premultiplied) alpha.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    /// Creates an opaque color.
+    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 0xFF }
+    }
+
+    /// Parses a color written as `#RRGGBB` or `#RRGGBBAA`. The `#` is
+    /// optional.
+    ///
+    /// ```
+    /// use resize_xcursor::filters::Color;
+    ///
+    /// assert_eq!(Color::from_hex("#FF8000"), Some(Color::rgb(0xFF, 0x80, 0x00)));
+    /// assert_eq!(Color::from_hex("ff800080").map(|color| color.a), Some(0x80));
+    /// assert_eq!(Color::from_hex("orange"), None);
+    /// ```
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return None;
+        }
+
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+
+        Some(Self {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+            a: match hex.len() {
+                8 => channel(6)?,
+                _ => 0xFF,
+            },
+        })
+    }
+
+    fn to_rgba(self) -> Rgba {
+        Rgba {
+            r: f32::from(self.r) / 255.0,
+            g: f32::from(self.g) / 255.0,
+            b: f32::from(self.b) / 255.0,
+            a: f32::from(self.a) / 255.0,
+        }
+    }
+}
+
+/// A [`Transform`] that rotates the hue of every pixel, keeping its
+/// saturation and brightness. This is a quick way to recolor a theme.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct HueShift {
+    /// How far to rotate each hue, in degrees. For example, 120 turns red
+    /// into green and blue into red.
+    pub degrees: f32,
+}
+
+impl HueShift {
+    pub fn new(degrees: f32) -> Self {
+        Self { degrees }
+    }
+}
+
+impl Transform for HueShift {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let shift = self.degrees.rem_euclid(360.0) / 60.0;
+
+        Ok(map_colors(image, |color| {
+            let (hue, saturation, value) = color.to_hsv();
+            Rgba::from_hsv((hue + shift) % 6.0, saturation, value, color.a)
+        }))
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that blends a color into every pixel that isn't fully
+/// transparent, keeping its alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Tint {
+    /// The color to blend in. Its alpha is ignored.
+    pub color: Color,
+    /// How much of the color to blend in, from 0 (none) to 1 (replacing
+    /// every pixel's color).
+    pub strength: f32,
+}
+
+impl Tint {
+    pub fn new(color: Color, strength: f32) -> Self {
+        Self { color, strength }
+    }
+}
+
+impl Transform for Tint {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let tint = self.color.to_rgba();
+        let strength = self.strength.clamp(0.0, 1.0);
+        let blend = |channel: f32, tint: f32| channel + (tint - channel) * strength;
+
+        Ok(map_colors(image, |color| Rgba {
+            r: blend(color.r, tint.r),
+            g: blend(color.g, tint.g),
+            b: blend(color.b, tint.b),
+            a: color.a,
+        }))
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that desaturates every pixel, blending its color towards
+/// a gray of the same luminance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Grayscale {
+    /// How far to desaturate each pixel, from 0 (not at all) to 1 (fully
+    /// gray).
+    pub amount: f32,
+}
+
+impl Grayscale {
+    pub fn new(amount: f32) -> Self {
+        Self { amount }
+    }
+}
+
+impl Default for Grayscale {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Transform for Grayscale {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let amount = self.amount.clamp(0.0, 1.0);
+
+        Ok(map_colors(image, |color| {
+            let luminance = color.luminance();
+            color.map_channels(|channel| channel + (luminance - channel) * amount)
+        }))
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that multiplies the brightness of every pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Brightness {
+    /// The factor to multiply each color channel by. For example, 1.2 makes
+    /// every pixel 20% brighter, and 0.8 makes it 20% darker.
+    pub factor: f32,
+}
+
+impl Brightness {
+    pub fn new(factor: f32) -> Self {
+        Self { factor }
+    }
+}
+
+impl Transform for Brightness {
+    fn apply(&self, image: Image) -> Result<Image> {
+        Ok(map_colors(image, |color| {
+            color.map_channels(|channel| channel * self.factor)
+        }))
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that changes the contrast of every pixel, moving its
+/// color channels towards or away from middle gray.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Contrast {
+    /// The factor to scale the contrast by. Values above 1 increase the
+    /// contrast, while values below 1 reduce it.
+    pub factor: f32,
+}
+
+impl Contrast {
+    pub fn new(factor: f32) -> Self {
+        Self { factor }
+    }
+}
+
+impl Transform for Contrast {
+    fn apply(&self, image: Image) -> Result<Image> {
+        Ok(map_colors(image, |color| {
+            color.map_channels(|channel| (channel - 0.5) * self.factor + 0.5)
+        }))
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that applies gamma correction to every pixel, raising
+/// each of its color channels to the power of `1 / gamma`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Gamma {
+    /// Values above 1 brighten the midtones of each image, while values
+    /// below 1 darken them. Pure black and white are left as they are.
+    pub gamma: f32,
+}
+
+impl Gamma {
+    pub fn new(gamma: f32) -> Self {
+        Self { gamma }
+    }
+}
+
+impl Transform for Gamma {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let exponent = self.gamma.recip();
+
+        Ok(map_colors(image, |color| {
+            color.map_channels(|channel| channel.powf(exponent))
+        }))
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that multiplies the opacity of every pixel, making the
+/// image translucent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Opacity {
+    /// The factor to multiply each pixel's alpha by, from 0 (fully
+    /// transparent) to 1 (unchanged).
+    pub factor: f32,
+}
+
+impl Opacity {
+    pub fn new(factor: f32) -> Self {
+        Self { factor }
+    }
+}
+
+impl Transform for Opacity {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        let factor = self.factor.clamp(0.0, 1.0);
+
+        for pixel in image.pixels_mut() {
+            // Scaling every channel of a premultiplied pixel scales its alpha
+            // while keeping its color
+            let channels = pixel
+                .to_le_bytes()
+                .map(|channel| (f32::from(channel) * factor).round() as u8);
+
+            *pixel = u32::from_le_bytes(channels);
+        }
+
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that swaps specific colors for others, such as a theme's
+/// accent color.
+///
+/// Pixels are compared by their color, ignoring their alpha, so antialiased
+/// edges drawn in a replaced color are replaced too. Pixels that are only
+/// close to a replaced color (within the tolerance) keep their difference
+/// from it, so gradients and antialiasing are preserved.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplaceColors {
+    replacements: Vec<(Color, Color)>,
+    tolerance: u8,
+}
+
+impl ReplaceColors {
+    /// Creates a transform that doesn't replace any colors yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces `from` with `to`. If a pixel is close to more than one
+    /// color, the first one added is used. Both colors' alpha is ignored.
+    pub fn replace(&mut self, from: Color, to: Color) -> &mut Self {
+        self.replacements.push((from, to));
+        self
+    }
+
+    /// Sets how far each of a pixel's channels can be from a replaced color
+    /// (from 0 to 255) for it to be replaced. Defaults to 0, which only
+    /// replaces exact matches.
+    pub fn tolerance(&mut self, tolerance: u8) -> &mut Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Returns whether this doesn't replace any colors.
+    pub fn is_empty(&self) -> bool {
+        self.replacements.is_empty()
+    }
+}
+
+impl Transform for ReplaceColors {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        for pixel in image.pixels_mut() {
+            let [b, g, r, a] = pixel.to_le_bytes().map(f32::from);
+
+            if a == 0.0 {
+                continue;
+            }
+
+            // Colors are compared as if they had the pixel's alpha, so that
+            // antialiased edges match exactly rather than after rounding
+            let alpha = a / 255.0;
+            let tolerance = f32::from(self.tolerance) * alpha + 0.5;
+            let premultiply = |color: Color| {
+                [color.b, color.g, color.r].map(|channel| f32::from(channel) * alpha)
+            };
+
+            let replacement = self.replacements.iter().find(|&&(from, _)| {
+                premultiply(from)
+                    .into_iter()
+                    .zip([b, g, r])
+                    .all(|(from, channel)| (channel - from).abs() <= tolerance)
+            });
+
+            let Some(&(from, to)) = replacement else {
+                continue;
+            };
+
+            let channels = [b, g, r];
+            let [from, to] = [from, to].map(premultiply);
+            let [b, g, r] = std::array::from_fn(|index| {
+                (to[index] + channels[index] - from[index])
+                    .round()
+                    .clamp(0.0, a) as u8
+            });
+
+            *pixel = u32::from_le_bytes([b, g, r, a as u8]);
+        }
+
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that makes every pixel either fully opaque or fully
+/// transparent, for crisp edges without any antialiasing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AlphaThreshold {
+    /// The lowest alpha (from 0 to 255) that's made fully opaque. Pixels with
+    /// a lower alpha are made fully transparent.
+    pub threshold: u8,
+}
+
+impl AlphaThreshold {
+    pub fn new(threshold: u8) -> Self {
+        Self { threshold }
+    }
+}
+
+impl Transform for AlphaThreshold {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        for pixel in image.pixels_mut() {
+            let alpha = (*pixel >> 24) as u8;
+
+            *pixel = if alpha == 0 || alpha < self.threshold {
+                0
+            } else {
+                Rgba {
+                    a: 1.0,
+                    ..Rgba::from_pixel(*pixel)
+                }
+                .to_pixel()
+            };
+        }
+
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that premultiplies every pixel's color by its alpha.
+///
+/// Xcursor pixels are supposed to be premultiplied already, but some tools
+/// write straight alpha instead, which gives cursors bright halos around
+/// their edges. This fixes such files, and should come before any other
+/// transforms, which all expect premultiplied pixels.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Premultiply;
+
+impl Transform for Premultiply {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        for pixel in image.pixels_mut() {
+            let [b, g, r, a] = pixel.to_le_bytes();
+            let premultiply = |channel: u8| ((u32::from(channel) * u32::from(a) + 127) / 255) as u8;
+            *pixel = u32::from_le_bytes([premultiply(b), premultiply(g), premultiply(r), a]);
+        }
+
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that divides every pixel's color by its alpha, undoing
+/// [`Premultiply`].
+///
+/// libXcursor expects premultiplied pixels, so this is only useful when
+/// writing cursors for tools that expect straight alpha. It should come
+/// after any other transforms.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Unpremultiply;
+
+impl Transform for Unpremultiply {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        for pixel in image.pixels_mut() {
+            let [b, g, r, a] = pixel.to_le_bytes();
+
+            let unpremultiply = |channel: u8| match a {
+                0 => 0,
+                _ => ((u32::from(channel) * 255 + u32::from(a) / 2) / u32::from(a)).min(255) as u8,
+            };
+
+            *pixel = u32::from_le_bytes([unpremultiply(b), unpremultiply(g), unpremultiply(r), a]);
+        }
+
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that inverts the color of every pixel, keeping its alpha.
+/// This turns a light theme into a dark one, and vice versa.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Invert;
+
+impl Transform for Invert {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        for pixel in image.pixels_mut() {
+            let [b, g, r, a] = pixel.to_le_bytes();
+
+            // Since the pixel is premultiplied, each channel is inverted
+            // within its alpha rather than within 255. This is exact, unlike
+            // unpremultiplying it first.
+            *pixel = u32::from_le_bytes([
+                a.saturating_sub(b),
+                a.saturating_sub(g),
+                a.saturating_sub(r),
+                a,
+            ]);
+        }
+
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A color with straight (not premultiplied) alpha, with each channel
+/// between 0 and 1.
+#[derive(Clone, Copy, Debug)]
+struct Rgba {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
+impl Rgba {
+    /// Converts a premultiplied ARGB pixel. The pixel can't be fully
+    /// transparent, since its color would be lost.
+    fn from_pixel(pixel: u32) -> Self {
+        let [b, g, r, a] = pixel.to_le_bytes();
+        let a = f32::from(a) / 255.0;
+        let unpremultiply = |channel: u8| (f32::from(channel) / 255.0 / a).min(1.0);
+
+        Self {
+            r: unpremultiply(r),
+            g: unpremultiply(g),
+            b: unpremultiply(b),
+            a,
+        }
+    }
+
+    /// Converts this color into a premultiplied ARGB pixel.
+    fn to_pixel(self) -> u32 {
+        let a = self.a.clamp(0.0, 1.0);
+        let premultiply = |channel: f32| (channel.clamp(0.0, 1.0) * a * 255.0).round() as u8;
+
+        u32::from_le_bytes([
+            premultiply(self.b),
+            premultiply(self.g),
+            premultiply(self.r),
+            (a * 255.0).round() as u8,
+        ])
+    }
+
+    /// Applies `f` to the red, green, and blue channels, keeping the alpha.
+    fn map_channels(self, f: impl Fn(f32) -> f32) -> Self {
+        Self {
+            r: f(self.r),
+            g: f(self.g),
+            b: f(self.b),
+            a: self.a,
+        }
+    }
+
+    /// Returns this color's luminance, using the Rec. 709 coefficients.
+    fn luminance(self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Returns this color's hue (from 0 to 6), saturation, and value.
+    fn to_hsv(self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let chroma = max - min;
+
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == self.r {
+            ((self.g - self.b) / chroma).rem_euclid(6.0)
+        } else if max == self.g {
+            (self.b - self.r) / chroma + 2.0
+        } else {
+            (self.r - self.g) / chroma + 4.0
+        };
+
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue, saturation, max)
+    }
+
+    /// The inverse of [`Rgba::to_hsv`].
+    fn from_hsv(hue: f32, saturation: f32, value: f32, a: f32) -> Self {
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let m = value - chroma;
+
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        Self {
+            r: r + m,
+            g: g + m,
+            b: b + m,
+            a,
+        }
+    }
+}
+
+/// Replaces the color of every pixel that isn't fully transparent with
+/// `f(color)`.
+fn map_colors(mut image: Image, f: impl Fn(Rgba) -> Rgba) -> Image {
+    for pixel in image.pixels_mut() {
+        if *pixel >> 24 != 0 {
+            *pixel = f(Rgba::from_pixel(*pixel)).to_pixel();
+        }
+    }
+
+    image
+}
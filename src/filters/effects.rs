@@ -0,0 +1,462 @@
+//! Transforms that draw something new on or around an image, such as a
+//! shadow or an outline.
+
+use super::{Anchor, Color};
+use crate::{pool, Error, Image, ImageLayout, Result, Transform};
+use std::fmt::{self, Debug, Formatter};
+
+/// A [`Transform`] that draws a soft shadow underneath every image.
+///
+/// Images are enlarged to fit their shadow, and their hotspot is moved so
+/// that it still points at the same pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Shadow {
+    /// How far to move the shadow to the right of the image. This can be
+    /// negative.
+    pub x_offset: i32,
+    /// How far to move the shadow below the image. This can be negative.
+    pub y_offset: i32,
+    /// How many pixels the edge of the shadow is blurred over.
+    pub blur: u32,
+    /// The shadow's color. Its alpha sets how dark the shadow is.
+    pub color: Color,
+}
+
+impl Shadow {
+    pub fn new(x_offset: i32, y_offset: i32, blur: u32, color: Color) -> Self {
+        Self {
+            x_offset,
+            y_offset,
+            blur,
+            color,
+        }
+    }
+
+    /// Returns the bounds of an image with the given dimensions once its
+    /// shadow has been added.
+    fn bounds(&self, width: u32, height: u32) -> Result<Bounds> {
+        let blur = i64::from(self.blur);
+        let (x_offset, y_offset) = (i64::from(self.x_offset), i64::from(self.y_offset));
+
+        let left = (x_offset - blur).min(0);
+        let top = (y_offset - blur).min(0);
+        let right = (x_offset + blur).max(0) + i64::from(width);
+        let bottom = (y_offset + blur).max(0) + i64::from(height);
+
+        Bounds::new(-left, -top, right - left, bottom - top)
+    }
+}
+
+impl Transform for Shadow {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let bounds = self.bounds(image.width(), image.height())?;
+        let width = image.width() as usize;
+        let canvas_width = bounds.width as usize;
+        let pixel_count = canvas_width * bounds.height as usize;
+
+        // The shadow's alpha, before it's tinted with its color
+        let mut shadow = vec![0.0; pixel_count];
+        let shadow_x = (i64::from(bounds.x) + i64::from(self.x_offset)) as usize;
+        let shadow_y = (i64::from(bounds.y) + i64::from(self.y_offset)) as usize;
+
+        for (row, source) in image.pixels().chunks_exact(width.max(1)).enumerate() {
+            let start = (shadow_y + row) * canvas_width + shadow_x;
+
+            for (alpha, pixel) in shadow[start..start + width].iter_mut().zip(source) {
+                *alpha = f32::from((pixel >> 24) as u8) / 255.0;
+            }
+        }
+
+        gaussian_blur(&mut shadow, canvas_width, self.blur as usize);
+
+        let mut pixels = pool::take(pixel_count);
+        let shadow_alpha = f32::from(self.color.a) / 255.0;
+
+        pixels.extend(
+            shadow
+                .iter()
+                .map(|alpha| premultiplied(self.color, alpha * shadow_alpha)),
+        );
+
+        for (row, source) in image.pixels().chunks_exact(width.max(1)).enumerate() {
+            let start = (bounds.y as usize + row) * canvas_width + bounds.x as usize;
+
+            for (pixel, &source) in pixels[start..start + width].iter_mut().zip(source) {
+                *pixel = over(source, *pixel);
+            }
+        }
+
+        let (size, delay) = (image.size(), image.delay());
+        let (xhot, yhot) = (image.xhot() + bounds.x, image.yhot() + bounds.y);
+        pool::give(image.into_pixels());
+
+        Image::new(size, bounds.width, bounds.height, xhot, yhot, delay, pixels)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        let bounds = self.bounds(layout.width, layout.height)?;
+
+        Ok(Some(ImageLayout {
+            size: layout.size,
+            width: bounds.width,
+            height: bounds.height,
+        }))
+    }
+}
+
+/// A [`Transform`] that draws an outline around the visible parts of every
+/// image. This makes small cursors much easier to see on busy backgrounds.
+///
+/// Images are enlarged to fit their outline, and their hotspot is moved so
+/// that it still points at the same pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Outline {
+    /// How thick the outline is, in pixels.
+    pub width: u32,
+    /// The outline's color.
+    pub color: Color,
+}
+
+impl Outline {
+    pub fn new(width: u32, color: Color) -> Self {
+        Self { width, color }
+    }
+
+    /// Returns the bounds of an image with the given dimensions once its
+    /// outline has been added.
+    fn bounds(&self, width: u32, height: u32) -> Result<Bounds> {
+        let margin = i64::from(self.width);
+
+        Bounds::new(
+            margin,
+            margin,
+            i64::from(width) + 2 * margin,
+            i64::from(height) + 2 * margin,
+        )
+    }
+}
+
+impl Transform for Outline {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let bounds = self.bounds(image.width(), image.height())?;
+        let (width, height) = (image.width() as i64, image.height() as i64);
+        let radius = i64::from(self.width);
+
+        // Outlines are round, so they stay the same thickness around corners
+        let neighbors = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(|(dx, dy)| dx * dx + dy * dy <= radius * radius)
+            .collect::<Vec<_>>();
+
+        let alpha_at = |x: i64, y: i64| {
+            if (0..width).contains(&x) && (0..height).contains(&y) {
+                image.pixels()[(y * width + x) as usize] >> 24
+            } else {
+                0
+            }
+        };
+
+        let mut pixels = pool::take(bounds.width as usize * bounds.height as usize);
+        let outline_alpha = f32::from(self.color.a) / 255.0;
+
+        for y in 0..i64::from(bounds.height) {
+            for x in 0..i64::from(bounds.width) {
+                // Coordinates in the original image
+                let (x, y) = (x - radius, y - radius);
+
+                let coverage = neighbors
+                    .iter()
+                    .map(|(dx, dy)| alpha_at(x + dx, y + dy))
+                    .max()
+                    .unwrap_or(0);
+
+                let outline = premultiplied(self.color, coverage as f32 / 255.0 * outline_alpha);
+
+                let pixel = if (0..width).contains(&x) && (0..height).contains(&y) {
+                    image.pixels()[(y * width + x) as usize]
+                } else {
+                    0
+                };
+
+                pixels.push(over(pixel, outline));
+            }
+        }
+
+        let (size, delay) = (image.size(), image.delay());
+        let (xhot, yhot) = (image.xhot() + bounds.x, image.yhot() + bounds.y);
+        pool::give(image.into_pixels());
+
+        Image::new(size, bounds.width, bounds.height, xhot, yhot, delay, pixels)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        let bounds = self.bounds(layout.width, layout.height)?;
+
+        Ok(Some(ImageLayout {
+            size: layout.size,
+            width: bounds.width,
+            height: bounds.height,
+        }))
+    }
+}
+
+/// A [`Transform`] that draws another image, such as a badge, on top of
+/// every image.
+///
+/// Parts of the overlay that would be outside of an image are cut off, so
+/// images keep their dimensions.
+#[derive(Clone)]
+pub struct Overlay {
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>,
+    anchor: Anchor,
+}
+
+impl Overlay {
+    /// Creates an overlay from premultiplied ARGB pixels, row by row. It's
+    /// placed in the bottom right corner of each image by default.
+    ///
+    /// This fails if the dimensions don't match the number of pixels given.
+    pub fn new(width: u32, height: u32, pixels: Vec<u32>) -> Result<Self> {
+        if u64::try_from(pixels.len()).ok() != Some(u64::from(width) * u64::from(height)) {
+            return Err(Error::DimensionMismatch {
+                width,
+                height,
+                pixel_count: pixels.len(),
+            });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+            anchor: Anchor::BottomRight,
+        })
+    }
+
+    /// Sets where to place the overlay on each image.
+    pub fn anchor(&mut self, anchor: Anchor) -> &mut Self {
+        self.anchor = anchor;
+        self
+    }
+}
+
+impl Debug for Overlay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Overlay")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("anchor", &self.anchor)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Transform for Overlay {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        let (x, y) = self
+            .anchor
+            .position(self.width, self.height, image.width(), image.height());
+
+        let image_width = image.width() as usize;
+        let (x, y) = (x as usize, y as usize);
+        let visible_width = self.width.min(image.width().saturating_sub(x as u32)) as usize;
+
+        if visible_width == 0 {
+            return Ok(image);
+        }
+
+        let rows = image
+            .pixels_mut()
+            .chunks_exact_mut(image_width)
+            .skip(y)
+            .zip(self.pixels.chunks_exact(self.width as usize));
+
+        for (row, overlay) in rows {
+            for (pixel, &overlay) in row[x..x + visible_width].iter_mut().zip(overlay) {
+                *pixel = over(overlay, *pixel);
+            }
+        }
+
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that softens every image with a Gaussian blur.
+///
+/// Images keep their dimensions, so anything blurred past their edges is cut
+/// off. Add a margin with a [`Canvas`](super::Canvas) first to avoid this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Blur {
+    /// How many pixels the blur reaches in each direction.
+    pub radius: u32,
+}
+
+impl Blur {
+    pub fn new(radius: u32) -> Self {
+        Self { radius }
+    }
+}
+
+impl Transform for Blur {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        let width = image.width() as usize;
+        let mut plane = Vec::with_capacity(image.pixels().len());
+
+        // Premultiplied channels can be blurred separately without darkening
+        // edges, since transparent pixels don't have any color
+        let mut channels = [0; 4].map(|_| Vec::new());
+
+        for (channel, blurred) in channels.iter_mut().enumerate() {
+            plane.clear();
+            plane.extend(
+                image
+                    .pixels()
+                    .iter()
+                    .map(|pixel| f32::from(pixel.to_le_bytes()[channel])),
+            );
+
+            gaussian_blur(&mut plane, width, self.radius as usize);
+            blurred.extend(plane.iter().map(|value| value.round().min(255.0) as u8));
+        }
+
+        for (index, pixel) in image.pixels_mut().iter_mut().enumerate() {
+            let [b, g, r, a] = [0, 1, 2, 3].map(|channel| channels[channel][index]);
+
+            // Rounding could leave a color channel slightly above the alpha
+            *pixel = u32::from_le_bytes([b.min(a), g.min(a), r.min(a), a]);
+        }
+
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// Where an image ends up once it's been enlarged.
+struct Bounds {
+    /// The coordinates of the original image's top left corner.
+    x: u32,
+    y: u32,
+    /// The dimensions of the enlarged image.
+    width: u32,
+    height: u32,
+}
+
+impl Bounds {
+    /// Fails if the enlarged image would be larger than
+    /// [`Image::MAX_DIMENSION`].
+    fn new(x: i64, y: i64, width: i64, height: i64) -> Result<Self> {
+        let max = i64::from(Image::MAX_DIMENSION);
+
+        if width > max || height > max {
+            let clamp = |length: i64| u32::try_from(length).unwrap_or(u32::MAX);
+
+            return Err(Error::ImageTooLarge {
+                width: clamp(width),
+                height: clamp(height),
+            });
+        }
+
+        // Each of these is between 0 and `Image::MAX_DIMENSION`
+        Ok(Self {
+            x: x as u32,
+            y: y as u32,
+            width: width as u32,
+            height: height as u32,
+        })
+    }
+}
+
+/// Returns a premultiplied pixel with the color's red, green, and blue
+/// channels, and the given alpha (from 0 to 1).
+fn premultiplied(color: Color, alpha: f32) -> u32 {
+    let premultiply = |channel: u8| (f32::from(channel) * alpha).round() as u8;
+
+    u32::from_le_bytes([
+        premultiply(color.b),
+        premultiply(color.g),
+        premultiply(color.r),
+        (alpha * 255.0).round() as u8,
+    ])
+}
+
+/// Composites the premultiplied pixel `top` over `bottom`.
+fn over(top: u32, bottom: u32) -> u32 {
+    let top = top.to_le_bytes();
+    let bottom = bottom.to_le_bytes();
+    let coverage = 1.0 - f32::from(top[3]) / 255.0;
+
+    u32::from_le_bytes(std::array::from_fn(|channel| {
+        (f32::from(top[channel]) + f32::from(bottom[channel]) * coverage)
+            .round()
+            .min(255.0) as u8
+    }))
+}
+
+/// Applies a Gaussian blur to a plane of values `width` values wide. The
+/// blur reaches `radius` values in each direction, and values outside of the
+/// plane count as 0.
+///
+/// The kernel never reaches further than the plane is wide or tall, since
+/// the rest of it would only ever be multiplied by values outside of it.
+fn gaussian_blur(plane: &mut [f32], width: usize, radius: usize) {
+    if radius == 0 || width == 0 {
+        return;
+    }
+
+    let height = plane.len() / width;
+
+    // Almost all of a Gaussian's weight is within two standard deviations
+    let sigma = radius as f32 / 2.0;
+    let reach = radius.min(width.max(height));
+    let mut kernel = (0..=2 * reach)
+        .map(|index| {
+            let distance = index as f32 - reach as f32;
+            (-distance * distance / (2.0 * sigma * sigma)).exp()
+        })
+        .collect::<Vec<_>>();
+
+    let total = kernel.iter().sum::<f32>();
+    kernel.iter_mut().for_each(|weight| *weight /= total);
+
+    let mut line = Vec::new();
+
+    for y in 0..height {
+        line.clear();
+        line.extend_from_slice(&plane[y * width..(y + 1) * width]);
+        blur_line(&line, &kernel, |x, value| plane[y * width + x] = value);
+    }
+
+    for x in 0..width {
+        line.clear();
+        line.extend((0..height).map(|y| plane[y * width + x]));
+        blur_line(&line, &kernel, |y, value| plane[y * width + x] = value);
+    }
+}
+
+/// Convolves `line` with `kernel`, which is centered on each value, passing
+/// the results to `set` along with their index.
+fn blur_line(line: &[f32], kernel: &[f32], mut set: impl FnMut(usize, f32)) {
+    let radius = kernel.len() / 2;
+
+    for index in 0..line.len() {
+        let start = index.saturating_sub(radius);
+        let end = (index + radius + 1).min(line.len());
+
+        let value = (start..end)
+            .map(|source| line[source] * kernel[source + radius - index])
+            .sum();
+
+        set(index, value);
+    }
+}
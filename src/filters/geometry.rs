@@ -0,0 +1,383 @@
+//! Transforms that move an image's pixels around.
+
+use crate::{pool, Error, Image, ImageLayout, Result, Transform};
+
+/// A [`Transform`] that mirrors every image, moving its hotspot along with
+/// it. This is useful for making left-handed cursors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flip {
+    /// Mirrors images left to right.
+    Horizontal,
+    /// Mirrors images top to bottom.
+    Vertical,
+}
+
+impl Transform for Flip {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+
+        if width == 0 || height == 0 {
+            return Ok(image);
+        }
+
+        let pixels = image.pixels_mut();
+
+        match self {
+            Self::Horizontal => {
+                for row in pixels.chunks_exact_mut(width) {
+                    row.reverse();
+                }
+            }
+
+            Self::Vertical => {
+                // The middle row of an image with an odd height stays put
+                let (top, bottom) = pixels.split_at_mut(height / 2 * width);
+
+                for (top_row, bottom_row) in top
+                    .chunks_exact_mut(width)
+                    .zip(bottom.rchunks_exact_mut(width))
+                {
+                    top_row.swap_with_slice(bottom_row);
+                }
+            }
+        }
+
+        let (xhot, yhot) = match self {
+            Self::Horizontal => (mirror(image.xhot(), image.width()), image.yhot()),
+            Self::Vertical => (image.xhot(), mirror(image.yhot(), image.height())),
+        };
+
+        image.set_hotspot(xhot, yhot);
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// A [`Transform`] that rotates every image clockwise by a multiple of 90
+/// degrees, moving its hotspot along with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotate {
+    Degrees90,
+    Degrees180,
+    Degrees270,
+}
+
+impl Rotate {
+    /// Returns the rotation for the given number of degrees clockwise, or
+    /// `None` if it isn't 90, 180, or 270.
+    pub fn from_degrees(degrees: u32) -> Option<Self> {
+        match degrees {
+            90 => Some(Self::Degrees90),
+            180 => Some(Self::Degrees180),
+            270 => Some(Self::Degrees270),
+            _ => None,
+        }
+    }
+
+    fn rotated_layout(&self, layout: ImageLayout) -> ImageLayout {
+        match self {
+            Self::Degrees180 => layout,
+            Self::Degrees90 | Self::Degrees270 => ImageLayout {
+                size: layout.size,
+                width: layout.height,
+                height: layout.width,
+            },
+        }
+    }
+}
+
+impl Transform for Rotate {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let layout = self.rotated_layout(image.layout());
+        let (width, height) = (image.width(), image.height());
+        let (xhot, yhot) = (image.xhot(), image.yhot());
+
+        // Where each pixel of the rotated image comes from in the original
+        let source = |x: u32, y: u32| match self {
+            Self::Degrees90 => (y, height - 1 - x),
+            Self::Degrees180 => (width - 1 - x, height - 1 - y),
+            Self::Degrees270 => (width - 1 - y, x),
+        };
+
+        let mut pixels = pool::take(image.pixels().len());
+
+        for y in 0..layout.height {
+            for x in 0..layout.width {
+                let (source_x, source_y) = source(x, y);
+                pixels.push(image.pixels()[source_y as usize * width as usize + source_x as usize]);
+            }
+        }
+
+        let (xhot, yhot) = match self {
+            Self::Degrees90 => (mirror(yhot, height), xhot),
+            Self::Degrees180 => (mirror(xhot, width), mirror(yhot, height)),
+            Self::Degrees270 => (yhot, mirror(xhot, width)),
+        };
+
+        let delay = image.delay();
+        pool::give(image.into_pixels());
+
+        Image::new(
+            layout.size,
+            layout.width,
+            layout.height,
+            xhot,
+            yhot,
+            delay,
+            pixels,
+        )
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(self.rotated_layout(layout)))
+    }
+}
+
+/// A [`Transform`] that crops fully transparent margins off of every image,
+/// moving its hotspot to match.
+///
+/// The hotspot is always kept inside the cropped image, and images that are
+/// entirely transparent are left as they are.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Trim;
+
+impl Transform for Trim {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let width = image.width() as usize;
+        let is_opaque = |pixel: &u32| pixel >> 24 != 0;
+
+        let mut rows = image
+            .pixels()
+            .chunks_exact(width.max(1))
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(is_opaque))
+            .map(|(y, _)| y as u32);
+
+        let Some(top) = rows.next() else {
+            return Ok(image);
+        };
+
+        let bottom = rows.next_back().unwrap_or(top);
+        let (mut left, mut right) = (u32::MAX, 0);
+
+        for row in image.pixels().chunks_exact(width) {
+            if let Some(x) = row.iter().position(is_opaque) {
+                left = left.min(x as u32);
+            }
+
+            if let Some(x) = row.iter().rposition(is_opaque) {
+                right = right.max(x as u32);
+            }
+        }
+
+        // Keep the hotspot inside the image, even if it's somewhere
+        // transparent
+        let (xhot, yhot) = (image.xhot(), image.yhot());
+        let (left, right) = (left.min(xhot), right.max(xhot.min(image.width() - 1)));
+        let (top, bottom) = (top.min(yhot), bottom.max(yhot.min(image.height() - 1)));
+
+        let (new_width, new_height) = (right - left + 1, bottom - top + 1);
+
+        if (new_width, new_height) == (image.width(), image.height()) {
+            return Ok(image);
+        }
+
+        let mut pixels = pool::take(new_width as usize * new_height as usize);
+
+        for row in image
+            .pixels()
+            .chunks_exact(width)
+            .skip(top as usize)
+            .take(new_height as usize)
+        {
+            pixels.extend_from_slice(&row[left as usize..=right as usize]);
+        }
+
+        let (size, delay) = (image.size(), image.delay());
+        pool::give(image.into_pixels());
+
+        Image::new(
+            size,
+            new_width,
+            new_height,
+            xhot - left,
+            yhot - top,
+            delay,
+            pixels,
+        )
+    }
+}
+
+/// A [`Transform`] that places every image on a larger transparent canvas,
+/// moving its hotspot to match.
+///
+/// This fails if an image doesn't fit on the canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    /// Where to place each image on the canvas. Defaults to
+    /// [`Anchor::Center`].
+    pub anchor: Anchor,
+}
+
+/// Where a [`Canvas`] places each image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Anchor {
+    /// Centers each image, rounding towards the top left.
+    #[default]
+    Center,
+    TopLeft,
+    BottomRight,
+    /// Places the top left corner of each image at these coordinates.
+    At {
+        x: u32,
+        y: u32,
+    },
+}
+
+impl Anchor {
+    /// Returns where the top left corner of something with the given
+    /// dimensions goes when it's anchored inside of something larger. This
+    /// might not leave it entirely inside.
+    pub(super) fn position(
+        self,
+        width: u32,
+        height: u32,
+        outer_width: u32,
+        outer_height: u32,
+    ) -> (u32, u32) {
+        match self {
+            Self::Center => (
+                outer_width.saturating_sub(width) / 2,
+                outer_height.saturating_sub(height) / 2,
+            ),
+            Self::TopLeft => (0, 0),
+            Self::BottomRight => (
+                outer_width.saturating_sub(width),
+                outer_height.saturating_sub(height),
+            ),
+            Self::At { x, y } => (x, y),
+        }
+    }
+}
+
+impl Canvas {
+    /// Creates a canvas of the given size, with images centered on it.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            anchor: Anchor::Center,
+        }
+    }
+
+    /// Sets where to place each image on the canvas.
+    pub fn anchor(&mut self, anchor: Anchor) -> &mut Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Returns where the top left corner of an image with the given
+    /// dimensions goes.
+    fn position(&self, width: u32, height: u32) -> Result<(u32, u32)> {
+        let (x, y) = self.anchor.position(width, height, self.width, self.height);
+
+        let fits = |position: u32, length: u32, canvas_length: u32| {
+            position
+                .checked_add(length)
+                .is_some_and(|end| end <= canvas_length)
+        };
+
+        if !fits(x, width, self.width) || !fits(y, height, self.height) {
+            return Err(Error::OutsideCanvas {
+                width,
+                height,
+                x,
+                y,
+                canvas_width: self.width,
+                canvas_height: self.height,
+            });
+        }
+
+        Ok((x, y))
+    }
+}
+
+impl Transform for Canvas {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let (x, y) = self.position(image.width(), image.height())?;
+        let canvas_width = self.width as usize;
+
+        let mut pixels = pool::take(canvas_width * self.height as usize);
+        pixels.resize(canvas_width * self.height as usize, 0);
+
+        if image.width() != 0 {
+            for (row, source) in image
+                .pixels()
+                .chunks_exact(image.width() as usize)
+                .enumerate()
+            {
+                let start = (y as usize + row) * canvas_width + x as usize;
+                pixels[start..start + source.len()].copy_from_slice(source);
+            }
+        }
+
+        let (size, delay) = (image.size(), image.delay());
+        let (xhot, yhot) = (image.xhot() + x, image.yhot() + y);
+        pool::give(image.into_pixels());
+
+        Image::new(size, self.width, self.height, xhot, yhot, delay, pixels)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        self.position(layout.width, layout.height)?;
+
+        Ok(Some(ImageLayout {
+            size: layout.size,
+            width: self.width,
+            height: self.height,
+        }))
+    }
+}
+
+/// A [`Transform`] that moves each hotspot that's outside of its image onto
+/// the nearest pixel inside of it.
+///
+/// Hotspots outside of their image usually come from broken conversions, and
+/// scaling moves them even further away.
+///
+/// ```
+/// use resize_xcursor::{filters::ClampHotspot, Image, Transform};
+///
+/// let image = Image::new(2, 2, 2, 5, 1, 0, vec![0; 4])?;
+/// let image = ClampHotspot.apply(image)?;
+/// assert_eq!((image.xhot(), image.yhot()), (1, 1));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClampHotspot;
+
+impl Transform for ClampHotspot {
+    fn apply(&self, mut image: Image) -> Result<Image> {
+        let xhot = image.xhot().min(image.width().saturating_sub(1));
+        let yhot = image.yhot().min(image.height().saturating_sub(1));
+
+        image.set_hotspot(xhot, yhot);
+        Ok(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        Ok(Some(layout))
+    }
+}
+
+/// Mirrors a coordinate along an axis that's `length` pixels long.
+fn mirror(coordinate: u32, length: u32) -> u32 {
+    length.saturating_sub(1).saturating_sub(coordinate)
+}
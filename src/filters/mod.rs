@@ -0,0 +1,16 @@
+//! Built-in [`Transform`](crate::Transform)s for editing images, such as
+//! flipping, rotating, or recoloring them.
+//!
+//! Like [`ResizeOptions`](crate::ResizeOptions), these can be chained
+//! together with a [`Pipeline`](crate::Pipeline).
+
+mod color;
+mod effects;
+mod geometry;
+
+pub use color::{
+    AlphaThreshold, Brightness, Color, Contrast, Gamma, Grayscale, HueShift, Invert, Opacity,
+    Premultiply, ReplaceColors, Tint, Unpremultiply,
+};
+pub use effects::{Blur, Outline, Overlay, Shadow};
+pub use geometry::{Anchor, Canvas, ClampHotspot, Flip, Rotate, Trim};
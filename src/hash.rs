@@ -0,0 +1,44 @@
+//! Implementation of `hash`.
+
+use crate::edit::read_cursor;
+use clap::Args;
+use std::{collections::HashMap, path::PathBuf};
+
+#[derive(Args)]
+pub(crate) struct HashArgs {
+    /// One or more Xcursor files to hash.
+    #[clap(value_parser, required = true)]
+    cursors: Vec<PathBuf>,
+
+    /// Only prints each file's hash, without the hash of each image.
+    #[clap(long)]
+    files_only: bool,
+}
+
+pub(crate) fn run(args: HashArgs) -> anyhow::Result<()> {
+    for path in &args.cursors {
+        let cursor = read_cursor(path)?;
+        println!("{:016x}  {}", cursor.content_hash(), path.display());
+
+        if args.files_only {
+            continue;
+        }
+
+        let mut frame_counts = HashMap::new();
+
+        for image in cursor.images() {
+            let index = frame_counts.entry(image.size()).or_insert(0);
+
+            println!(
+                "{:016x}  {} (size {}, frame {index})",
+                image.content_hash(),
+                path.display(),
+                image.size()
+            );
+
+            *index += 1;
+        }
+    }
+
+    Ok(())
+}
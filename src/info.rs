@@ -0,0 +1,269 @@
+//! Implementation of `info`.
+
+use crate::{estimate::describe, input};
+use anyhow::{anyhow, Context};
+use clap::Args;
+use resize_xcursor::{
+    read_xcursor::{CommentKind, ParsedComment},
+    transform::estimate,
+    validation::{size_mismatches, unusual_geometries},
+    Chunk, Image, Pipeline, ResizeOptions, TocEntry, XcursorReader,
+};
+use std::{collections::HashMap, path::PathBuf};
+
+#[derive(Args)]
+pub(crate) struct InfoArgs {
+    /// The Xcursor file to describe.
+    #[clap(value_parser)]
+    cursor: PathBuf,
+
+    /// Prints the raw header of every chunk instead, without parsing the
+    /// chunks themselves.
+    ///
+    /// This works even for files with broken chunks, and shows how long each
+    /// chunk claims to be compared to the space it actually has before the
+    /// next chunk (or the end of the file).
+    #[clap(long, verbatim_doc_comment)]
+    dump_chunks: bool,
+
+    /// Also prints how large the file would be once resized by `--scale`,
+    /// and roughly how much memory resizing it would take.
+    #[clap(long, requires = "scale", conflicts_with = "dump-chunks")]
+    estimate: bool,
+
+    /// The scale factor to estimate for, with `--estimate`.
+    #[clap(short, long, value_name = "N", requires = "estimate")]
+    scale: Option<u32>,
+}
+
+pub(crate) fn run(args: InfoArgs) -> anyhow::Result<()> {
+    let bytes = input::read(&args.cursor)
+        .with_context(|| format!("couldn't read {}", args.cursor.display()))?;
+
+    let reader = XcursorReader::new(&bytes).map_err(|err| {
+        anyhow!(
+            "{} isn't a valid Xcursor file: {err}",
+            args.cursor.display()
+        )
+    })?;
+    let header = reader.header();
+    let toc_len = reader.table_of_contents().len();
+    let toc_end = u64::from(header.header_size) + toc_len as u64 * u64::from(TocEntry::BYTE_LENGTH);
+
+    println!("{}: {} bytes", args.cursor.display(), bytes.len());
+    println!(
+        "header: {} bytes, version {:#010x}",
+        header.header_size, header.version
+    );
+    println!(
+        "table of contents: {} entries, bytes {}..{}",
+        toc_len, header.header_size, toc_end
+    );
+    println!();
+
+    if args.dump_chunks {
+        dump_chunks(&reader);
+        return Ok(());
+    }
+
+    // The header and table of contents were fine, so the chunks' headers
+    // can still be dumped
+    let chunks = reader
+        .chunks()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| {
+            anyhow!(
+                "{} isn't a valid Xcursor file: {err}\n\
+            (use --dump-chunks to see each chunk's header)",
+                args.cursor.display()
+            )
+        })?;
+
+    println!("{:>5}  {:>10}  {:>10}  chunk", "index", "offset", "length");
+
+    // Images with the same nominal size are frames of the same animation
+    let mut frames = HashMap::<u32, usize>::new();
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let description = match chunk {
+            Chunk::Image(image) => {
+                let frame = frames.entry(image.info.size()).or_default();
+                *frame += 1;
+
+                format!(
+                    "image: size {}, frame {}, {}x{}, hotspot {},{}, delay {}ms",
+                    image.info.size(),
+                    *frame - 1,
+                    image.info.width,
+                    image.info.height,
+                    image.info.xhot,
+                    image.info.yhot,
+                    image.info.delay
+                )
+            }
+
+            Chunk::Comment(comment) => {
+                let kind = match comment.kind() {
+                    CommentKind::Copyright => "copyright",
+                    CommentKind::License => "license",
+                    CommentKind::Other => "other",
+                };
+
+                format!("comment ({kind}): {:?}", comment.text_lossy())
+            }
+
+            Chunk::Unknown(chunk) => format!(
+                "unknown: type {:#010x}, subtype {}",
+                chunk.header.chunk_type, chunk.header.subtype
+            ),
+        };
+
+        println!(
+            "{:>5}  {:>10}  {:>10}  {}",
+            index,
+            chunk.position(),
+            chunk.byte_length(),
+            description
+        );
+    }
+
+    let end = chunks
+        .iter()
+        .map(|chunk| u64::from(chunk.position()) + chunk.byte_length())
+        .max()
+        .unwrap_or(toc_end)
+        .max(toc_end);
+
+    if let Some(trailing) = (bytes.len() as u64).checked_sub(end).filter(|&len| len > 0) {
+        println!();
+        println!("{trailing} bytes after the last chunk");
+    }
+
+    let layouts = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            Chunk::Image(image) => Some(image.info.layout()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mismatches = size_mismatches(layouts.iter().copied());
+    let geometries = unusual_geometries(layouts.iter().copied());
+
+    if !mismatches.is_empty() || !geometries.is_empty() {
+        println!();
+    }
+
+    for mismatch in mismatches {
+        println!(
+            "the images with a nominal size of {} are actually {}px (use `edit fix-size` to fix them)",
+            mismatch.size, mismatch.actual_size
+        );
+    }
+
+    // These can look wrong or fail to scale, even though they're allowed
+    for geometry in geometries {
+        println!("{geometry}");
+    }
+
+    if let Some(scale) = args.scale.filter(|_| args.estimate) {
+        let mut pipeline = Pipeline::new();
+        pipeline.push(ResizeOptions::new(scale));
+
+        let estimate = estimate(header, &chunks, &pipeline)
+            .with_context(|| format!("couldn't estimate the size of {}", args.cursor.display()))?;
+
+        println!();
+        println!(
+            "at scale {scale}: {}",
+            describe(bytes.len() as u64, estimate)
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the table of contents entry and raw header fields of every chunk.
+fn dump_chunks(reader: &XcursorReader<'_>) {
+    let bytes = reader.bytes();
+    let toc = reader.table_of_contents();
+
+    let field = |offset: u64| {
+        let offset = usize::try_from(offset).ok()?;
+        let field = bytes.get(offset..offset.checked_add(4)?)?;
+        Some(u32::from_le_bytes(field.try_into().unwrap()))
+    };
+
+    let show = |value: Option<u32>, format: fn(u32) -> String| {
+        value.map_or_else(|| "(past the end of the file)".to_owned(), format)
+    };
+
+    for (index, toc_entry) in toc.iter().enumerate() {
+        let position = u64::from(toc_entry.position);
+
+        println!(
+            "chunk {index} at byte {position}: type {:#010x}, subtype {} in the table of contents",
+            toc_entry.entry_type, toc_entry.subtype
+        );
+
+        let header_size = field(position);
+        let chunk_type = field(position + 4);
+        let subtype = field(position + 8);
+        let version = field(position + 12);
+
+        let mismatch = |value: Option<u32>, expected: u32| match value {
+            Some(value) if value != expected => " (doesn't match the table of contents)",
+            _ => "",
+        };
+
+        println!(
+            "    header size: {}",
+            show(header_size, |size| format!("{size} bytes"))
+        );
+        println!(
+            "    type: {}{}",
+            show(chunk_type, |chunk_type| format!("{chunk_type:#010x}")),
+            mismatch(chunk_type, toc_entry.entry_type)
+        );
+        println!(
+            "    subtype: {}{}",
+            show(subtype, |subtype| subtype.to_string()),
+            mismatch(subtype, toc_entry.subtype)
+        );
+        println!(
+            "    version: {}",
+            show(version, |version| version.to_string())
+        );
+
+        // Only images and comments say how long they are
+        let declared_length = match chunk_type {
+            Some(Image::TYPE) => Some(field(position + 16).zip(field(position + 20)).map(
+                |(width, height)| {
+                    u64::from(Image::HEADER_SIZE) + u64::from(width) * u64::from(height) * 4
+                },
+            )),
+            Some(ParsedComment::TYPE) => Some(
+                field(position + 16)
+                    .map(|len| u64::from(ParsedComment::HEADER_SIZE) + u64::from(len)),
+            ),
+            _ => None,
+        };
+
+        // The space before the next chunk starts, or before the end of the file
+        let actual_length = toc
+            .iter()
+            .map(|other| u64::from(other.position))
+            .filter(|&other| other > position)
+            .min()
+            .unwrap_or(bytes.len() as u64)
+            .min(bytes.len() as u64)
+            .saturating_sub(position);
+
+        let declared_length = match declared_length {
+            Some(Some(length)) => format!("{length} bytes declared"),
+            Some(None) => "declared past the end of the file".to_owned(),
+            None => "not declared".to_owned(),
+        };
+
+        println!("    length: {declared_length}, {actual_length} bytes available");
+    }
+}
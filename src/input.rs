@@ -0,0 +1,117 @@
+//! Reading input files, optionally by memory-mapping them.
+
+use clap::ValueEnum;
+use std::{
+    fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+/// The number of files that [`IoBackend::Readahead`] reads ahead of the one
+/// being processed.
+const READAHEAD_FILES: usize = 16;
+
+/// How input files are read when resizing many of them. Outputs are always
+/// written as soon as each file has been resized, whichever backend is used.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum IoBackend {
+    /// Reads each file when it's needed.
+    Standard,
+    /// Reads upcoming files on a background thread while the current one is
+    /// being processed.
+    Readahead,
+}
+
+/// The contents of an input file.
+pub(crate) enum Input {
+    Read(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Read(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map) => map,
+        }
+    }
+}
+
+/// Reads the file at `path`.
+///
+/// With the `mmap` feature, large files are memory-mapped instead of being
+/// copied into memory. The file mustn't be truncated while the result is
+/// alive, since that will crash this process; replacing it is fine.
+pub(crate) fn read(path: impl AsRef<Path>) -> io::Result<Input> {
+    #[cfg(feature = "mmap")]
+    {
+        // Mapping small files is slower than just reading them
+        const MMAP_THRESHOLD: u64 = 64 * 1024;
+
+        let file = fs::File::open(&path)?;
+
+        if file.metadata()?.len() >= MMAP_THRESHOLD {
+            // SAFETY: This is only unsound if the file is modified while it's
+            // mapped, which this program never does (see above). Other programs
+            // modifying cursors while they're being resized isn't supported.
+            return Ok(Input::Mapped(unsafe { memmap2::Mmap::map(&file)? }));
+        }
+    }
+
+    fs::read(path).map(Input::Read)
+}
+
+/// An input file that might not have been read yet, as returned by
+/// [`read_all`].
+pub(crate) enum PendingInput {
+    /// The file is only read by [`PendingInput::read`].
+    Unread(PathBuf),
+    /// The file was already read on a background thread.
+    Read(io::Result<Input>),
+}
+
+impl PendingInput {
+    /// Returns the file's contents, reading it first if that hasn't happened
+    /// yet.
+    pub(crate) fn read(self) -> io::Result<Input> {
+        match self {
+            Self::Unread(path) => read(path),
+            Self::Read(res) => res,
+        }
+    }
+}
+
+/// Returns every file in `paths`, in order, using the given backend.
+///
+/// With [`IoBackend::Standard`], files are only read by
+/// [`PendingInput::read`], so that whoever takes them from the iterator can
+/// read them without holding anything up.
+pub(crate) fn read_all(
+    paths: Vec<PathBuf>,
+    backend: IoBackend,
+) -> Box<dyn Iterator<Item = PendingInput> + Send> {
+    match backend {
+        IoBackend::Standard => Box::new(paths.into_iter().map(PendingInput::Unread)),
+
+        IoBackend::Readahead => {
+            let (sender, receiver) = mpsc::sync_channel(READAHEAD_FILES);
+
+            thread::spawn(move || {
+                for path in paths {
+                    // The receiver is dropped if processing stops early
+                    if sender.send(PendingInput::Read(read(path))).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            Box::new(receiver.into_iter())
+        }
+    }
+}
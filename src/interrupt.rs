@@ -0,0 +1,72 @@
+//! Handling Ctrl-C.
+//!
+//! Commands that go through many files call [`stop_between_files`], and check
+//! [`interrupted`] before each one. For them, the first Ctrl-C lets the
+//! current file finish before stopping, so that no file is left half-written,
+//! and a second Ctrl-C exits immediately. Every other command exits on the
+//! first Ctrl-C. Either way, any temporary files that were being written are
+//! removed before exiting.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+/// Set once Ctrl-C has been pressed.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Set by [`stop_between_files`].
+static STOPS_BETWEEN_FILES: AtomicBool = AtomicBool::new(false);
+
+/// Temporary files that are currently being written.
+static TEMP_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Installs the Ctrl-C handler.
+pub(crate) fn install() -> anyhow::Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) || !STOPS_BETWEEN_FILES.load(Ordering::SeqCst) {
+            for path in temp_files().iter() {
+                let _ = fs::remove_file(path);
+            }
+
+            // 128 + SIGINT, as a shell would report
+            process::exit(130);
+        }
+
+        eprintln!("interrupted, stopping after the current file (press Ctrl-C again to stop now)");
+    })?;
+
+    Ok(())
+}
+
+/// Makes the first Ctrl-C only set [`interrupted`], instead of exiting. The
+/// caller has to check it regularly.
+pub(crate) fn stop_between_files() {
+    STOPS_BETWEEN_FILES.store(true, Ordering::SeqCst);
+}
+
+/// Returns whether Ctrl-C has been pressed.
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Marks `path` as a temporary file to remove if this process is stopped
+/// by a second Ctrl-C.
+pub(crate) fn add_temp_file(path: &Path) {
+    temp_files().push(path.to_owned());
+}
+
+/// Removes a path added by [`add_temp_file`].
+pub(crate) fn remove_temp_file(path: &Path) {
+    temp_files().retain(|other| other != path);
+}
+
+fn temp_files() -> MutexGuard<'static, Vec<PathBuf>> {
+    // A panic while holding the lock doesn't leave the list inconsistent
+    TEMP_FILES.lock().unwrap_or_else(|err| err.into_inner())
+}
@@ -0,0 +1,94 @@
+//! A small JSON writer for the reports printed by `validate` and
+//! `theme lint`, which only ever hold strings, numbers, arrays and objects.
+
+/// Writes a JSON object to a string one field at a time, in the order the
+/// fields are given.
+pub(crate) struct Object<'a> {
+    out: &'a mut String,
+    empty: bool,
+}
+
+impl<'a> Object<'a> {
+    pub(crate) fn new(out: &'a mut String) -> Self {
+        out.push('{');
+        Self { out, empty: true }
+    }
+
+    pub(crate) fn string(&mut self, key: &str, value: &str) -> &mut Self {
+        write_string(self.key(key), value);
+        self
+    }
+
+    pub(crate) fn number(&mut self, key: &str, value: u64) -> &mut Self {
+        self.key(key).push_str(&value.to_string());
+        self
+    }
+
+    /// Writes an array of objects, each written by calling `write` with
+    /// one of `items`.
+    pub(crate) fn objects<T>(
+        &mut self,
+        key: &str,
+        items: &[T],
+        mut write: impl FnMut(&mut Object, &T),
+    ) -> &mut Self {
+        let out = self.key(key);
+        out.push('[');
+
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+
+            let mut object = Object::new(out);
+            write(&mut object, item);
+            object.finish();
+        }
+
+        out.push(']');
+        self
+    }
+
+    /// Writes an object, whose fields are written by `write`.
+    pub(crate) fn object(&mut self, key: &str, write: impl FnOnce(&mut Object)) -> &mut Self {
+        let mut object = Object::new(self.key(key));
+        write(&mut object);
+        object.finish();
+        self
+    }
+
+    pub(crate) fn finish(self) {
+        self.out.push('}');
+    }
+
+    /// Writes a field's key, leaving its value to be written next.
+    fn key(&mut self, key: &str) -> &mut String {
+        if !self.empty {
+            self.out.push(',');
+        }
+
+        self.empty = false;
+        write_string(self.out, key);
+        self.out.push(':');
+        self.out
+    }
+}
+
+/// Writes a string with JSON's escapes.
+fn write_string(out: &mut String, string: &str) {
+    out.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
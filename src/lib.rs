@@ -0,0 +1,56 @@
+//! Resizes Xcursor files.
+//!
+//! The simplest way to use this crate is [`resize`], which takes the bytes
+//! of an Xcursor file and returns a resized [`XcursorBuilder`] ready to be
+//! written out:
+//!
+//! ```no_run
+//! use resize_xcursor::ResizeOptions;
+//! use std::fs::{self, File};
+//!
+//! let cursor_bytes = fs::read("left_ptr")?;
+//! let cursor = resize_xcursor::resize(&cursor_bytes, &ResizeOptions::new(2))?;
+//! cursor.write_to(File::create("left_ptr-big")?)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Metadata types such as [`ImageInfo`] and [`TocEntry`] implement
+//! `serde`'s `Serialize` and `Deserialize`, so they can be shared with
+//! other tools as JSON (or any other format).
+
+pub mod animation;
+mod error;
+pub mod filters;
+mod pool;
+pub mod read_xcursor;
+pub mod resample;
+pub mod resize;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod sink;
+pub mod transform;
+pub mod validation;
+pub mod write_xcursor;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use error::{Error, ParseErrorKind, Result};
+pub use read_xcursor::{
+    parse, parse_chunks, Chunk, FileHeader, ImageInfo, Limits, ParsedImage, SeekReader,
+    XcursorInfo, XcursorReader,
+};
+pub use resample::{Frame, NearestNeighbor, Resampler};
+pub use resize::{resize, resize_images, resize_images_to, ResizeOptions};
+pub use sink::{MemorySink, OutputSink, SinkWriter};
+pub use transform::{
+    estimate, transform_chunks_to, transform_images, transform_images_to, transform_seekable_to,
+    Estimate, Pipeline, Transform,
+};
+pub use write_xcursor::{
+    DuplicateSizePolicy, Image, ImageLayout, RawChunk, StreamingWriter, TocEntry, XcursorBuilder,
+};
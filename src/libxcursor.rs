@@ -0,0 +1,268 @@
+//! Loading written cursors through the system's libXcursor, for
+//! `--check-libxcursor`. Enabled by the `libxcursor` feature.
+//!
+//! libXcursor is loaded at runtime, so this builds without its development
+//! files and fails with a clear message on systems without it.
+
+use crate::input;
+use anyhow::{bail, Context};
+use resize_xcursor::parse;
+use std::{
+    ffi::{c_char, c_int, c_long, c_uint, c_void, CStr},
+    path::{Path, PathBuf},
+    ptr, slice,
+};
+
+/// A loaded image, laid out like libXcursor's `XcursorImage`.
+#[repr(C)]
+struct XcursorImage {
+    _version: c_uint,
+    size: c_uint,
+    width: c_uint,
+    height: c_uint,
+    xhot: c_uint,
+    yhot: c_uint,
+    delay: c_uint,
+    pixels: *const c_uint,
+}
+
+/// Every image loaded from a file, laid out like libXcursor's
+/// `XcursorImages`.
+#[repr(C)]
+struct XcursorImages {
+    nimage: c_int,
+    images: *const *const XcursorImage,
+    _name: *const c_char,
+}
+
+/// A source for libXcursor to read a file from, laid out like its
+/// `XcursorFile`. `closure` points to a [`MemoryFile`].
+#[repr(C)]
+struct XcursorFile {
+    closure: *mut c_void,
+    read: unsafe extern "C" fn(*mut XcursorFile, *mut u8, c_int) -> c_int,
+    write: unsafe extern "C" fn(*mut XcursorFile, *const u8, c_int) -> c_int,
+    seek: unsafe extern "C" fn(*mut XcursorFile, c_long, c_int) -> c_int,
+}
+
+/// The bytes of a file being read by libXcursor, and how far it's read.
+struct MemoryFile<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+type LoadAllImages = unsafe extern "C" fn(*mut XcursorFile) -> *mut XcursorImages;
+type ImagesDestroy = unsafe extern "C" fn(*mut XcursorImages);
+
+/// The functions used from the system's libXcursor.
+pub(crate) struct Libxcursor {
+    load_all_images: LoadAllImages,
+    images_destroy: ImagesDestroy,
+}
+
+impl Libxcursor {
+    /// Loads the system's libXcursor. It's never unloaded.
+    pub(crate) fn open() -> anyhow::Result<Self> {
+        // SAFETY: libXcursor doesn't run anything when it's loaded, and the
+        // symbols are only used with the signatures libXcursor gives them
+        unsafe {
+            let library = libc::dlopen(c"libXcursor.so.1".as_ptr(), libc::RTLD_NOW);
+
+            if library.is_null() {
+                bail!(
+                    "couldn't load libXcursor for --check-libxcursor: {}",
+                    dl_error()
+                );
+            }
+
+            let symbol = |name: &CStr| {
+                let symbol = libc::dlsym(library, name.as_ptr());
+
+                match symbol.is_null() {
+                    true => bail!(
+                        "libXcursor doesn't have `{}`: {}",
+                        name.to_string_lossy(),
+                        dl_error()
+                    ),
+                    false => Ok(symbol),
+                }
+            };
+
+            Ok(Self {
+                load_all_images: std::mem::transmute::<*mut c_void, LoadAllImages>(symbol(
+                    c"XcursorXcFileLoadAllImages",
+                )?),
+                images_destroy: std::mem::transmute::<*mut c_void, ImagesDestroy>(symbol(
+                    c"XcursorImagesDestroy",
+                )?),
+            })
+        }
+    }
+
+    /// Reads each written output back and checks that libXcursor loads
+    /// every image in it, the same way this crate reads them.
+    pub(crate) fn check_all(&self, outputs: &[PathBuf]) -> anyhow::Result<()> {
+        for output in outputs {
+            let bytes = input::read(output)
+                .with_context(|| format!("couldn't read {}", output.display()))?;
+            self.check(output, &bytes)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that libXcursor loads every image in a file, with the same
+    /// nominal size, dimensions, hotspot, delay, and pixels that
+    /// [`parse`] reads.
+    fn check(&self, path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+        let expected =
+            parse(bytes).with_context(|| format!("couldn't read {} back", path.display()))?;
+
+        let mut memory_file = MemoryFile { bytes, position: 0 };
+        let mut file = XcursorFile {
+            closure: ptr::addr_of_mut!(memory_file).cast(),
+            read: read_memory_file,
+            write: write_memory_file,
+            seek: seek_memory_file,
+        };
+
+        // SAFETY: `file` and `memory_file` outlive the call, and libXcursor
+        // only calls back into them while loading
+        let images = unsafe { (self.load_all_images)(&mut file) };
+
+        if images.is_null() {
+            bail!(
+                "libXcursor couldn't load {} (run `validate` on it to find out why)",
+                path.display()
+            );
+        }
+
+        // SAFETY: libXcursor returned these images, and they're only
+        // destroyed afterwards
+        let res = unsafe {
+            let images = slice::from_raw_parts((*images).images, (*images).nimage as usize);
+            compare(path, &expected, images)
+        };
+
+        // SAFETY: `images` came from `XcursorXcFileLoadAllImages`, and
+        // nothing refers to it anymore
+        unsafe { (self.images_destroy)(images) };
+        res
+    }
+}
+
+/// Compares the images libXcursor loaded with the ones this crate read.
+///
+/// # Safety
+/// Every image, and its pixels, must be valid for reads.
+unsafe fn compare(
+    path: &Path,
+    expected: &[resize_xcursor::ParsedImage],
+    images: &[*const XcursorImage],
+) -> anyhow::Result<()> {
+    if images.len() != expected.len() {
+        bail!(
+            "libXcursor loads {} images from {}, instead of {}",
+            images.len(),
+            path.display(),
+            expected.len()
+        );
+    }
+
+    for (index, (expected, &image)) in expected.iter().zip(images).enumerate() {
+        let image = &*image;
+        let info = &expected.info;
+
+        let fields_match = (
+            image.size,
+            image.width,
+            image.height,
+            image.xhot,
+            image.yhot,
+            image.delay,
+        ) == (
+            info.size(),
+            info.width,
+            info.height,
+            info.xhot,
+            info.yhot,
+            info.delay,
+        );
+
+        // libXcursor doesn't load images without pixels, so `pixels` is
+        // never null here
+        let pixel_count = image.width as usize * image.height as usize;
+
+        if !fields_match
+            || !slice::from_raw_parts(image.pixels, pixel_count)
+                .iter()
+                .copied()
+                .eq(expected.pixels())
+        {
+            bail!(
+                "libXcursor loads image {index} (with a nominal size of {}) from {} differently",
+                info.size(),
+                path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the last error from `dlopen` or `dlsym`.
+unsafe fn dl_error() -> String {
+    let err = libc::dlerror();
+
+    match err.is_null() {
+        true => "unknown error".to_owned(),
+        false => CStr::from_ptr(err).to_string_lossy().into_owned(),
+    }
+}
+
+/// Reads up to `len` bytes, returning how many were read like `fread`.
+unsafe extern "C" fn read_memory_file(
+    file: *mut XcursorFile,
+    buffer: *mut u8,
+    len: c_int,
+) -> c_int {
+    let memory_file = &mut *(*file).closure.cast::<MemoryFile>();
+    let remaining = memory_file
+        .bytes
+        .get(memory_file.position..)
+        .unwrap_or_default();
+    let len = remaining.len().min(len.max(0) as usize);
+
+    ptr::copy_nonoverlapping(remaining.as_ptr(), buffer, len);
+    memory_file.position += len;
+    len as c_int
+}
+
+/// libXcursor never writes while loading, so this always fails.
+unsafe extern "C" fn write_memory_file(_: *mut XcursorFile, _: *const u8, _: c_int) -> c_int {
+    -1
+}
+
+/// Moves to a new position, returning 0 on success like `fseek`.
+unsafe extern "C" fn seek_memory_file(
+    file: *mut XcursorFile,
+    offset: c_long,
+    whence: c_int,
+) -> c_int {
+    let memory_file = &mut *(*file).closure.cast::<MemoryFile>();
+
+    let base = match whence {
+        libc::SEEK_SET => 0,
+        libc::SEEK_CUR => memory_file.position as c_long,
+        libc::SEEK_END => memory_file.bytes.len() as c_long,
+        _ => return -1,
+    };
+
+    match usize::try_from(base + offset) {
+        Ok(position) => {
+            memory_file.position = position;
+            0
+        }
+        Err(_) => -1,
+    }
+}
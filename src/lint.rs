@@ -0,0 +1,345 @@
+//! The checks run by `validate` and `theme lint`, and the reports they
+//! print.
+//!
+//! Each problem is a [`Finding`] with a stable rule id, such as
+//! `format.chunk_out_of_bounds` or `delay.zero`, made of a category and the
+//! name of the problem's kind. The kind and the finding's details are read
+//! from the problem's `Serialize` impl (see [`details`]), so new kinds are
+//! named the same way everywhere. With `--format json`, findings are printed
+//! as a single report, whose schema is described in the README. Its
+//! `version` is only increased when existing fields or rule ids change, so
+//! new fields and rules can appear without it changing.
+
+use crate::{
+    details::{self, Detail, Details},
+    json,
+};
+use clap::ValueEnum;
+use resize_xcursor::{
+    parse_chunks,
+    validation::{round_trip, unusual_delays, unusual_geometries, validate, GeometryKind},
+    Chunk, Error, ImageInfo, ParseErrorKind,
+};
+use serde::Serialize;
+use std::{fmt::Display, path::Path};
+
+/// The version of the JSON report's schema.
+const REPORT_VERSION: u64 = 1;
+
+/// How the findings of `validate` and `theme lint` are printed.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ReportFormat {
+    /// One line per finding, followed by a summary.
+    Text,
+    /// A single JSON report on standard output, described in the README.
+    Json,
+}
+
+/// How serious a finding is. Errors make `validate` and `theme lint` fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+/// A problem with a cursor.
+pub(crate) struct Finding {
+    rule: String,
+    severity: Severity,
+    file: String,
+    cursor: String,
+    /// The same message printed with `--format text`.
+    message: String,
+    details: Details,
+}
+
+impl Finding {
+    /// Makes a finding out of a problem, using its message.
+    pub(crate) fn new<P: Serialize + Display>(
+        category: &str,
+        severity: Severity,
+        path: &Path,
+        cursor: &str,
+        problem: &P,
+    ) -> Self {
+        Self::with_message(
+            category,
+            severity,
+            path,
+            cursor,
+            problem.to_string(),
+            problem,
+        )
+    }
+
+    /// Like [`Finding::new`], but with a message other than the problem's.
+    pub(crate) fn with_message(
+        category: &str,
+        severity: Severity,
+        path: &Path,
+        cursor: &str,
+        message: String,
+        problem: &impl Serialize,
+    ) -> Self {
+        let (kind, details) =
+            details::describe(problem).expect("problems should be described by their kind");
+
+        Self {
+            rule: format!("{category}.{kind}"),
+            severity,
+            file: path.display().to_string(),
+            cursor: cursor.to_owned(),
+            message,
+            details,
+        }
+    }
+
+    /// Prints the finding the way `--format text` does.
+    pub(crate) fn print(&self) {
+        match self.severity {
+            Severity::Error => println!("{}: {}", self.file, self.message),
+            Severity::Warning => println!("{}: warning: {}", self.file, self.message),
+        }
+    }
+
+    fn write_json(&self, object: &mut json::Object) {
+        object
+            .string("rule", &self.rule)
+            .string("severity", self.severity.name())
+            .string("file", &self.file)
+            .string("cursor", &self.cursor)
+            .string("message", &self.message)
+            .object("details", |details| {
+                for (key, value) in &self.details {
+                    match value {
+                        Detail::Number(number) => details.number(key, *number),
+                        Detail::String(string) => details.string(key, string),
+                    };
+                }
+            });
+    }
+}
+
+/// Why a file that looked valid couldn't be parsed for `--round-trip`.
+#[derive(Serialize)]
+struct ParseFailure<'a> {
+    offset: usize,
+    kind: &'a ParseErrorKind,
+}
+
+/// Why a file that looked valid couldn't be rewritten for `--round-trip`,
+/// other than failing to parse it.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RewriteFailure {
+    Unwritable,
+}
+
+/// Checks a cursor the way `validate` does, returning every finding in the
+/// order they're printed.
+///
+/// Violations of the format are errors. With `round_trip`, files that are
+/// otherwise valid but would change when rewritten are errors too. Unusual
+/// geometries and delays are only warnings.
+pub(crate) fn check_cursor(
+    path: &Path,
+    cursor: &str,
+    bytes: &[u8],
+    round_trip: bool,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for violation in validate(bytes) {
+        findings.push(Finding::new(
+            "format",
+            Severity::Error,
+            path,
+            cursor,
+            &violation,
+        ));
+    }
+
+    // Files that aren't valid can't be expected to round-trip
+    if findings.is_empty() && round_trip {
+        if let Some(finding) = check_round_trip(path, cursor, bytes) {
+            findings.push(finding);
+        }
+    }
+
+    // These are allowed, so they're only warned about. Images larger than
+    // their nominal size are already reported as size mismatches.
+    let images = parse_chunks(bytes)
+        .map(|chunks| {
+            chunks
+                .iter()
+                .filter_map(|chunk| match chunk {
+                    Chunk::Image(image) => Some(image.info),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    for geometry in unusual_geometries(images.iter().map(ImageInfo::layout)) {
+        if geometry.kind != GeometryKind::LargerThanNominal {
+            findings.push(Finding::new(
+                "geometry",
+                Severity::Warning,
+                path,
+                cursor,
+                &geometry,
+            ));
+        }
+    }
+
+    let frames = images.iter().map(|image| (image.size(), image.delay));
+
+    for delay in unusual_delays(frames) {
+        findings.push(Finding::new(
+            "delay",
+            Severity::Warning,
+            path,
+            cursor,
+            &delay,
+        ));
+    }
+
+    findings
+}
+
+/// Checks that a valid cursor is rewritten byte for byte.
+fn check_round_trip(path: &Path, cursor: &str, bytes: &[u8]) -> Option<Finding> {
+    let finding = match round_trip(bytes) {
+        Ok(None) => return None,
+        Ok(Some(divergence)) => Finding::with_message(
+            "round_trip",
+            Severity::Error,
+            path,
+            cursor,
+            format!("changes when rewritten: {divergence}"),
+            &divergence,
+        ),
+        Err(Error::Parse { offset, kind }) => Finding::with_message(
+            "parse",
+            Severity::Error,
+            path,
+            cursor,
+            format!("couldn't be rewritten: {} (at byte {offset})", kind),
+            &ParseFailure {
+                offset,
+                kind: &kind,
+            },
+        ),
+        Err(err) => Finding::with_message(
+            "round_trip",
+            Severity::Error,
+            path,
+            cursor,
+            format!("couldn't be rewritten: {err}"),
+            &RewriteFailure::Unwritable,
+        ),
+    };
+
+    Some(finding)
+}
+
+/// Every finding about a set of files.
+pub(crate) struct Report {
+    version: u64,
+    /// The number of files checked.
+    files: usize,
+    errors: usize,
+    warnings: usize,
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    pub(crate) fn new(files: usize, findings: Vec<Finding>) -> Self {
+        let count = |severity| {
+            findings
+                .iter()
+                .filter(|finding| finding.severity == severity)
+                .count()
+        };
+
+        Self {
+            version: REPORT_VERSION,
+            files,
+            errors: count(Severity::Error),
+            warnings: count(Severity::Warning),
+            findings,
+        }
+    }
+
+    /// Returns the number of files with at least one error.
+    pub(crate) fn failed_files(&self) -> usize {
+        let mut files = self
+            .findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .map(|finding| &finding.file)
+            .collect::<Vec<_>>();
+
+        files.sort();
+        files.dedup();
+        files.len()
+    }
+
+    /// Prints the report as JSON, on a single line.
+    pub(crate) fn print_json(&self) {
+        let mut out = String::new();
+        let mut report = json::Object::new(&mut out);
+
+        report
+            .number("version", self.version)
+            .number("files", self.files as u64)
+            .number("errors", self.errors as u64)
+            .number("warnings", self.warnings as u64)
+            .objects("findings", &self.findings, |object, finding| {
+                finding.write_json(object)
+            });
+
+        report.finish();
+        println!("{out}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_failure_details() {
+        let kind = ParseErrorKind::TooManyChunks { count: 2, max: 1 };
+        let finding = Finding::with_message(
+            "parse",
+            Severity::Error,
+            Path::new("left_ptr"),
+            "left_ptr",
+            String::new(),
+            &ParseFailure {
+                offset: 12,
+                kind: &kind,
+            },
+        );
+
+        assert_eq!(finding.rule, "parse.too_many_chunks");
+        assert_eq!(
+            finding.details,
+            [
+                ("offset", Detail::Number(12)),
+                ("count", Detail::Number(2)),
+                ("max", Detail::Number(1)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Implementation of `list`.
+
+use crate::{estimate::format_bytes, input};
+use anyhow::Context;
+use clap::Args;
+use resize_xcursor::XcursorReader;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+#[derive(Args)]
+pub(crate) struct ListArgs {
+    /// Xcursor files, or theme directories to list every cursor in.
+    ///
+    /// Directories with a `cursors` directory in them are treated as
+    /// themes. Symlinks to other cursors in a directory are left out.
+    #[clap(value_parser, required = true, verbatim_doc_comment)]
+    paths: Vec<PathBuf>,
+
+    /// Only lists animated cursors.
+    #[clap(long, conflicts_with = "static-only")]
+    animated_only: bool,
+
+    /// Only lists cursors that aren't animated.
+    #[clap(long)]
+    static_only: bool,
+
+    /// Only lists cursors whose largest nominal size is at least this.
+    #[clap(long, value_name = "SIZE")]
+    min_size: Option<u32>,
+
+    /// Only lists cursors whose largest nominal size is at most this.
+    #[clap(long, value_name = "SIZE")]
+    max_size: Option<u32>,
+
+    /// Only lists cursors without any images with this nominal size.
+    #[clap(long, value_name = "SIZE")]
+    missing_size: Option<u32>,
+}
+
+impl ListArgs {
+    /// Returns whether a cursor passes every filter.
+    fn selects(&self, listing: &Listing) -> bool {
+        let largest_size = listing.largest_size();
+
+        !(self.animated_only && !listing.is_animated()
+            || self.static_only && listing.is_animated()
+            || self.min_size.is_some_and(|size| largest_size < size)
+            || self.max_size.is_some_and(|size| largest_size > size)
+            || self
+                .missing_size
+                .is_some_and(|size| listing.frames.contains_key(&size)))
+    }
+}
+
+/// The facts about a cursor that are listed.
+struct Listing {
+    path: PathBuf,
+    bytes: u64,
+    /// The number of frames of each nominal size, in order of nominal size.
+    frames: BTreeMap<u32, usize>,
+}
+
+impl Listing {
+    fn is_animated(&self) -> bool {
+        self.frames.values().any(|&frames| frames > 1)
+    }
+
+    fn largest_size(&self) -> u32 {
+        self.frames.keys().copied().max().unwrap_or(0)
+    }
+}
+
+pub(crate) fn run(args: ListArgs) -> anyhow::Result<()> {
+    let mut listings = Vec::new();
+
+    for path in cursor_paths(&args.paths)? {
+        let bytes =
+            input::read(&path).with_context(|| format!("couldn't read {}", path.display()))?;
+
+        // Only image headers are needed, so pixels don't have to be read
+        let images = XcursorReader::new(&bytes)
+            .and_then(|reader| reader.image_infos().collect::<Result<Vec<_>, _>>());
+
+        let images = match images {
+            Ok(images) => images,
+            Err(err) => {
+                warn!(
+                    "{} isn't a valid Xcursor file, skipping: {err}",
+                    path.display()
+                );
+                continue;
+            }
+        };
+
+        let mut frames = BTreeMap::new();
+
+        for image in &images {
+            *frames.entry(image.size()).or_default() += 1;
+        }
+
+        let listing = Listing {
+            path,
+            bytes: bytes.len() as u64,
+            frames,
+        };
+
+        if args.selects(&listing) {
+            listings.push(listing);
+        }
+    }
+
+    if listings.is_empty() {
+        println!("no cursors found");
+        return Ok(());
+    }
+
+    let rows = listings
+        .iter()
+        .map(|listing| {
+            let sizes = listing
+                .frames
+                .keys()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+
+            // Animations usually have as many frames in each nominal size,
+            // so they're only counted separately when they don't
+            let mut frame_counts = listing.frames.values().copied().collect::<Vec<_>>();
+            frame_counts.dedup();
+
+            let frames = match frame_counts.as_slice() {
+                _ if !listing.is_animated() => "static".to_owned(),
+                [frames] => frames.to_string(),
+                _ => listing
+                    .frames
+                    .iter()
+                    .map(|(size, frames)| format!("{frames} ({size}px)"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+
+            (
+                listing.path.display().to_string(),
+                format_bytes(listing.bytes),
+                sizes,
+                frames,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let path_width = column_width(rows.iter().map(|row| &row.0), "cursor");
+    let sizes_width = column_width(rows.iter().map(|row| &row.2), "sizes");
+
+    println!(
+        "{:<path_width$}  {:>10}  {:<sizes_width$}  frames",
+        "cursor", "size", "sizes"
+    );
+
+    for (path, bytes, sizes, frames) in &rows {
+        println!("{path:<path_width$}  {bytes:>10}  {sizes:<sizes_width$}  {frames}");
+    }
+
+    Ok(())
+}
+
+/// Returns the width of a column with the given header and values.
+fn column_width<'a>(values: impl Iterator<Item = &'a String>, header: &str) -> usize {
+    values.map(String::len).max().unwrap_or(0).max(header.len())
+}
+
+/// Returns every file to list: each path that's a file, and every file in
+/// each directory (or in its `cursors` directory, for themes), sorted by name.
+fn cursor_paths(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut res = Vec::new();
+
+    for path in paths {
+        if !path.is_dir() {
+            res.push(path.clone());
+            continue;
+        }
+
+        let cursors = path.join("cursors");
+        let dir = match cursors.is_dir() {
+            true => &cursors,
+            false => path,
+        };
+
+        res.extend(dir_files(dir)?);
+    }
+
+    Ok(res)
+}
+
+/// Returns the files in a directory that aren't symlinks, sorted by name.
+fn dir_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir).with_context(|| format!("couldn't read {}", dir.display()))? {
+        let entry = entry?;
+
+        if entry.file_type()?.is_file() {
+            files.push(entry.path());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
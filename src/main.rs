@@ -1,26 +1,337 @@
-#![feature(array_chunks)]
+mod compare;
+mod details;
+mod edit;
+mod estimate;
+mod hash;
+mod info;
+mod input;
+mod interrupt;
+mod json;
+#[cfg(feature = "libxcursor")]
+mod libxcursor;
+mod lint;
+mod list;
+mod output;
+#[cfg(feature = "png")]
+mod pipe_filter;
+#[cfg(feature = "png")]
+mod png;
+mod preset;
+mod repair;
+#[cfg(feature = "parallel")]
+mod schedule;
+#[cfg(feature = "themes")]
+mod theme;
+mod timings;
+mod validate;
 
-mod write_xcursor;
-
-use anyhow::bail;
-use clap::Parser;
+use anyhow::{anyhow, bail, Context};
+use clap::{Parser, Subcommand, ValueEnum};
+use resize_xcursor::{
+    animation::{merge_identical_chunks, ClampDelays, ScaleDelays},
+    filters::{
+        AlphaThreshold, Anchor, Blur, Brightness, Canvas, ClampHotspot, Color, Contrast, Flip,
+        Gamma, Grayscale, HueShift, Invert, Opacity, Outline, Premultiply, ReplaceColors, Rotate,
+        Shadow, Tint, Trim, Unpremultiply,
+    },
+    read_xcursor::CommentKind,
+    transform::Transform,
+    transform_chunks_to, transform_seekable_to,
+    validation::{self, KeepDuplicate},
+    Chunk, Error, FileHeader, ImageInfo, Limits, Pipeline, RawChunk, ResizeOptions, SeekReader,
+    SinkWriter, XcursorReader,
+};
 use std::{
+    borrow::Cow,
+    collections::BTreeMap,
     fs::{self, File},
-    iter,
-    path::PathBuf,
+    io::{self, BufReader, IsTerminal},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
-use write_xcursor::{Image, Xcursor};
-use xcursor::parser::parse_xcursor;
+use tracing::{error, info, info_span, warn};
+use tracing_subscriber::EnvFilter;
 
 /// Resizes Xcursor files
 #[derive(Parser)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// The number of threads to use. Defaults to the number of CPUs.
+    ///
+    /// Files are resized in parallel, starting with the largest, unless
+    /// `--low-memory` or `--timings` is given. Either way, files are
+    /// reported (and written to stdout) in the order they were given.
+    #[cfg(feature = "parallel")]
+    #[clap(short, long, global = true, verbatim_doc_comment)]
+    jobs: Option<usize>,
+
+    /// Prints more information about what's happening. Can be given up to
+    /// three times for even more detail.
+    ///
+    /// The `RESIZE_XCURSOR_LOG` environment variable can be used instead,
+    /// and accepts `tracing-subscriber` filter directives (such as `debug`).
+    #[clap(short, long, global = true, action = clap::ArgAction::Count, verbatim_doc_comment)]
+    verbose: u8,
+
     /// The scale factor to apply to each cursor.
     ///
     /// For example, a scale of 2 applied to a 32x32 pixel cursor will
-    /// result in a 64x64 pixel cursor.
-    #[clap(short, long, verbatim_doc_comment)]
-    scale: u32,
+    /// result in a 64x64 pixel cursor. This can be left out when `--preset`
+    /// is given.
+    ///
+    /// A scale of 1 rewrites files without changing them, as long as they
+    /// pass `validate --round-trip`.
+    #[clap(short, long, required_unless_present = "preset", verbatim_doc_comment)]
+    // This is only optional so that it can be omitted when using a subcommand
+    scale: Option<u32>,
+
+    /// Multiplies every frame's delay by this factor while resizing.
+    ///
+    /// For example, `--speed 2` makes animations twice as slow, while
+    /// `--speed 0.5` makes them twice as fast.
+    #[clap(long, value_name = "F", value_parser = parse_factor, verbatim_doc_comment)]
+    speed: Option<f64>,
+
+    /// Raises every frame's delay to at least this many milliseconds, after
+    /// `--speed`.
+    ///
+    /// Animations with 0ms delays make compositors redraw the cursor as fast
+    /// as they can.
+    #[clap(long, value_name = "MS", verbatim_doc_comment)]
+    min_delay: Option<u32>,
+
+    /// Lowers every frame's delay to at most this many milliseconds, after
+    /// `--speed`.
+    ///
+    /// Animations with very long delays look like they've frozen.
+    #[clap(long, value_name = "MS", verbatim_doc_comment)]
+    max_delay: Option<u32>,
+
+    /// If given, mirrors every image left to right, along with its hotspot.
+    #[clap(long)]
+    flip_h: bool,
+
+    /// If given, mirrors every image top to bottom, along with its hotspot.
+    #[clap(long)]
+    flip_v: bool,
+
+    /// Rotates every image clockwise by 90, 180, or 270 degrees, along with
+    /// its hotspot.
+    #[clap(long, value_name = "DEGREES", value_parser = parse_rotation, verbatim_doc_comment)]
+    rotate: Option<Rotate>,
+
+    /// If given, crops fully transparent margins off of every image, moving
+    /// its hotspot to match.
+    ///
+    /// Each frame of an animation is cropped separately.
+    #[clap(long, verbatim_doc_comment)]
+    trim: bool,
+
+    /// Places every image on a transparent canvas of this size (such as
+    /// `64x64`), moving its hotspot to match.
+    ///
+    /// This fails if an image is larger than the canvas.
+    #[clap(long, value_name = "WxH", value_parser = parse_dimensions, verbatim_doc_comment)]
+    canvas: Option<(u32, u32)>,
+
+    /// Where to place each image on the canvas given by `--canvas`:
+    /// `center`, `topleft`, `bottomright`, or the coordinates of the image's
+    /// top left corner (as `X,Y`).
+    #[clap(
+        long,
+        value_parser = parse_anchor,
+        default_value = "center",
+        requires = "canvas",
+        verbatim_doc_comment
+    )]
+    anchor: Anchor,
+
+    /// Replaces a color with another, given as `OLD=NEW` (such as
+    /// `#3584E4=#E66100`). Can be given more than once.
+    ///
+    /// Antialiased edges drawn in the old color are replaced as well.
+    #[clap(
+        long = "replace-color",
+        value_name = "OLD=NEW",
+        value_parser = parse_color_pair,
+        verbatim_doc_comment
+    )]
+    replace_colors: Vec<(Color, Color)>,
+
+    /// Replaces colors according to a palette file, which has one `OLD=NEW`
+    /// pair per line, just like `--replace-color`:
+    ///
+    ///     // Blue accents become orange
+    ///     #3584E4=#E66100
+    ///     #1C71D8=#C64600
+    ///
+    /// Blank lines and lines starting with `//` are ignored. Colors given to
+    /// `--replace-color` take priority over the palette file.
+    #[clap(long, value_name = "FILE", value_parser, verbatim_doc_comment)]
+    palette_map: Option<PathBuf>,
+
+    /// How far each channel of a pixel's color can be from a color given to
+    /// `--replace-color` (from 0 to 255) for it to be replaced. Pixels that
+    /// are close to the old color keep their difference from it.
+    #[clap(long, value_name = "N", default_value_t = 0, verbatim_doc_comment)]
+    color_tolerance: u8,
+
+    /// Rotates the hue of every pixel by this many degrees, keeping its
+    /// saturation and brightness.
+    ///
+    /// For example, `--hue-shift 60` turns blue accents purple.
+    #[clap(
+        long,
+        value_name = "DEG",
+        value_parser = parse_hue_shift,
+        allow_hyphen_values = true,
+        verbatim_doc_comment
+    )]
+    hue_shift: Option<f32>,
+
+    /// Blends a color into every pixel that isn't fully transparent, given
+    /// as `#RRGGBB` with an optional strength from 0 to 1 (such as
+    /// `#3584E4:0.8`). The strength defaults to 0.5.
+    #[clap(long, value_name = "COLOR[:STRENGTH]", value_parser = parse_tint, verbatim_doc_comment)]
+    tint: Option<Tint>,
+
+    /// If given, inverts the color of every pixel, keeping its alpha. This
+    /// turns a black cursor with a white outline into a white one with a
+    /// black outline.
+    #[clap(long, verbatim_doc_comment)]
+    invert: bool,
+
+    /// Desaturates every pixel by the given amount, from 0 (not at all) to 1
+    /// (fully gray). If no amount is given, as in `--grayscale`, it
+    /// defaults to 1.
+    #[clap(
+        long,
+        value_name = "AMOUNT",
+        value_parser = parse_amount,
+        min_values = 0,
+        max_values = 1,
+        require_equals = true,
+        default_missing_value = "1",
+        verbatim_doc_comment
+    )]
+    grayscale: Option<f32>,
+
+    /// Multiplies the brightness of every pixel by this factor.
+    ///
+    /// For example, `--brightness 1.2` makes a theme 20% brighter.
+    #[clap(long, value_name = "F", value_parser = parse_factor, verbatim_doc_comment)]
+    brightness: Option<f64>,
+
+    /// Scales the contrast of every pixel by this factor, moving its colors
+    /// away from (or, below 1, towards) middle gray.
+    #[clap(long, value_name = "F", value_parser = parse_factor, verbatim_doc_comment)]
+    contrast: Option<f64>,
+
+    /// Applies gamma correction to every pixel. Values above 1 brighten
+    /// midtones, which helps with artwork that looks too dark, while values
+    /// below 1 darken them.
+    #[clap(long, value_name = "G", value_parser = parse_factor, verbatim_doc_comment)]
+    gamma: Option<f64>,
+
+    /// Multiplies the opacity of every pixel by this amount, from 0 (fully
+    /// transparent) to 1 (unchanged).
+    ///
+    /// For example, `--opacity 0.8` makes a translucent "ghost" cursor.
+    #[clap(long, value_name = "AMOUNT", value_parser = parse_amount, verbatim_doc_comment)]
+    opacity: Option<f32>,
+
+    /// Softens every image with a Gaussian blur that reaches this many pixels
+    /// in each direction. Images keep their dimensions, so use `--canvas`
+    /// to make room for the blur if needed.
+    #[clap(long, value_name = "RADIUS", verbatim_doc_comment)]
+    blur: Option<u32>,
+
+    /// Draws a soft shadow underneath every image, given as
+    /// `DX,DY,BLUR,COLOR` (such as `2,2,3,#00000080`). The shadow is
+    /// moved `DX` pixels right and `DY` pixels down, and its edges are
+    /// blurred over `BLUR` pixels.
+    ///
+    /// Images are enlarged to fit their shadow, and their hotspots are moved
+    /// to match.
+    #[clap(
+        long,
+        value_name = "DX,DY,BLUR,COLOR",
+        value_parser = parse_shadow,
+        allow_hyphen_values = true,
+        verbatim_doc_comment
+    )]
+    shadow: Option<Shadow>,
+
+    /// Draws an outline around the visible parts of every image, given as
+    /// `WIDTH,COLOR` (such as `1,#FFFFFF`). This makes small cursors easier
+    /// to see on busy backgrounds.
+    ///
+    /// Images are enlarged to fit their outline, and their hotspots are
+    /// moved to match.
+    #[clap(
+        long,
+        value_name = "WIDTH,COLOR",
+        value_parser = parse_outline,
+        verbatim_doc_comment
+    )]
+    outline: Option<Outline>,
+
+    /// Applies a combination of transforms after any others. `accessibility`
+    /// adds a thick outline that contrasts with each image and a subtle
+    /// shadow, and doubles the size of each cursor unless `--scale` is
+    /// given.
+    #[clap(
+        long,
+        value_enum,
+        conflicts_with_all = &["outline", "shadow"],
+        verbatim_doc_comment
+    )]
+    preset: Option<preset::Preset>,
+
+    /// Draws a PNG on top of every image, such as a badge. It's placed in the
+    /// bottom right corner unless an anchor is given after a colon, such as
+    /// `badge.png:topleft` or `badge.png:4,4` (see `--anchor`).
+    ///
+    /// The PNG isn't resized, and any part of it that doesn't fit on an image
+    /// is cut off.
+    #[cfg(feature = "png")]
+    #[clap(
+        long,
+        value_name = "PNG[:ANCHOR]",
+        value_parser = parse_overlay,
+        verbatim_doc_comment
+    )]
+    overlay: Option<(PathBuf, Anchor)>,
+
+    /// Makes pixels with at least this alpha (from 0 to 255) fully opaque,
+    /// and every other pixel fully transparent. This gives cursors crisp
+    /// edges, for environments where semi-transparent pixels look wrong.
+    ///
+    /// This is applied after `--shadow`, `--outline`, and `--overlay`, so
+    /// that their edges are made crisp too.
+    #[clap(long, value_name = "N", verbatim_doc_comment)]
+    alpha_threshold: Option<u8>,
+
+    /// If given, treats the input cursors' pixels as having straight alpha,
+    /// and premultiplies them before doing anything else.
+    ///
+    /// Xcursor pixels are supposed to be premultiplied, but some tools write
+    /// straight alpha instead, which gives cursors bright halos around their
+    /// edges.
+    #[clap(long, verbatim_doc_comment)]
+    assume_straight_alpha: bool,
+
+    /// If given, writes pixels with straight alpha rather than premultiplied
+    /// alpha, after every other transform.
+    ///
+    /// libXcursor expects premultiplied pixels, so this is only useful for
+    /// tools that expect straight alpha.
+    #[clap(long, verbatim_doc_comment)]
+    emit_straight_alpha: bool,
 
     /// If given, ignores any unrecognized filetypes.
     ///
@@ -29,9 +340,261 @@ struct Args {
     #[clap(short, long, verbatim_doc_comment)]
     ignore_unrecognized: bool,
 
+    /// If given, recovers what it can from damaged files, instead of failing.
+    ///
+    /// Images that are cut off or can't be found are left out of the
+    /// output, with a warning. Files with no images left still fail.
+    #[clap(long, conflicts_with = "low-memory", verbatim_doc_comment)]
+    salvage: bool,
+
+    /// If given, moves hotspots that are outside of their image onto the
+    /// nearest pixel inside of it, before anything else is done.
+    ///
+    /// Otherwise, these hotspots are only warned about.
+    #[clap(long, verbatim_doc_comment)]
+    fix_hotspots: bool,
+
+    /// Which images to keep when a nominal size appears more than once,
+    /// split into separate groups by images of other sizes.
+    ///
+    /// libXcursor plays every image with the same nominal size as one
+    /// animation, so only one of these groups is kept. Otherwise, they're
+    /// only warned about.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "KEEP",
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    dedupe_sizes: Option<DedupeSizes>,
+
+    /// Leaves out every image whose nominal size would be larger than this
+    /// once it's scaled.
+    ///
+    /// This keeps large scale factors from making cursors with big images
+    /// too large to write. Files with no images small enough fail.
+    #[clap(
+        long,
+        value_name = "SIZE",
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    max_size: Option<u32>,
+
+    /// Only resizes the images with this nominal size, leaving out every
+    /// other image. Can be given more than once.
+    ///
+    /// Files with none of these sizes fail.
+    #[clap(
+        long = "only-size",
+        value_name = "SIZE",
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    only_sizes: Vec<u32>,
+
+    /// Fails before resizing a file if resizing it would take more than
+    /// this much memory, such as `512MiB` or `2G`.
+    ///
+    /// This is an estimate, which includes the input file itself. Use it to
+    /// stop before running out of memory partway through a theme.
+    #[clap(
+        long,
+        value_name = "BYTES",
+        value_parser = estimate::parse_byte_count,
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    max_memory: Option<u64>,
+
+    /// Fails before resizing a file if it would be larger than this once
+    /// resized, such as `64MiB`.
+    #[clap(
+        long,
+        value_name = "BYTES",
+        value_parser = estimate::parse_byte_count,
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    max_output_bytes: Option<u64>,
+
+    /// Scales images by less than the scale factor if they would be larger
+    /// than this, instead of making them larger than most toolkits handle.
+    ///
+    /// Images are still never scaled by less than 1. If two nominal sizes
+    /// would end up the same, only the larger one is kept.
+    #[clap(
+        long,
+        value_name = "SIZE",
+        conflicts_with_all = &["low-memory", "max-size"],
+        verbatim_doc_comment
+    )]
+    clamp_size: Option<u32>,
+
+    /// A Rhai script to run on each image after it's resized.
+    ///
+    /// The script must define a `transform(frame)` function, which is given
+    /// a map with the image's `size`, `width`, `height`, `xhot`, `yhot`,
+    /// `delay`, and `pixels` (ARGB, row by row), and returns the new image.
+    #[cfg(feature = "scripting")]
+    #[clap(long, value_parser, verbatim_doc_comment)]
+    script: Option<PathBuf>,
+
+    /// A shell command to pipe each image through, after it's resized.
+    ///
+    /// Each image is written to the command's stdin as a PNG, and the
+    /// command must write the new image to its stdout as a PNG. The image's
+    /// nominal size, dimensions, hotspot, and delay are available in the
+    /// `RESIZE_XCURSOR_SIZE`, `RESIZE_XCURSOR_WIDTH`, `RESIZE_XCURSOR_HEIGHT`,
+    /// `RESIZE_XCURSOR_XHOT`, `RESIZE_XCURSOR_YHOT`, and `RESIZE_XCURSOR_DELAY`
+    /// environment variables. If the image's dimensions change, its hotspot is
+    /// moved to stay in the same relative position.
+    #[cfg(feature = "png")]
+    #[clap(long, value_name = "CMD", verbatim_doc_comment)]
+    pipe_filter: Option<String>,
+
+    /// How input files are read. Outputs are written the same way either way.
+    ///
+    /// `readahead` reads upcoming files on a background thread while the
+    /// current one is being resized, which helps when resizing many small files.
+    #[clap(long, value_enum, default_value = "standard", verbatim_doc_comment)]
+    io_backend: input::IoBackend,
+
+    /// The maximum number of chunks to accept in an input file.
+    #[clap(long, value_name = "N", default_value_t = Limits::default().max_chunks)]
+    max_chunks: u32,
+
+    /// The maximum width or height to accept for an input image.
+    #[clap(long, value_name = "N", default_value_t = Limits::default().max_dimension)]
+    max_dimension: u32,
+
+    /// The maximum number of pixels to accept in an input file, across
+    /// all of its images.
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = Limits::default().max_total_pixels,
+        verbatim_doc_comment
+    )]
+    max_total_pixels: u64,
+
+    /// If given, prints how long each stage of resizing took for every file.
+    ///
+    /// Each image is written as soon as it's been resized, so encoding it
+    /// is part of the time taken to scale it.
+    #[clap(long, verbatim_doc_comment)]
+    timings: bool,
+
+    /// If given, prints how large each file would be once resized, and
+    /// roughly how much memory resizing it would take, without resizing or
+    /// writing anything.
+    ///
+    /// Every other option is taken into account. Filters that depend on an
+    /// image's pixels, such as `--trim`, are assumed not to change its size.
+    #[clap(long, conflicts_with_all = &["timings", "low-memory"], verbatim_doc_comment)]
+    dry_run: bool,
+
+    /// If given, reads, resizes, and writes one image at a time, so that
+    /// only one image is held in memory at once.
+    ///
+    /// This is slower, but helps with very large animated cursors on
+    /// machines with little memory.
+    #[clap(long, conflicts_with = "timings", verbatim_doc_comment)]
+    low_memory: bool,
+
+    /// If given, keeps resizing the other files when one can't be resized,
+    /// such as when a filter gives one of its images the wrong number of
+    /// pixels. Each failure is reported, and this fails once every file has
+    /// been processed.
+    #[clap(long, verbatim_doc_comment)]
+    keep_going: bool,
+
+    /// If given, loads each output through the system's libXcursor once
+    /// everything has been written, and fails if it can't load every image
+    /// the same way this tool reads them.
+    ///
+    /// This catches problems that other tools are more lenient about, such
+    /// as images with hotspots outside of them. Outputs can't be written to
+    /// stdout.
+    #[cfg(feature = "libxcursor")]
+    #[clap(
+        long,
+        conflicts_with_all = &["dry-run", "ignore-unrecognized", "keep-going"],
+        verbatim_doc_comment
+    )]
+    check_libxcursor: bool,
+
+    /// If given, merges runs of identical frames in each animation into a
+    /// single, longer frame before resizing.
+    ///
+    /// This makes animated cursors smaller without changing how they look.
+    /// It can't be used with `--low-memory`, since frames have to be
+    /// compared with each other.
+    #[clap(long, conflicts_with = "low-memory", verbatim_doc_comment)]
+    optimize_frames: bool,
+
+    /// If given, removes any comments (such as copyright notices and
+    /// licenses) from each cursor. By default, they're kept.
+    ///
+    /// Comments given with `--copyright`, `--license`, or `--comment` are
+    /// still added, so these can be used together to replace a cursor's
+    /// comments.
+    #[clap(long, conflicts_with = "low-memory", verbatim_doc_comment)]
+    strip_comments: bool,
+
+    /// The order to write each cursor's images in.
+    ///
+    /// By default, images are written in the same order as in the input.
+    /// Some parsers other than libXcursor assume that the table of contents
+    /// is sorted by nominal size, which `size-asc` ensures. Frames of an
+    /// animation always stay in order.
+    #[clap(
+        long,
+        value_enum,
+        default_value = "preserve",
+        value_name = "ORDER",
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    sort_toc: SortToc,
+
+    /// Adds a copyright notice to every cursor. Can be given more than once.
+    #[clap(
+        long,
+        value_name = "TEXT",
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    copyright: Vec<String>,
+
+    /// Adds license text to every cursor, such as `CC-BY-SA-4.0`. Can be
+    /// given more than once.
+    #[clap(
+        long,
+        value_name = "TEXT",
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    license: Vec<String>,
+
+    /// Adds a comment to every cursor. Can be given more than once.
+    ///
+    /// Comments are added after any that the cursor already has. Like
+    /// `--copyright` and `--license`, this can't be used with
+    /// `--low-memory`.
+    #[clap(
+        long = "comment",
+        value_name = "TEXT",
+        conflicts_with = "low-memory",
+        verbatim_doc_comment
+    )]
+    comments: Vec<String>,
+
     /// A list of output filenames.
     ///
     /// There must be exactly as many input filenames as output filenames.
+    /// An output filename of `-` writes that cursor to stdout.
     #[clap(short, long = "output", value_parser, verbatim_doc_comment)]
     // `value_parser` on `PathBuf` ^^^^^^^^^^^^ allows for non-UTF-8 paths
     output_filenames: Option<Vec<PathBuf>>,
@@ -44,8 +607,155 @@ struct Args {
     input_filenames: Vec<PathBuf>,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Edits a single Xcursor file without resizing it
+    #[clap(subcommand)]
+    Edit(edit::EditCommand),
+
+    /// Describes the header, table of contents, and chunks of an Xcursor file
+    ///
+    /// Each chunk is listed with its offset and length in bytes. Images
+    /// also show their nominal size, dimensions, hotspot, delay, and which
+    /// frame of their animation they are.
+    #[clap(verbatim_doc_comment)]
+    Info(info::InfoArgs),
+
+    /// Checks that Xcursor files follow the format exactly
+    ///
+    /// This checks the file header, that the table of contents is sorted and
+    /// points to non-overlapping chunks, that each chunk's header is right,
+    /// and that each chunk is exactly as long as it says, with no unused
+    /// bytes. Nominal sizes that don't match their images, or whose images
+    /// are split into separate groups, are reported too. Every problem is
+    /// printed, and this fails if any are found. Images that aren't square
+    /// or have no pixels, and animations with delays of 0ms or over 10s, are
+    /// allowed, but are warned about. `--round-trip` also checks that each
+    /// file would be rewritten byte for byte. `--format json` prints a
+    /// report with a rule id for each problem instead.
+    #[clap(verbatim_doc_comment)]
+    Validate(validate::ValidateArgs),
+
+    /// Rewrites a malformed Xcursor file so that it's valid
+    ///
+    /// This fixes table of contents entries that point to the wrong place,
+    /// wrong header sizes and versions, overlapping chunks, and unused bytes
+    /// between or after chunks. Each problem found is printed as a warning.
+    /// Files that are already valid are left untouched.
+    #[clap(verbatim_doc_comment)]
+    Repair(repair::RepairArgs),
+
+    /// Compares two Xcursor files, image by image
+    ///
+    /// Images are matched up by their nominal size and their index in its
+    /// animation. Differences in dimensions, hotspots, and delays are
+    /// printed, along with how many pixels differ in each frame and by how
+    /// much. This fails if the cursors differ at all.
+    #[clap(verbatim_doc_comment)]
+    Compare(compare::CompareArgs),
+
+    /// Prints a hash of each Xcursor file's contents, and of each image
+    ///
+    /// Hashes cover each image's nominal size, dimensions, hotspot, delay,
+    /// and pixels, along with any other chunks, but not where in the file
+    /// they're stored. They're the same everywhere and across versions, so
+    /// they can be saved to check whether a regenerated theme actually
+    /// changed.
+    #[clap(verbatim_doc_comment)]
+    Hash(hash::HashArgs),
+
+    /// Lists Xcursor files, and the cursors in theme directories
+    ///
+    /// Each cursor is printed on its own line, with how large it is, its
+    /// nominal sizes, and how many frames it has if it's animated. Filters
+    /// such as `--animated-only` and `--missing-size` help find the cursors
+    /// in a theme that need attention. Files that aren't Xcursor files are
+    /// skipped with a warning.
+    #[clap(verbatim_doc_comment)]
+    List(list::ListArgs),
+
+    /// Operations on entire cursor themes
+    #[cfg(feature = "themes")]
+    #[clap(subcommand)]
+    Theme(theme::ThemeCommand),
+}
+
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    init_logging(args.verbose);
+    interrupt::install()?;
+
+    #[cfg(feature = "parallel")]
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
+    }
+
+    match args.command {
+        Some(Command::Edit(edit_command)) => edit_command.run(),
+        Some(Command::Info(info_args)) => info::run(info_args),
+        Some(Command::Validate(validate_args)) => validate::run(validate_args),
+        Some(Command::Repair(repair_args)) => repair::run(repair_args),
+        Some(Command::Compare(compare_args)) => compare::run(compare_args),
+        Some(Command::Hash(hash_args)) => hash::run(hash_args),
+        Some(Command::List(list_args)) => list::run(list_args),
+        #[cfg(feature = "themes")]
+        Some(Command::Theme(theme_command)) => theme_command.run(),
+        None => resize(args),
+    }
+}
+
+fn resize(args: Args) -> anyhow::Result<()> {
+    interrupt::stop_between_files();
+
+    // `clap` requires `--scale` or `--preset` when no subcommand is given
+    let scale = args
+        .scale
+        .or_else(|| args.preset.map(preset::Preset::default_scale))
+        .expect("`--scale` should be required");
+    let pipeline = pipeline(&args, scale)?;
+
+    let mut limits = Limits::default();
+    limits.max_chunks = args.max_chunks;
+    limits.max_dimension = args.max_dimension;
+    limits.max_total_pixels = args.max_total_pixels;
+
+    let mut comments = Vec::new();
+
+    for (kind, texts) in [
+        (CommentKind::Copyright, args.copyright.as_slice()),
+        (CommentKind::License, &args.license),
+        (CommentKind::Other, &args.comments),
+    ] {
+        for text in texts {
+            comments.push(RawChunk::comment(kind, text)?);
+        }
+    }
+
+    let parse_options = ParseOptions {
+        limits,
+        ignore_unrecognized: args.ignore_unrecognized,
+        salvage: args.salvage,
+        fix_hotspots: args.fix_hotspots,
+        dedupe_sizes: args.dedupe_sizes.map(KeepDuplicate::from),
+        scale,
+        max_size: args.max_size,
+        only_sizes: &args.only_sizes,
+        budget: estimate::Budget {
+            memory: args.max_memory,
+            output_bytes: args.max_output_bytes,
+        },
+        clamp_size: args.clamp_size,
+        optimize_frames: args.optimize_frames,
+        strip_comments: args.strip_comments,
+        sort_toc: args.sort_toc,
+        comments: &comments,
+    };
+
+    if args.dry_run {
+        return estimate::dry_run(&args.input_filenames, &pipeline, parse_options);
+    }
 
     let output_filenames = match args.output_filenames {
         Some(output_filenames) => {
@@ -63,66 +773,1034 @@ fn main() -> anyhow::Result<()> {
         None => args.input_filenames.clone(),
     };
 
-    for (input_filename, output_filename) in args.input_filenames.into_iter().zip(output_filenames)
-    {
-        let cursor_bytes = fs::read(&input_filename)?;
+    if output_filenames.iter().any(|path| output::is_stdout(path)) && io::stdout().is_terminal() {
+        bail!("refusing to write a cursor to a terminal (redirect stdout to a file instead)");
+    }
+
+    // libXcursor is loaded upfront, so that nothing is written if it's missing
+    #[cfg(feature = "libxcursor")]
+    let libxcursor_check = match args.check_libxcursor {
+        true => {
+            if output_filenames.iter().any(|path| output::is_stdout(path)) {
+                bail!("--check-libxcursor can't check outputs written to stdout");
+            }
+
+            Some((libxcursor::Libxcursor::open()?, output_filenames.clone()))
+        }
+        false => None,
+    };
+
+    let files = args
+        .input_filenames
+        .into_iter()
+        .zip(output_filenames)
+        .collect::<Vec<_>>();
+
+    #[cfg(feature = "parallel")]
+    if !args.low_memory && !args.timings {
+        schedule::resize_all(
+            files,
+            args.io_backend,
+            &pipeline,
+            parse_options,
+            args.keep_going,
+        )?;
+
+        #[cfg(feature = "libxcursor")]
+        if let Some((library, outputs)) = &libxcursor_check {
+            library.check_all(outputs)?;
+        }
+
+        return Ok(());
+    }
+
+    let mut report = args.timings.then(timings::Report::default);
+
+    // `--low-memory` reads files itself
+    let input_paths = match args.low_memory {
+        true => Vec::new(),
+        false => files
+            .iter()
+            .map(|(input_filename, _)| input_filename.clone())
+            .collect(),
+    };
+    let mut inputs = input::read_all(input_paths, args.io_backend);
+
+    let file_count = files.len();
+    let mut failures = 0;
+
+    for (index, (input_filename, output_filename)) in files.into_iter().enumerate() {
+        if interrupt::interrupted() {
+            bail!("interrupted after processing {index} of {file_count} files");
+        }
+
+        let _span = info_span!("file", path = %input_filename.display()).entered();
 
-        let cursor_images = match parse_xcursor(&cursor_bytes) {
-            Some(res) => res,
-            None => {
-                if args.ignore_unrecognized {
-                    continue;
+        // Every error is about this file, so the rest can still be resized
+        let res = (|| {
+            if args.low_memory {
+                match resize_low_memory(
+                    &input_filename,
+                    &output_filename,
+                    &pipeline,
+                    limits,
+                    args.fix_hotspots,
+                ) {
+                    Ok(()) => info!(output = %output_filename.display(), "resized"),
+                    Err(err) if err.is_limit_exceeded() => {
+                        bail!("{} is too large: {err}", input_filename.display())
+                    }
+                    Err(Error::Parse { .. }) if args.ignore_unrecognized => {
+                        info!("not an Xcursor file, skipping")
+                    }
+                    Err(err @ Error::Parse { .. }) => {
+                        bail!(
+                            "{} isn't a valid Xcursor file: {err}",
+                            input_filename.display()
+                        )
+                    }
+                    Err(err) => return Err(resize_error(&input_filename, err)),
                 }
 
-                bail!(
-                    "{} doesn't seem to be a valid Xcursor file",
-                    input_filename.display()
-                );
+                return Ok(());
             }
-        };
-
-        let mut cursor = Xcursor::new();
-
-        for image in cursor_images {
-            let unscaled_pixels = image
-                .pixels_rgba
-                .array_chunks::<4>()
-                .copied()
-                .map(u32::from_le_bytes)
-                .collect::<Vec<_>>();
-
-            let scaled_pixels = unscaled_pixels
-                // Get each row
-                .chunks_exact(image.width as usize)
-                .flat_map(|row| {
-                    let scaled_rows = row
-                        .iter()
-                        // Duplicate each pixel `args.scale` times
-                        .flat_map(|pixel| iter::repeat(*pixel).take(args.scale as usize));
-
-                    // Duplicate each row `args.scale` times
-                    iter::repeat(scaled_rows)
-                        .take(args.scale as usize)
-                        .flatten()
-                })
-                .collect();
-
-            let output_image = Image::new(
-                image.size * args.scale,
-                image.width * args.scale,
-                image.height * args.scale,
-                image.xhot * args.scale,
-                image.yhot * args.scale,
-                image.delay,
-                scaled_pixels,
-            )?;
-
-            cursor.add_chunk(output_image)?;
-        }
-
-        let output_file = File::create(output_filename)?;
-        cursor.write_to(output_file)?;
+
+            let mut stages = timings::Stages::default();
+            let cursor_bytes = timings::time(&mut stages.read, || {
+                inputs
+                    .next()
+                    .expect("every input filename should be read")
+                    .read()
+            })
+            .with_context(|| format!("couldn't read {}", input_filename.display()))?;
+
+            let res = resize_file(
+                &input_filename,
+                &output_filename,
+                &cursor_bytes,
+                &pipeline,
+                parse_options,
+                &mut stages,
+            );
+            let resized = matches!(res, Ok(FileResult::Resized(_)));
+            report_file(&output_filename, res)?;
+
+            if let Some(report) = report.as_mut().filter(|_| resized) {
+                report.push(input_filename, stages);
+            }
+
+            Ok(())
+        })();
+
+        if let Err(err) = res {
+            if !args.keep_going {
+                return Err(err);
+            }
+
+            error!("{err:#}");
+            failures += 1;
+        }
+    }
+
+    if let Some(report) = report {
+        report.print();
+    }
+
+    if failures > 0 {
+        bail!("couldn't resize {failures} of {file_count} files");
+    }
+
+    #[cfg(feature = "libxcursor")]
+    if let Some((library, outputs)) = &libxcursor_check {
+        library.check_all(outputs)?;
     }
 
     Ok(())
 }
+
+/// What happened to a file, before it's been reported.
+enum FileResult {
+    /// The file was resized. Outputs to stdout are only committed once
+    /// they're reported, so that they always appear in input order.
+    Resized(Option<Box<dyn SinkWriter>>),
+    /// The file isn't an Xcursor file, and `--ignore-unrecognized` was given.
+    Skipped,
+}
+
+/// Resizes a file that's already been read, adding how long each stage
+/// took to `stages`.
+fn resize_file(
+    input_filename: &Path,
+    output_filename: &Path,
+    cursor_bytes: &[u8],
+    pipeline: &Pipeline,
+    parse_options: ParseOptions<'_>,
+    stages: &mut timings::Stages,
+) -> anyhow::Result<FileResult> {
+    let cursor_bytes = salvage_damaged(input_filename, cursor_bytes, parse_options);
+    let parsed = timings::time(&mut stages.parse, || {
+        parse_file(input_filename, &cursor_bytes, parse_options)
+    })?;
+
+    let Some((header, cursor_chunks)) = parsed else {
+        return Ok(FileResult::Skipped);
+    };
+
+    parse_options.budget.check(
+        input_filename,
+        cursor_bytes.len() as u64,
+        header,
+        &cursor_chunks,
+        pipeline,
+        parse_options.optimize_frames,
+    )?;
+
+    // Images are written as they're resized, so that only a few of them
+    // have to be held in memory at once. The output file is often the
+    // input file, which is fine since it's only replaced at the end.
+    let mut output = timings::time(&mut stages.write, || output::create(output_filename))?;
+    let mut writing = Duration::ZERO;
+    let mut resizing = Duration::ZERO;
+
+    timings::time(&mut resizing, || {
+        let writer = timings::TimedWriter::new(&mut output, &mut writing);
+        transform_chunks_to(header, cursor_chunks, pipeline, writer)
+    })
+    .map_err(|err| resize_error(input_filename, err))?;
+
+    stages.scale += resizing.saturating_sub(writing);
+    stages.write += writing;
+
+    if output::is_stdout(output_filename) {
+        return Ok(FileResult::Resized(Some(output)));
+    }
+
+    timings::time(&mut stages.write, || output.commit())?;
+    Ok(FileResult::Resized(None))
+}
+
+/// Reports what happened to a file, committing its output if that hasn't
+/// happened yet.
+fn report_file(output_filename: &Path, res: anyhow::Result<FileResult>) -> anyhow::Result<()> {
+    match res? {
+        FileResult::Resized(output) => {
+            if let Some(output) = output {
+                output.commit()?;
+            }
+
+            info!(output = %output_filename.display(), "resized");
+        }
+
+        FileResult::Skipped => info!("not an Xcursor file, skipping"),
+    }
+
+    Ok(())
+}
+
+/// Options for how input files are parsed, and how their chunks are changed
+/// before they're resized.
+#[derive(Clone, Copy)]
+struct ParseOptions<'a> {
+    limits: Limits,
+    ignore_unrecognized: bool,
+    /// Whether to recover what's left of damaged files (see
+    /// [`salvage_damaged`]).
+    salvage: bool,
+    /// Whether to merge identical frames in each animation (see
+    /// [`merge_identical_chunks`]).
+    optimize_frames: bool,
+    /// Whether hotspots outside of their image are being fixed, which is
+    /// mentioned when warning about them.
+    fix_hotspots: bool,
+    /// Which group of images to keep for each nominal size that's split
+    /// into several, if any.
+    dedupe_sizes: Option<KeepDuplicate>,
+    /// The scale factor that every image is resized by.
+    scale: u32,
+    /// The largest nominal size to keep once scaled, if any.
+    max_size: Option<u32>,
+    /// The only nominal sizes to keep, or every size if this is empty.
+    only_sizes: &'a [u32],
+    /// How much resizing each file may take.
+    budget: estimate::Budget,
+    /// The largest nominal size to scale images to, if any.
+    clamp_size: Option<u32>,
+    /// Whether to remove the comments that each file already has.
+    strip_comments: bool,
+    /// The order to put each file's images in.
+    sort_toc: SortToc,
+    /// Comments to add to every file, after the ones it already has.
+    comments: &'a [RawChunk],
+}
+
+/// Parses a file's header and every chunk in it. Returns `None` if the file
+/// isn't an Xcursor file and `--ignore-unrecognized` was given.
+fn parse_file<'a>(
+    input_filename: &Path,
+    cursor_bytes: &'a [u8],
+    options: ParseOptions<'a>,
+) -> anyhow::Result<Option<(FileHeader, Vec<Chunk<'a>>)>> {
+    let cursor_chunks =
+        XcursorReader::with_limits(cursor_bytes, options.limits).and_then(|reader| {
+            let cursor_chunks = reader.chunks().collect::<Result<Vec<_>, _>>()?;
+            Ok((reader.header(), cursor_chunks))
+        });
+
+    match cursor_chunks {
+        Ok((header, mut cursor_chunks)) => {
+            let images = |cursor_chunks: &[Chunk]| {
+                cursor_chunks
+                    .iter()
+                    .filter_map(|chunk| match chunk {
+                        Chunk::Image(image) => Some(image.info),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            // Other warnings are about the images that are kept
+            if let Some(keep) = options.dedupe_sizes {
+                let layouts = images(&cursor_chunks)
+                    .iter()
+                    .map(ImageInfo::layout)
+                    .collect::<Vec<_>>();
+
+                for duplicate in validation::duplicate_sizes(layouts) {
+                    warn!(
+                        "{}: the images with a nominal size of {} are split into {} separate groups, keeping only one",
+                        input_filename.display(),
+                        duplicate.size,
+                        duplicate.groups.len()
+                    );
+                }
+
+                cursor_chunks = validation::dedupe_chunks(cursor_chunks, keep);
+            }
+
+            if !options.only_sizes.is_empty() {
+                cursor_chunks = keep_only_sizes(input_filename, cursor_chunks, options.only_sizes)?;
+            }
+
+            if let Some(max_size) = options.max_size {
+                cursor_chunks =
+                    drop_oversized(input_filename, cursor_chunks, options.scale, max_size)?;
+            }
+
+            cursor_chunks = check_scaled_sizes(input_filename, cursor_chunks, options);
+
+            warn_about_images(
+                input_filename,
+                &images(&cursor_chunks),
+                options.fix_hotspots,
+            );
+
+            if options.optimize_frames {
+                cursor_chunks = merge_identical_chunks(cursor_chunks);
+            }
+
+            if options.strip_comments {
+                cursor_chunks.retain(|chunk| !matches!(chunk, Chunk::Comment(_)));
+            }
+
+            // Other chunks are sorted before every image. The sort is
+            // stable, so animations keep their frames in order.
+            if options.sort_toc == SortToc::SizeAsc {
+                cursor_chunks.sort_by_key(|chunk| match chunk {
+                    Chunk::Image(image) => Some(image.info.size()),
+                    _ => None,
+                });
+            }
+
+            // New comments go before the images, along with any comments
+            // already written there
+            let first_image = cursor_chunks
+                .iter()
+                .position(|chunk| matches!(chunk, Chunk::Image(_)))
+                .unwrap_or(cursor_chunks.len());
+            cursor_chunks.splice(
+                first_image..first_image,
+                options.comments.iter().map(Chunk::from),
+            );
+            Ok(Some((header, cursor_chunks)))
+        }
+        Err(err) if err.is_limit_exceeded() => {
+            bail!("{} is too large: {err}", input_filename.display())
+        }
+        Err(Error::Parse { .. }) if options.ignore_unrecognized => Ok(None),
+        Err(err @ Error::Parse { .. }) => {
+            bail!(
+                "{} isn't a valid Xcursor file: {err}",
+                input_filename.display()
+            )
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Removes every image without one of the given nominal sizes, for
+/// `--only-size`.
+fn keep_only_sizes<'a>(
+    input_filename: &Path,
+    mut cursor_chunks: Vec<Chunk<'a>>,
+    sizes: &[u32],
+) -> anyhow::Result<Vec<Chunk<'a>>> {
+    let has_images = |cursor_chunks: &[Chunk]| {
+        cursor_chunks
+            .iter()
+            .any(|chunk| matches!(chunk, Chunk::Image(_)))
+    };
+    let had_images = has_images(&cursor_chunks);
+
+    cursor_chunks.retain(|chunk| match chunk {
+        Chunk::Image(image) => sizes.contains(&image.info.size()),
+        _ => true,
+    });
+
+    if had_images && !has_images(&cursor_chunks) {
+        bail!(
+            "{} has no images with the nominal sizes given to --only-size",
+            input_filename.display()
+        );
+    }
+
+    Ok(cursor_chunks)
+}
+
+/// Removes every image whose nominal size would be larger than `max_size` once
+/// it's scaled by `scale`, for `--max-size`.
+fn drop_oversized<'a>(
+    input_filename: &Path,
+    cursor_chunks: Vec<Chunk<'a>>,
+    scale: u32,
+    max_size: u32,
+) -> anyhow::Result<Vec<Chunk<'a>>> {
+    let mut dropped_sizes = Vec::new();
+    let mut kept_image = false;
+
+    let cursor_chunks = cursor_chunks
+        .into_iter()
+        .filter(|chunk| {
+            let Chunk::Image(image) = chunk else {
+                return true;
+            };
+
+            let size = image.info.size();
+
+            // Sizes that overflow are certainly too large
+            if size
+                .checked_mul(scale)
+                .is_some_and(|scaled| scaled <= max_size)
+            {
+                kept_image = true;
+                return true;
+            }
+
+            if !dropped_sizes.contains(&size) {
+                dropped_sizes.push(size);
+            }
+
+            false
+        })
+        .collect::<Vec<_>>();
+
+    if !kept_image && !dropped_sizes.is_empty() {
+        bail!(
+            "every image in {} would be larger than --max-size {max_size} once scaled",
+            input_filename.display()
+        );
+    }
+
+    for size in dropped_sizes {
+        info!("leaving out the {size}px images, which would be larger than --max-size");
+    }
+
+    Ok(cursor_chunks)
+}
+
+/// Warns about every nominal size that would be larger than most toolkits
+/// handle once it's scaled. With `--clamp-size`, those sizes are scaled by
+/// less instead, and sizes that would end up the same as a larger one are
+/// removed.
+fn check_scaled_sizes<'a>(
+    input_filename: &Path,
+    cursor_chunks: Vec<Chunk<'a>>,
+    options: ParseOptions,
+) -> Vec<Chunk<'a>> {
+    let mut resize = ResizeOptions::new(options.scale);
+
+    if let Some(clamp_size) = options.clamp_size {
+        resize.clamp_size(clamp_size);
+    }
+
+    let scaled_size = |size: u32| size.saturating_mul(resize.scale_for(size));
+
+    let mut sizes = cursor_chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            Chunk::Image(image) => Some(image.info.size()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    if options.clamp_size.is_none() {
+        warn_about_scaled_sizes(
+            input_filename,
+            sizes.iter().map(|&size| (size, scaled_size(size))),
+        );
+        return cursor_chunks;
+    }
+
+    // Larger sizes have more detail to start with, so they're kept instead
+    let mut dropped_sizes = Vec::new();
+
+    for &size in &sizes {
+        let scaled = scaled_size(size);
+
+        if let Some(&other) = sizes
+            .iter()
+            .rev()
+            .find(|&&other| other > size && scaled_size(other) == scaled)
+        {
+            warn!(
+                "{}: leaving out the {size}px images, which would be {scaled}px once scaled, like the {other}px images",
+                input_filename.display()
+            );
+            dropped_sizes.push(size);
+        }
+    }
+
+    cursor_chunks
+        .into_iter()
+        .filter(|chunk| match chunk {
+            Chunk::Image(image) => !dropped_sizes.contains(&image.info.size()),
+            _ => true,
+        })
+        .collect()
+}
+
+/// Warns about every nominal size that would be larger than most toolkits
+/// handle once it's scaled, given pairs of nominal sizes and the sizes they
+/// would be scaled to.
+fn warn_about_scaled_sizes(input_filename: &Path, sizes: impl IntoIterator<Item = (u32, u32)>) {
+    // Each size is only warned about once, even if it has several frames
+    let mut largest = BTreeMap::<u32, u32>::new();
+
+    for (size, scaled) in sizes {
+        let entry = largest.entry(size).or_default();
+        *entry = scaled.max(*entry);
+    }
+
+    for (size, scaled) in largest {
+        if scaled > validation::MAX_USUAL_SIZE {
+            warn!(
+                "{}: the {size}px images would be {scaled}px once scaled, which is larger than most toolkits handle (use --clamp-size to limit them)",
+                input_filename.display()
+            );
+        }
+    }
+}
+
+/// Explains an error that happened while resizing a file, suggesting
+/// `--max-size` if the file would have been too large.
+fn resize_error(input_filename: &Path, err: Error) -> anyhow::Error {
+    if err.is_too_large() {
+        return anyhow!(
+            "{} would be too large once resized: {err}\n\
+            (use --max-size to leave out its largest images)",
+            input_filename.display()
+        );
+    }
+
+    anyhow::Error::new(err).context(format!("couldn't resize {}", input_filename.display()))
+}
+
+/// Warns about every image whose hotspot is outside of it, and every nominal
+/// size whose images are actually a different size or are split into
+/// separate groups.
+fn warn_about_images(input_filename: &Path, images: &[ImageInfo], fix_hotspots: bool) {
+    for image in images {
+        if image.xhot < image.width && image.yhot < image.height {
+            continue;
+        }
+
+        warn!(
+            "{}: the hotspot of a {}px image ({},{}) is outside of it ({}x{}){}",
+            input_filename.display(),
+            image.size(),
+            image.xhot,
+            image.yhot,
+            image.width,
+            image.height,
+            match fix_hotspots {
+                true => ", moving it inside",
+                false => " (use --fix-hotspots to move it inside)",
+            }
+        );
+    }
+
+    // Scaling these by their nominal size makes the mismatch larger
+    for mismatch in validation::size_mismatches(images.iter().map(ImageInfo::layout)) {
+        warn!(
+            "{}: the images with a nominal size of {} are actually {}px (use `edit fix-size` to fix them)",
+            input_filename.display(),
+            mismatch.size,
+            mismatch.actual_size
+        );
+    }
+
+    for duplicate in validation::duplicate_sizes(images.iter().map(ImageInfo::layout)) {
+        warn!(
+            "{}: the images with a nominal size of {} are split into {} separate groups (use --dedupe-sizes to keep only one)",
+            input_filename.display(),
+            duplicate.size,
+            duplicate.groups.len()
+        );
+    }
+}
+
+/// With `--salvage`, replaces a damaged file with a valid cursor made from
+/// whatever could be recovered from it. Files that are fine (or that can't
+/// be salvaged) are returned unchanged, so that [`parse_file`] can report
+/// them as usual.
+fn salvage_damaged<'a>(
+    input_filename: &Path,
+    cursor_bytes: &'a [u8],
+    options: ParseOptions<'_>,
+) -> Cow<'a, [u8]> {
+    if !options.salvage {
+        return Cow::Borrowed(cursor_bytes);
+    }
+
+    let parsed = XcursorReader::with_limits(cursor_bytes, options.limits)
+        .and_then(|reader| reader.chunks().try_for_each(|chunk| chunk.map(drop)));
+
+    let err = match parsed {
+        Err(err @ Error::Parse { .. }) if !err.is_limit_exceeded() => err,
+        _ => return Cow::Borrowed(cursor_bytes),
+    };
+
+    let Ok(salvaged) = validation::salvage(cursor_bytes) else {
+        return Cow::Borrowed(cursor_bytes);
+    };
+
+    let mut salvaged_bytes = Vec::new();
+
+    if salvaged.cursor.write_to(&mut salvaged_bytes).is_err() {
+        return Cow::Borrowed(cursor_bytes);
+    }
+
+    match salvaged.lost.len() {
+        0 => warn!(
+            "{} is damaged ({err}), but every chunk was recovered",
+            input_filename.display()
+        ),
+        lost => warn!(
+            "{} is damaged ({err}), so {lost} chunks were left out",
+            input_filename.display()
+        ),
+    }
+
+    for err in &salvaged.lost {
+        warn!("    {err}");
+    }
+
+    Cow::Owned(salvaged_bytes)
+}
+
+/// Sets up logging to stderr, using `RESIZE_XCURSOR_LOG` if it's set and
+/// `--verbose` otherwise.
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+
+    let filter =
+        EnvFilter::try_from_env("RESIZE_XCURSOR_LOG").unwrap_or_else(|_| EnvFilter::new(level));
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
+/// Resizes a file one image at a time for `--low-memory`.
+fn resize_low_memory(
+    input_filename: &Path,
+    output_filename: &Path,
+    pipeline: &Pipeline,
+    limits: Limits,
+    fix_hotspots: bool,
+) -> resize_xcursor::Result<()> {
+    let input = BufReader::new(File::open(input_filename)?);
+    let mut input = SeekReader::with_limits(input, limits)?;
+    let images = input.image_infos()?;
+    warn_about_images(input_filename, &images, fix_hotspots);
+
+    // `--clamp-size` isn't allowed here, so every size is warned about
+    let mut sizes = Vec::new();
+
+    for image in &images {
+        if let Some(layout) = pipeline.layout(image.layout())? {
+            sizes.push((image.size(), layout.size));
+        }
+    }
+
+    warn_about_scaled_sizes(input_filename, sizes);
+
+    output::write(output_filename, |output| {
+        transform_seekable_to(input, pipeline, output)?;
+        Ok(())
+    })
+}
+
+/// Builds the transforms to apply to each image from the command line.
+///
+/// Transforms are always applied in the same order, starting with resizing.
+fn pipeline(args: &Args, scale: u32) -> anyhow::Result<Pipeline> {
+    let mut pipeline = Pipeline::new();
+
+    // This has to happen before resizing moves hotspots even further away
+    if args.fix_hotspots {
+        pipeline.push(ClampHotspot);
+    }
+
+    if args.assume_straight_alpha {
+        pipeline.push(Premultiply);
+    }
+
+    let mut resize = ResizeOptions::new(scale);
+
+    if let Some(clamp_size) = args.clamp_size {
+        resize.clamp_size(clamp_size);
+    }
+
+    pipeline.push(resize);
+
+    if let Some(speed) = args.speed {
+        pipeline.push(ScaleDelays::new(speed));
+    }
+
+    if args.min_delay.is_some() || args.max_delay.is_some() {
+        let (min, max) = (
+            args.min_delay.unwrap_or(0),
+            args.max_delay.unwrap_or(u32::MAX),
+        );
+
+        if min > max {
+            bail!("--min-delay ({min}ms) can't be longer than --max-delay ({max}ms)");
+        }
+
+        pipeline.push(ClampDelays::new(min, max));
+    }
+
+    if args.flip_h {
+        pipeline.push(Flip::Horizontal);
+    }
+
+    if args.flip_v {
+        pipeline.push(Flip::Vertical);
+    }
+
+    if let Some(rotate) = args.rotate {
+        pipeline.push(rotate);
+    }
+
+    if args.trim {
+        pipeline.push(Trim);
+    }
+
+    if let Some((width, height)) = args.canvas {
+        pipeline.push(*Canvas::new(width, height).anchor(args.anchor));
+    }
+
+    let mut replace_colors = ReplaceColors::new();
+    replace_colors.tolerance(args.color_tolerance);
+
+    for &(from, to) in &args.replace_colors {
+        replace_colors.replace(from, to);
+    }
+
+    if let Some(path) = &args.palette_map {
+        for (from, to) in read_palette_map(path)? {
+            replace_colors.replace(from, to);
+        }
+    }
+
+    if !replace_colors.is_empty() {
+        pipeline.push(replace_colors);
+    }
+
+    if let Some(degrees) = args.hue_shift {
+        pipeline.push(HueShift::new(degrees));
+    }
+
+    if let Some(tint) = args.tint {
+        pipeline.push(tint);
+    }
+
+    if args.invert {
+        pipeline.push(Invert);
+    }
+
+    if let Some(amount) = args.grayscale {
+        pipeline.push(Grayscale::new(amount));
+    }
+
+    if let Some(factor) = args.brightness {
+        pipeline.push(Brightness::new(factor as f32));
+    }
+
+    if let Some(factor) = args.contrast {
+        pipeline.push(Contrast::new(factor as f32));
+    }
+
+    if let Some(gamma) = args.gamma {
+        pipeline.push(Gamma::new(gamma as f32));
+    }
+
+    if let Some(factor) = args.opacity {
+        pipeline.push(Opacity::new(factor));
+    }
+
+    if let Some(radius) = args.blur {
+        pipeline.push(Blur::new(radius));
+    }
+
+    if let Some(shadow) = args.shadow {
+        pipeline.push(shadow);
+    }
+
+    if let Some(outline) = args.outline {
+        pipeline.push(outline);
+    }
+
+    if let Some(preset) = args.preset {
+        preset.push_to(&mut pipeline, scale);
+    }
+
+    #[cfg(feature = "png")]
+    if let Some((path, anchor)) = &args.overlay {
+        use resize_xcursor::filters::Overlay;
+
+        let png = png::read(path)?;
+        let mut overlay = Overlay::new(png.width, png.height, png.pixels)?;
+        overlay.anchor(*anchor);
+        pipeline.push(overlay);
+    }
+
+    if let Some(threshold) = args.alpha_threshold {
+        pipeline.push(AlphaThreshold::new(threshold));
+    }
+
+    #[cfg(feature = "scripting")]
+    if let Some(path) = &args.script {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read {}", path.display()))?;
+        let script = resize_xcursor::script::Script::new(&source)
+            .with_context(|| format!("couldn't compile {}", path.display()))?;
+        pipeline.push(script);
+    }
+
+    #[cfg(feature = "png")]
+    if let Some(command) = &args.pipe_filter {
+        pipeline.push(pipe_filter::PipeFilter::new(command.clone()));
+    }
+
+    if args.emit_straight_alpha {
+        pipeline.push(Unpremultiply);
+    }
+
+    Ok(pipeline)
+}
+
+/// Reads the pairs of colors in a palette file for `--palette-map`.
+fn read_palette_map(path: &Path) -> anyhow::Result<Vec<(Color, Color)>> {
+    let palette =
+        fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+
+    palette
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with("//"))
+        .map(|(index, line)| {
+            parse_color_pair(line).map_err(|err| anyhow!("{}:{}: {err}", path.display(), index + 1))
+        })
+        .collect()
+}
+
+/// The order that images are written in.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortToc {
+    /// Sorts images by their nominal size, smallest first.
+    SizeAsc,
+    /// Keeps images in the same order as in the input.
+    Preserve,
+}
+
+/// A [`KeepDuplicate`] that can be picked on the command line.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum DedupeSizes {
+    /// Keeps the first group of images.
+    First,
+    /// Keeps the last group of images.
+    Last,
+    /// Keeps the group with the largest image.
+    Largest,
+}
+
+impl From<DedupeSizes> for KeepDuplicate {
+    fn from(keep: DedupeSizes) -> Self {
+        match keep {
+            DedupeSizes::First => KeepDuplicate::First,
+            DedupeSizes::Last => KeepDuplicate::Last,
+            DedupeSizes::Largest => KeepDuplicate::Largest,
+        }
+    }
+}
+
+/// Parses a factor to multiply something by, which has to be a positive
+/// number.
+fn parse_factor(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(factor),
+        _ => Err(format!("`{s}` isn't a positive number")),
+    }
+}
+
+/// Parses the number of degrees to rotate each image by.
+fn parse_rotation(s: &str) -> Result<Rotate, String> {
+    s.parse()
+        .ok()
+        .and_then(Rotate::from_degrees)
+        .ok_or_else(|| format!("`{s}` isn't 90, 180, or 270"))
+}
+
+/// Parses the number of degrees to shift each hue by, which can be any
+/// finite number.
+fn parse_hue_shift(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(degrees) if degrees.is_finite() => Ok(degrees),
+        _ => Err(format!("`{s}` isn't a number of degrees")),
+    }
+}
+
+/// Parses dimensions like `64x64`.
+fn parse_dimensions(s: &str) -> Result<(u32, u32), String> {
+    let dimensions = s
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+
+    dimensions.ok_or_else(|| format!("`{s}` isn't a width and height like `64x64`"))
+}
+
+/// Parses where to place images on a canvas.
+fn parse_anchor(s: &str) -> Result<Anchor, String> {
+    match s {
+        "center" => Ok(Anchor::Center),
+        "topleft" => Ok(Anchor::TopLeft),
+        "bottomright" => Ok(Anchor::BottomRight),
+        _ => match edit::parse_pair(s) {
+            Ok((x, y)) => Ok(Anchor::At { x, y }),
+            Err(_) => Err(format!(
+                "`{s}` isn't `center`, `topleft`, `bottomright`, or coordinates like `4,4`"
+            )),
+        },
+    }
+}
+
+/// Parses the path to an overlay, with an optional anchor after a colon.
+#[cfg(feature = "png")]
+fn parse_overlay(s: &str) -> Result<(PathBuf, Anchor), String> {
+    // Colons are allowed in paths, so the part after the last colon is only
+    // an anchor if it parses as one
+    match s.rsplit_once(':') {
+        Some((path, anchor)) => match parse_anchor(anchor) {
+            Ok(anchor) => Ok((PathBuf::from(path), anchor)),
+            Err(_) => Ok((PathBuf::from(s), Anchor::BottomRight)),
+        },
+        None => Ok((PathBuf::from(s), Anchor::BottomRight)),
+    }
+}
+
+/// Parses a color written as `#RRGGBB` or `#RRGGBBAA`.
+fn parse_color(s: &str) -> Result<Color, String> {
+    Color::from_hex(s).ok_or_else(|| format!("`{s}` isn't a color like `#RRGGBB`"))
+}
+
+/// Parses a pair of colors like `#000000=#FFFFFF`.
+fn parse_color_pair(s: &str) -> Result<(Color, Color), String> {
+    match s.split_once('=') {
+        Some((from, to)) => Ok((parse_color(from.trim())?, parse_color(to.trim())?)),
+        None => Err("expected two colors separated by `=`, like `#000000=#FFFFFF`".to_owned()),
+    }
+}
+
+/// Parses an amount from 0 to 1.
+fn parse_amount(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(amount) if (0.0..=1.0).contains(&amount) => Ok(amount),
+        _ => Err(format!("`{s}` isn't an amount from 0 to 1")),
+    }
+}
+
+/// Parses a tint color, with an optional strength after a colon.
+fn parse_tint(s: &str) -> Result<Tint, String> {
+    let (color, strength) = match s.split_once(':') {
+        Some((color, strength)) => (color, Some(strength)),
+        None => (s, None),
+    };
+
+    let strength = match strength {
+        Some(strength) => parse_amount(strength)?,
+        None => 0.5,
+    };
+
+    Ok(Tint::new(parse_color(color)?, strength))
+}
+
+/// Parses a shadow's offset, blur radius, and color.
+fn parse_shadow(s: &str) -> Result<Shadow, String> {
+    let parts = s.split(',').collect::<Vec<_>>();
+
+    let [x_offset, y_offset, blur, color] = parts[..] else {
+        return Err(
+            "expected an offset, a blur radius, and a color, like `2,2,3,#00000080`".to_owned(),
+        );
+    };
+
+    Ok(Shadow::new(
+        parse_number(x_offset)?,
+        parse_number(y_offset)?,
+        parse_number(blur)?,
+        parse_color(color.trim())?,
+    ))
+}
+
+/// Parses an outline's width and color.
+fn parse_outline(s: &str) -> Result<Outline, String> {
+    let Some((width, color)) = s.split_once(',') else {
+        return Err("expected a width and a color, like `1,#FFFFFF`".to_owned());
+    };
+
+    Ok(Outline::new(
+        parse_number(width)?,
+        parse_color(color.trim())?,
+    ))
+}
+
+/// Parses a number, ignoring any surrounding whitespace.
+fn parse_number<T: FromStr>(part: &str) -> Result<T, String> {
+    part.trim()
+        .parse()
+        .map_err(|_| format!("`{part}` isn't a valid number"))
+}
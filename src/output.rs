@@ -0,0 +1,277 @@
+//! Where output files are written.
+//!
+//! Every output goes through an [`OutputSink`]: usually [`FileSink`], or
+//! [`StdoutSink`] when the output filename is `-`.
+
+use crate::interrupt;
+use resize_xcursor::{OutputSink, Result, SinkWriter};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Writes outputs to files without leaving partial files behind.
+///
+/// Each output is written to a temporary file next to it, which replaces the
+/// output once it's been flushed. This means that the output is never left
+/// half-written, and that the output can be one of the files being read (even
+/// if it's memory-mapped). If the output is a symlink, the file it points to
+/// is replaced instead, even if it doesn't exist yet, so that symlinks (such
+/// as dotfiles managed elsewhere) are never replaced.
+pub(crate) struct FileSink;
+
+/// The most symlinks that are followed to find an output, like Linux's
+/// limit.
+const MAX_SYMLINKS: usize = 40;
+
+/// Returns the file that an output at `name` replaces, following symlinks.
+fn resolve_symlinks(name: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(name) {
+        return path;
+    }
+
+    // `canonicalize` fails for symlinks to files that don't exist yet
+    let mut path = name.to_owned();
+
+    for _ in 0..MAX_SYMLINKS {
+        let Ok(target) = fs::read_link(&path) else {
+            break;
+        };
+
+        // Relative targets are relative to the symlink's directory
+        path = path.parent().unwrap_or(Path::new("")).join(target);
+    }
+
+    path
+}
+
+impl OutputSink for FileSink {
+    fn create(&self, name: &Path) -> Result<Box<dyn SinkWriter + '_>> {
+        let path = resolve_symlinks(name);
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temp_path = path.with_file_name(format!(".{file_name}.tmp"));
+
+        interrupt::add_temp_file(&temp_path);
+
+        match File::create(&temp_path) {
+            Ok(file) => Ok(Box::new(TempFile {
+                path,
+                temp_path,
+                writer: Some(BufWriter::new(file)),
+                committed: false,
+            })),
+
+            Err(err) => {
+                interrupt::remove_temp_file(&temp_path);
+                Err(err.into())
+            }
+        }
+    }
+}
+
+/// A temporary file that replaces `path` once it's committed, and is
+/// removed otherwise.
+struct TempFile {
+    path: PathBuf,
+    temp_path: PathBuf,
+    /// This is only `None` while committing.
+    writer: Option<BufWriter<File>>,
+    committed: bool,
+}
+
+impl TempFile {
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        self.writer
+            .as_mut()
+            .expect("the temporary file should be open until it's committed")
+    }
+}
+
+impl Write for TempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.writer().write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+impl SinkWriter for TempFile {
+    fn commit(mut self: Box<Self>) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            // Flush and close the file before it's renamed
+            writer.into_inner().map_err(|err| err.into_error())?;
+        }
+
+        // Keep the permissions of the file being replaced
+        if let Ok(metadata) = fs::metadata(&self.path) {
+            fs::set_permissions(&self.temp_path, metadata.permissions())?;
+        }
+
+        fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+
+        interrupt::remove_temp_file(&self.temp_path);
+    }
+}
+
+/// Writes outputs to stdout, one after another.
+///
+/// Each output is held in memory until it's committed, so that outputs being
+/// written at the same time aren't mixed together.
+pub(crate) struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn create(&self, _name: &Path) -> Result<Box<dyn SinkWriter + '_>> {
+        Ok(Box::new(StdoutWriter { bytes: Vec::new() }))
+    }
+}
+
+struct StdoutWriter {
+    bytes: Vec<u8>,
+}
+
+impl Write for StdoutWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SinkWriter for StdoutWriter {
+    fn commit(self: Box<Self>) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&self.bytes)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Returns whether `path` means stdout.
+pub(crate) fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Starts writing the output at `path`, using [`StdoutSink`] if `path` is
+/// `-` and [`FileSink`] otherwise.
+pub(crate) fn create(path: &Path) -> Result<Box<dyn SinkWriter>> {
+    let sink: &'static dyn OutputSink = match is_stdout(path) {
+        true => &StdoutSink,
+        false => &FileSink,
+    };
+
+    sink.create(path)
+}
+
+/// Writes the output at `path` by calling `write` with a writer for it
+/// (see [`create`]).
+///
+/// The output is only committed if `write` succeeds.
+pub(crate) fn write<T>(path: &Path, write: impl FnOnce(&mut dyn Write) -> Result<T>) -> Result<T> {
+    let mut output = create(path)?;
+    let res = write(&mut output)?;
+    output.commit()?;
+    Ok(res)
+}
+
+/// Writes `contents` to the output at `path` (see [`write`]), like
+/// [`fs::write`].
+#[cfg(any(feature = "png", feature = "themes"))]
+pub(crate) fn write_bytes(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    write(path, |output| Ok(output.write_all(contents.as_ref())?))
+}
+
+/// Copies the file at `from` to the output at `to` (see [`write`]), like
+/// [`fs::copy`]. Symlinks are followed, so the copy is always a regular
+/// file.
+#[cfg(feature = "themes")]
+pub(crate) fn copy(from: &Path, to: &Path) -> Result<()> {
+    let mut input = File::open(from)?;
+
+    write(to, |output| {
+        io::copy(&mut input, output)?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// A directory for a test to write into, which is empty at first.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "resize-xcursor-output-{}-{name}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("dotfiles")).unwrap();
+        dir
+    }
+
+    fn write_text(path: &Path, text: &str) {
+        write(path, |output| Ok(output.write_all(text.as_bytes())?)).unwrap();
+    }
+
+    #[test]
+    fn replaces_file() {
+        let dir = test_dir("replaces-file");
+        let path = dir.join("file");
+        fs::write(&path, "old").unwrap();
+
+        write_text(&path, "new");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        // The temporary file is gone
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_through_symlinks() {
+        let dir = test_dir("symlinks");
+        let link = dir.join(".Xresources");
+        let target = dir.join("dotfiles/Xresources");
+        symlink("dotfiles/Xresources", &link).unwrap();
+
+        // Even if the file it points to doesn't exist yet
+        write_text(&link, "Xcursor.size: 48\n");
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "Xcursor.size: 48\n");
+
+        write_text(&link, "Xcursor.size: 64\n");
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "Xcursor.size: 64\n");
+
+        // Chains of symlinks are followed to the end
+        let outer = dir.join("outer");
+        symlink(&link, &outer).unwrap();
+        write_text(&outer, "Xcursor.size: 96\n");
+        assert!(fs::symlink_metadata(&outer).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "Xcursor.size: 96\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
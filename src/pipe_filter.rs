@@ -0,0 +1,86 @@
+//! Implementation of `--pipe-filter`.
+
+use crate::{edit, png};
+use anyhow::bail;
+use resize_xcursor::{Image, Transform};
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// A [`Transform`] that pipes each image through a shell command as a PNG.
+#[derive(Debug)]
+pub(crate) struct PipeFilter {
+    command: String,
+}
+
+impl PipeFilter {
+    pub(crate) fn new(command: String) -> Self {
+        Self { command }
+    }
+
+    fn run(&self, image: &Image) -> anyhow::Result<Image> {
+        let input = png::encode(image.width(), image.height(), image.pixels())?;
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("RESIZE_XCURSOR_SIZE", image.size().to_string())
+            .env("RESIZE_XCURSOR_WIDTH", image.width().to_string())
+            .env("RESIZE_XCURSOR_HEIGHT", image.height().to_string())
+            .env("RESIZE_XCURSOR_XHOT", image.xhot().to_string())
+            .env("RESIZE_XCURSOR_YHOT", image.yhot().to_string())
+            .env("RESIZE_XCURSOR_DELAY", image.delay().to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+
+        // Write the input on another thread, so that a command that starts
+        // writing before it's done reading can't deadlock
+        let (written, output) = thread::scope(|scope| {
+            let writer = scope.spawn(move || stdin.write_all(&input));
+            let output = child.wait_with_output();
+            (
+                writer.join().expect("writer thread shouldn't panic"),
+                output,
+            )
+        });
+
+        let output = output?;
+
+        if !output.status.success() {
+            bail!("command exited with {}", output.status);
+        }
+
+        // A command that exits successfully without reading all of its input
+        // is fine
+        if let Err(err) = written {
+            if err.kind() != io::ErrorKind::BrokenPipe {
+                return Err(err.into());
+            }
+        }
+
+        let output_image = png::decode(&output.stdout)?;
+
+        Ok(Image::new(
+            image.size(),
+            output_image.width,
+            output_image.height,
+            edit::rescale(image.xhot(), image.width(), output_image.width),
+            edit::rescale(image.yhot(), image.height(), output_image.height),
+            image.delay(),
+            output_image.pixels,
+        )?)
+    }
+}
+
+impl Transform for PipeFilter {
+    fn apply(&self, image: Image) -> resize_xcursor::Result<Image> {
+        self.run(&image).map_err(|err| {
+            io::Error::other(format!("pipe filter `{}` failed: {err:#}", self.command)).into()
+        })
+    }
+}
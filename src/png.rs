@@ -0,0 +1,96 @@
+//! Converting images to and from PNGs.
+//!
+//! Xcursor pixels are premultiplied ARGB, while PNGs use straight alpha,
+//! so pixels are converted in both directions.
+
+use anyhow::{bail, Context};
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+use std::{fs, io::Cursor, path::Path};
+
+/// A decoded PNG.
+pub(crate) struct PngImage {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Premultiplied ARGB pixels, row by row.
+    pub(crate) pixels: Vec<u32>,
+}
+
+/// Encodes premultiplied ARGB pixels as an RGBA PNG.
+pub(crate) fn encode(width: u32, height: u32, pixels: &[u32]) -> anyhow::Result<Vec<u8>> {
+    let mut res = Vec::new();
+    let mut encoder = Encoder::new(&mut res, width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+
+    let data = pixels
+        .iter()
+        .flat_map(|&pixel| unpremultiply(pixel))
+        .collect::<Vec<_>>();
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(res)
+}
+
+/// Decodes a PNG of any color type into premultiplied ARGB pixels.
+pub(crate) fn decode(bytes: &[u8]) -> anyhow::Result<PngImage> {
+    let mut decoder = Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info().context("invalid PNG")?;
+    let mut data = vec![0; reader.output_buffer_size().context("PNG is too large")?];
+    let info = reader.next_frame(&mut data).context("invalid PNG")?;
+    let data = &data[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        ColorType::Rgba => data
+            .chunks_exact(4)
+            .map(|pixel| premultiply([pixel[0], pixel[1], pixel[2], pixel[3]]))
+            .collect(),
+        ColorType::Rgb => data
+            .chunks_exact(3)
+            .map(|pixel| premultiply([pixel[0], pixel[1], pixel[2], 0xFF]))
+            .collect(),
+        ColorType::GrayscaleAlpha => data
+            .chunks_exact(2)
+            .map(|pixel| premultiply([pixel[0], pixel[0], pixel[0], pixel[1]]))
+            .collect(),
+        ColorType::Grayscale => data
+            .iter()
+            .map(|&value| premultiply([value, value, value, 0xFF]))
+            .collect(),
+        // `normalize_to_color8` expands palettes
+        ColorType::Indexed => bail!("unsupported PNG color type"),
+    };
+
+    Ok(PngImage {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+/// Reads and decodes a PNG file.
+pub(crate) fn read(path: &Path) -> anyhow::Result<PngImage> {
+    let bytes = fs::read(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    decode(&bytes).with_context(|| format!("couldn't decode {}", path.display()))
+}
+
+/// Converts a premultiplied ARGB pixel into straight RGBA bytes.
+fn unpremultiply(pixel: u32) -> [u8; 4] {
+    let [b, g, r, a] = pixel.to_le_bytes();
+
+    let unpremultiply = |channel: u8| match a {
+        0 => 0,
+        _ => ((u32::from(channel) * 255 + u32::from(a) / 2) / u32::from(a)).min(255) as u8,
+    };
+
+    [unpremultiply(r), unpremultiply(g), unpremultiply(b), a]
+}
+
+/// Converts straight RGBA bytes into a premultiplied ARGB pixel.
+fn premultiply([r, g, b, a]: [u8; 4]) -> u32 {
+    let premultiply = |channel: u8| ((u32::from(channel) * u32::from(a) + 127) / 255) as u8;
+    u32::from_le_bytes([premultiply(b), premultiply(g), premultiply(r), a])
+}
@@ -0,0 +1,56 @@
+//! A pool of pixel buffers, so that they can be reused between images (and
+//! files) instead of being reallocated for every one.
+
+use std::sync::{Mutex, MutexGuard};
+
+/// The most buffers to keep around at once. Buffers are only returned once
+/// an image has been written, so this only needs to cover the images that
+/// are in flight at the same time.
+const MAX_POOLED_BUFFERS: usize = 64;
+
+/// Buffers larger than this many pixels aren't kept, so that one huge image
+/// doesn't hold on to memory for the rest of the run.
+const MAX_POOLED_CAPACITY: usize = 1 << 22;
+
+static BUFFERS: Mutex<Vec<Vec<u32>>> = Mutex::new(Vec::new());
+
+/// Returns an empty buffer that can hold at least `capacity` pixels,
+/// reusing a pooled one if possible.
+pub(crate) fn take(capacity: usize) -> Vec<u32> {
+    let mut buffers = buffers();
+
+    let buffer = match buffers
+        .iter()
+        .position(|buffer| buffer.capacity() >= capacity)
+    {
+        Some(index) => Some(buffers.swap_remove(index)),
+        // Growing an existing buffer is still cheaper than starting over
+        None => buffers.pop(),
+    };
+
+    drop(buffers);
+
+    let mut buffer = buffer.unwrap_or_default();
+    buffer.reserve(capacity);
+    buffer
+}
+
+/// Returns a buffer to the pool once it's no longer needed.
+pub(crate) fn give(mut buffer: Vec<u32>) {
+    if buffer.capacity() == 0 || buffer.capacity() > MAX_POOLED_CAPACITY {
+        return;
+    }
+
+    let mut buffers = buffers();
+
+    if buffers.len() < MAX_POOLED_BUFFERS {
+        buffer.clear();
+        buffers.push(buffer);
+    }
+}
+
+fn buffers() -> MutexGuard<'static, Vec<Vec<u32>>> {
+    // The pool is only ever pushed to or popped from, so it's still
+    // fine to use if another thread panicked
+    BUFFERS.lock().unwrap_or_else(|err| err.into_inner())
+}
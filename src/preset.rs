@@ -0,0 +1,90 @@
+//! Presets that combine several transforms under one flag.
+
+use clap::ValueEnum;
+use resize_xcursor::{
+    filters::{Color, Outline, Shadow},
+    Image, ImageLayout, Pipeline, Transform,
+};
+
+/// A combination of transforms for a common use.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Preset {
+    /// Makes cursors easier to see for low-vision users, with a thick
+    /// outline that contrasts with each image and a subtle shadow. Cursors
+    /// are doubled in size unless `--scale` is given.
+    Accessibility,
+}
+
+impl Preset {
+    /// The scale to use if `--scale` isn't given.
+    pub(crate) fn default_scale(self) -> u32 {
+        match self {
+            Self::Accessibility => 2,
+        }
+    }
+
+    /// Adds this preset's transforms to the end of `pipeline`, for cursors
+    /// resized by `scale`.
+    pub(crate) fn push_to(self, pipeline: &mut Pipeline, scale: u32) {
+        match self {
+            Self::Accessibility => {
+                // Both of these are measured in the original cursor's pixels,
+                // so they look the same at any scale
+                let width = 2 * scale;
+                let offset = scale as i32;
+
+                pipeline.push(ContrastingOutline { width });
+                pipeline.push(Shadow::new(
+                    offset,
+                    offset,
+                    scale,
+                    Color {
+                        a: 0x60,
+                        ..Color::rgb(0, 0, 0)
+                    },
+                ));
+            }
+        }
+    }
+}
+
+/// An [`Outline`] that's white around dark images and black around light
+/// ones.
+#[derive(Debug)]
+struct ContrastingOutline {
+    width: u32,
+}
+
+impl ContrastingOutline {
+    fn outline(&self, color: Color) -> Outline {
+        Outline::new(self.width, color)
+    }
+}
+
+impl Transform for ContrastingOutline {
+    fn apply(&self, image: Image) -> resize_xcursor::Result<Image> {
+        // Since pixels are premultiplied, this weighs each pixel by its alpha
+        let (luminance, alpha) =
+            image
+                .pixels()
+                .iter()
+                .fold((0.0, 0.0), |(luminance, alpha), pixel| {
+                    let [b, g, r, a] = pixel.to_le_bytes().map(f32::from);
+                    let pixel_luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                    (luminance + pixel_luminance, alpha + a)
+                });
+
+        let color = if luminance < alpha / 2.0 {
+            Color::rgb(0xFF, 0xFF, 0xFF)
+        } else {
+            Color::rgb(0, 0, 0)
+        };
+
+        self.outline(color).apply(image)
+    }
+
+    fn layout(&self, layout: ImageLayout) -> resize_xcursor::Result<Option<ImageLayout>> {
+        // The outline's color doesn't affect its layout
+        self.outline(Color::default()).layout(layout)
+    }
+}
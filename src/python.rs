@@ -0,0 +1,124 @@
+//! Python bindings, enabled by the `python` feature.
+//!
+//! These are meant to be built with `maturin`:
+//!
+//! ```console
+//! $ maturin build --release --features python
+//! ```
+
+use crate::{Error, ResizeOptions, XcursorBuilder};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> Self {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+/// A single image in an Xcursor.
+#[pyclass(name = "Image", get_all, set_all, from_py_object)]
+#[derive(Clone)]
+struct PyImage {
+    size: u32,
+    width: u32,
+    height: u32,
+    xhot: u32,
+    yhot: u32,
+    delay: u32,
+    /// ARGB pixels, row by row.
+    pixels: Vec<u32>,
+}
+
+#[pymethods]
+impl PyImage {
+    #[new]
+    #[pyo3(signature = (size, width, height, xhot, yhot, delay, pixels))]
+    fn new(
+        size: u32,
+        width: u32,
+        height: u32,
+        xhot: u32,
+        yhot: u32,
+        delay: u32,
+        pixels: Vec<u32>,
+    ) -> Self {
+        Self {
+            size,
+            width,
+            height,
+            xhot,
+            yhot,
+            delay,
+            pixels,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Image(size={}, width={}, height={}, xhot={}, yhot={}, delay={})",
+            self.size, self.width, self.height, self.xhot, self.yhot, self.delay
+        )
+    }
+}
+
+/// Returns every image in an Xcursor file.
+#[pyfunction]
+fn parse(bytes: &[u8]) -> PyResult<Vec<PyImage>> {
+    let images = crate::parse(bytes)?
+        .into_iter()
+        .map(|image| PyImage {
+            size: image.info.size(),
+            width: image.info.width,
+            height: image.info.height,
+            xhot: image.info.xhot,
+            yhot: image.info.yhot,
+            delay: image.info.delay,
+            pixels: image.pixels().collect(),
+        })
+        .collect();
+
+    Ok(images)
+}
+
+/// Resizes an Xcursor file by the given scale factor, returning the bytes
+/// of the resized file.
+#[pyfunction]
+fn resize<'py>(py: Python<'py>, bytes: &[u8], scale: u32) -> PyResult<Bound<'py, PyBytes>> {
+    let cursor = crate::resize(bytes, &ResizeOptions::new(scale))?;
+    to_bytes(py, &cursor)
+}
+
+/// Builds an Xcursor file from a list of images, returning its bytes.
+#[pyfunction]
+fn write<'py>(py: Python<'py>, images: Vec<PyImage>) -> PyResult<Bound<'py, PyBytes>> {
+    let mut cursor = XcursorBuilder::new();
+
+    for image in images {
+        cursor.add_image(crate::Image::new(
+            image.size,
+            image.width,
+            image.height,
+            image.xhot,
+            image.yhot,
+            image.delay,
+            image.pixels,
+        )?)?;
+    }
+
+    to_bytes(py, &cursor)
+}
+
+fn to_bytes<'py>(py: Python<'py>, cursor: &XcursorBuilder) -> PyResult<Bound<'py, PyBytes>> {
+    let mut res = Vec::with_capacity(cursor.byte_length() as usize);
+    cursor.write_to(&mut res)?;
+    Ok(PyBytes::new(py, &res))
+}
+
+#[pymodule]
+fn resize_xcursor(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyImage>()?;
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(resize, module)?)?;
+    module.add_function(wrap_pyfunction!(write, module)?)?;
+    Ok(())
+}
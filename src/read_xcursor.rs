@@ -0,0 +1,1188 @@
+//! Facilities for reading Xcursor files.
+//!
+//! [`XcursorReader`] parses the file header and table of contents up front,
+//! but chunks are only parsed when iterating over [`XcursorReader::chunks`],
+//! and pixels are borrowed from the input rather than copied.
+
+// Information about Xcursor files can be found here:
+// https://www.x.org/archive/X11R7.7/doc/man/man3/Xcursor.3.xhtml
+
+use crate::{
+    pool,
+    write_xcursor::{Image, ImageLayout, RawChunk, TocEntry},
+    Error, ParseErrorKind, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    io::{Read, Seek, SeekFrom},
+};
+use tracing::trace;
+
+const XCURSOR_MAGIC: &[u8] = b"Xcur";
+const FILE_HEADER_MIN_SIZE: u32 = 16;
+const SIZE_OF_U32: usize = 4;
+
+/// The header at the start of every Xcursor file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHeader {
+    /// The length of the file header, in bytes.
+    ///
+    /// This is normally 16; the table of contents begins right after it.
+    pub header_size: u32,
+    pub version: u32,
+}
+
+impl Default for FileHeader {
+    /// The header used by `xcursorgen`, which is 16 bytes long and has a
+    /// version of `0x00010000`.
+    fn default() -> Self {
+        Self {
+            header_size: FILE_HEADER_MIN_SIZE,
+            version: 0x00010000,
+        }
+    }
+}
+
+/// A summary of an Xcursor file's structure, as returned by
+/// [`XcursorReader::info`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XcursorInfo {
+    pub header: FileHeader,
+    pub table_of_contents: Vec<TocEntry>,
+    pub images: Vec<ImageInfo>,
+}
+
+/// Caps on the size of an Xcursor file's contents, which are checked before
+/// anything is allocated for them.
+///
+/// Since cursor themes are often downloaded from the internet, these stop a
+/// malicious file from making this crate attempt huge allocations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Limits {
+    /// The maximum number of entries in the table of contents.
+    pub max_chunks: u32,
+    /// The maximum width or height of an image.
+    pub max_dimension: u32,
+    /// The maximum number of pixels in all of the file's images combined.
+    pub max_total_pixels: u64,
+}
+
+impl Limits {
+    /// Limits that allow any file. These shouldn't be used with untrusted files.
+    pub fn none() -> Self {
+        Self {
+            max_chunks: u32::MAX,
+            max_dimension: u32::MAX,
+            max_total_pixels: u64::MAX,
+        }
+    }
+
+    /// Checks an image's dimensions, adding its pixels to `total_pixels`.
+    fn check_image(
+        &self,
+        position: usize,
+        index: usize,
+        width: u32,
+        height: u32,
+        total_pixels: &mut u64,
+    ) -> Result<()> {
+        if width > self.max_dimension || height > self.max_dimension {
+            return Err(parse_error(
+                position + 16,
+                ParseErrorKind::ImageDimensionsExceeded {
+                    index,
+                    width,
+                    height,
+                    max: self.max_dimension,
+                },
+            ));
+        }
+
+        // This can't overflow, since both dimensions fit in a `u32`
+        *total_pixels = total_pixels.saturating_add(u64::from(width) * u64::from(height));
+
+        if *total_pixels > self.max_total_pixels {
+            return Err(parse_error(
+                position + 16,
+                ParseErrorKind::TotalPixelsExceeded {
+                    max: self.max_total_pixels,
+                },
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the number of entries in the table of contents.
+    fn check_chunks(&self, toc_length: u32) -> Result<()> {
+        if toc_length > self.max_chunks {
+            return Err(parse_error(
+                12,
+                ParseErrorKind::TooManyChunks {
+                    count: toc_length,
+                    max: self.max_chunks,
+                },
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The default limits allow far larger cursors than any real theme uses,
+/// but no images larger than libXcursor will load.
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_chunks: 1 << 16,
+            max_dimension: Image::MAX_DIMENSION,
+            max_total_pixels: 1 << 28,
+        }
+    }
+}
+
+/// Reads chunks out of an Xcursor file.
+#[derive(Clone, Debug)]
+pub struct XcursorReader<'a> {
+    bytes: &'a [u8],
+    header: FileHeader,
+    table_of_contents: Vec<TocEntry>,
+}
+
+impl<'a> XcursorReader<'a> {
+    /// Parses the file header and table of contents of an Xcursor file,
+    /// using the default [`Limits`].
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        Self::with_limits(bytes, Limits::default())
+    }
+
+    /// Parses the file header and table of contents of an Xcursor file,
+    /// checking its contents against `limits`.
+    ///
+    /// Every image's dimensions are checked here, so exceeding a limit
+    /// is always reported before any chunks are parsed.
+    pub fn with_limits(bytes: &'a [u8], limits: Limits) -> Result<Self> {
+        if !bytes.starts_with(XCURSOR_MAGIC) {
+            return Err(parse_error(0, ParseErrorKind::BadMagic));
+        }
+
+        let header_size = read_u32(bytes, 4, "the file header")?;
+        let version = read_u32(bytes, 8, "the file header")?;
+        let toc_length = read_u32(bytes, 12, "the file header")?;
+
+        if header_size < FILE_HEADER_MIN_SIZE {
+            return Err(parse_error(
+                4,
+                ParseErrorKind::BadFileHeaderSize(header_size),
+            ));
+        }
+
+        limits.check_chunks(toc_length)?;
+        trace!(
+            header_size,
+            version,
+            chunks = toc_length,
+            "read file header"
+        );
+
+        // Check that the whole table of contents is present before allocating
+        // space for it, so that a bogus `ntoc` can't cause a huge allocation
+        let toc_start = header_size as usize;
+        let toc_end = (toc_length as usize)
+            .checked_mul(TocEntry::BYTE_LENGTH as usize)
+            .and_then(|len| len.checked_add(toc_start));
+
+        if toc_end.is_none_or(|toc_end| toc_end > bytes.len()) {
+            return Err(parse_error(
+                bytes.len(),
+                ParseErrorKind::UnexpectedEof("the table of contents"),
+            ));
+        }
+
+        let table_of_contents = (0..toc_length as usize)
+            .map(|index| {
+                let offset = toc_start + index * TocEntry::BYTE_LENGTH as usize;
+
+                Ok(TocEntry {
+                    entry_type: read_u32(bytes, offset, "the table of contents")?,
+                    subtype: read_u32(bytes, offset + 4, "the table of contents")?,
+                    position: read_u32(bytes, offset + 8, "the table of contents")?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Image headers that are out of bounds are reported when they're parsed
+        let mut total_pixels = 0;
+
+        for (index, toc_entry) in table_of_contents.iter().enumerate() {
+            let position = toc_entry.position as usize;
+
+            if toc_entry.entry_type != Image::TYPE {
+                continue;
+            }
+
+            if let (Ok(width), Ok(height)) = (
+                read_u32(bytes, position.saturating_add(16), "an image"),
+                read_u32(bytes, position.saturating_add(20), "an image"),
+            ) {
+                limits.check_image(position, index, width, height, &mut total_pixels)?;
+            }
+        }
+
+        Ok(Self {
+            bytes,
+            header: FileHeader {
+                header_size,
+                version,
+            },
+            table_of_contents,
+        })
+    }
+
+    /// Returns the file header.
+    pub fn header(&self) -> FileHeader {
+        self.header
+    }
+
+    /// Returns the table of contents, in the order it appears in the file.
+    pub fn table_of_contents(&self) -> &[TocEntry] {
+        &self.table_of_contents
+    }
+
+    /// Collects the file header, table of contents, and the fields of every
+    /// image, without reading any pixels.
+    pub fn info(&self) -> Result<XcursorInfo> {
+        Ok(XcursorInfo {
+            header: self.header,
+            table_of_contents: self.table_of_contents.clone(),
+            images: self.image_infos().collect::<Result<_>>()?,
+        })
+    }
+
+    /// Returns the bytes this reader is reading from.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Parses the chunk described by the table of contents entry at `index`.
+    pub fn chunk(&self, index: usize) -> Result<Chunk<'a>> {
+        let header = self.chunk_header(index)?;
+        let position = self.table_of_contents[index].position as usize;
+
+        match header.chunk_type {
+            Image::TYPE => self.image(position, header).map(Chunk::Image),
+            ParsedComment::TYPE => self.comment(position, header).map(Chunk::Comment),
+            _ => Ok(Chunk::Unknown(UnknownChunk {
+                position: position as u32,
+                header,
+                bytes: &self.bytes[position..self.chunk_end(position)],
+            })),
+        }
+    }
+
+    /// Parses the header of the chunk described by the table of contents
+    /// entry at `index`, checking that it matches the entry.
+    pub fn chunk_header(&self, index: usize) -> Result<ChunkHeader> {
+        let toc_entry = self.table_of_contents[index];
+        let position = toc_entry.position as usize;
+
+        if position >= self.bytes.len() {
+            return Err(parse_error(
+                to_offset(toc_position_offset(self.header, index)),
+                ParseErrorKind::ChunkOutOfBounds { index },
+            ));
+        }
+
+        let header = ChunkHeader {
+            header_size: read_u32(self.bytes, position, "a chunk header")?,
+            chunk_type: read_u32(self.bytes, position + 4, "a chunk header")?,
+            subtype: read_u32(self.bytes, position + 8, "a chunk header")?,
+            version: read_u32(self.bytes, position + 12, "a chunk header")?,
+        };
+
+        check_chunk_header(header, toc_entry, index)?;
+        Ok(header)
+    }
+
+    /// Returns an iterator that parses each chunk, in table of contents order.
+    pub fn chunks(&self) -> impl Iterator<Item = Result<Chunk<'a>>> + '_ {
+        (0..self.table_of_contents.len()).map(|index| self.chunk(index))
+    }
+
+    /// Returns an iterator that parses each image chunk, skipping
+    /// any other kinds of chunk.
+    pub fn images(&self) -> impl Iterator<Item = Result<ParsedImage<'a>>> + '_ {
+        self.chunks().filter_map(|chunk| match chunk {
+            Ok(Chunk::Image(image)) => Some(Ok(image)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+    }
+
+    /// Returns an iterator over the fields of each image chunk, without
+    /// reading (or even checking for) any of their pixels. Other kinds of
+    /// chunk are skipped without being parsed.
+    pub fn image_infos(&self) -> impl Iterator<Item = Result<ImageInfo>> + '_ {
+        self.table_of_contents
+            .iter()
+            .enumerate()
+            .filter(|(_, toc_entry)| toc_entry.entry_type == Image::TYPE)
+            .map(|(index, toc_entry)| {
+                let header = self.chunk_header(index)?;
+                self.image_info(toc_entry.position as usize, header)
+            })
+    }
+
+    fn image_info(&self, position: usize, header: ChunkHeader) -> Result<ImageInfo> {
+        let field = |index| read_u32(self.bytes, position + 16 + index * SIZE_OF_U32, "an image");
+
+        Ok(ImageInfo {
+            position: position as u32,
+            header,
+            width: field(0)?,
+            height: field(1)?,
+            xhot: field(2)?,
+            yhot: field(3)?,
+            delay: field(4)?,
+        })
+    }
+
+    fn image(&self, position: usize, header: ChunkHeader) -> Result<ParsedImage<'a>> {
+        let info = self.image_info(position, header)?;
+        let pixels_start = position + Image::HEADER_SIZE as usize;
+        let pixels_len = info.pixels_byte_length().try_into().unwrap_or(usize::MAX);
+        let pixels_end = pixels_start.saturating_add(pixels_len);
+
+        if pixels_end > self.bytes.len() {
+            return Err(parse_error(
+                self.bytes.len(),
+                ParseErrorKind::UnexpectedEof("an image's pixels"),
+            ));
+        }
+
+        Ok(ParsedImage {
+            info,
+            pixel_bytes: &self.bytes[pixels_start..pixels_end],
+        })
+    }
+
+    fn comment(&self, position: usize, header: ChunkHeader) -> Result<ParsedComment<'a>> {
+        let len = read_u32(self.bytes, position + 16, "a comment")? as usize;
+        let text_start = position + ParsedComment::HEADER_SIZE as usize;
+        let text_end = text_start.saturating_add(len);
+
+        if text_end > self.bytes.len() {
+            return Err(parse_error(
+                self.bytes.len(),
+                ParseErrorKind::UnexpectedEof("a comment"),
+            ));
+        }
+
+        Ok(ParsedComment {
+            position: position as u32,
+            header,
+            text: &self.bytes[text_start..text_end],
+        })
+    }
+
+    /// Guesses where the chunk at `position` ends, since unknown chunks
+    /// don't declare their length. This is either the start of the next chunk
+    /// or the end of the file.
+    fn chunk_end(&self, position: usize) -> usize {
+        self.table_of_contents
+            .iter()
+            .map(|toc_entry| toc_entry.position as usize)
+            .filter(|&other| other > position)
+            .min()
+            .unwrap_or(self.bytes.len())
+            .min(self.bytes.len())
+    }
+}
+
+/// Reads images out of an Xcursor file one at a time, seeking to each one
+/// instead of reading the whole file into memory.
+///
+/// This uses much less memory than [`XcursorReader`] for large files, but
+/// pixels have to be copied into a buffer instead of being borrowed.
+#[derive(Debug)]
+pub struct SeekReader<R> {
+    reader: R,
+    /// The length of the file, in bytes.
+    len: u64,
+    limits: Limits,
+    header: FileHeader,
+    table_of_contents: Vec<TocEntry>,
+}
+
+impl<R: Read + Seek> SeekReader<R> {
+    /// Parses the file header and table of contents of an Xcursor file,
+    /// using the default [`Limits`].
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_limits(reader, Limits::default())
+    }
+
+    /// Parses the file header and table of contents of an Xcursor file,
+    /// checking its contents against `limits`.
+    ///
+    /// Unlike [`XcursorReader::with_limits`], image dimensions are only
+    /// checked by [`SeekReader::image_infos`].
+    pub fn with_limits(mut reader: R, limits: Limits) -> Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        let mut header_bytes = Vec::new();
+        (&mut reader)
+            .take(FILE_HEADER_MIN_SIZE.into())
+            .read_to_end(&mut header_bytes)?;
+
+        if !header_bytes.starts_with(XCURSOR_MAGIC) {
+            return Err(parse_error(0, ParseErrorKind::BadMagic));
+        }
+
+        let header_size = read_u32(&header_bytes, 4, "the file header")?;
+        let version = read_u32(&header_bytes, 8, "the file header")?;
+        let toc_length = read_u32(&header_bytes, 12, "the file header")?;
+
+        if header_size < FILE_HEADER_MIN_SIZE {
+            return Err(parse_error(
+                4,
+                ParseErrorKind::BadFileHeaderSize(header_size),
+            ));
+        }
+
+        limits.check_chunks(toc_length)?;
+        trace!(
+            header_size,
+            version,
+            chunks = toc_length,
+            "read file header"
+        );
+
+        // As in `XcursorReader::new`, a bogus `ntoc` mustn't cause a huge
+        // allocation. This can't overflow, since both fields are `u32`s.
+        let toc_byte_length = u64::from(toc_length) * u64::from(TocEntry::BYTE_LENGTH);
+
+        if u64::from(header_size) + toc_byte_length > len {
+            return Err(parse_error(
+                to_offset(len),
+                ParseErrorKind::UnexpectedEof("the table of contents"),
+            ));
+        }
+
+        let mut toc_bytes = vec![0; toc_byte_length as usize];
+        reader.seek(SeekFrom::Start(header_size.into()))?;
+        reader.read_exact(&mut toc_bytes)?;
+
+        let table_of_contents = toc_bytes
+            .chunks_exact(TocEntry::BYTE_LENGTH as usize)
+            .map(|entry| {
+                Ok(TocEntry {
+                    entry_type: read_u32(entry, 0, "the table of contents")?,
+                    subtype: read_u32(entry, 4, "the table of contents")?,
+                    position: read_u32(entry, 8, "the table of contents")?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            reader,
+            len,
+            limits,
+            header: FileHeader {
+                header_size,
+                version,
+            },
+            table_of_contents,
+        })
+    }
+
+    /// Returns the file header.
+    pub fn header(&self) -> FileHeader {
+        self.header
+    }
+
+    /// Returns the table of contents, in the order it appears in the file.
+    pub fn table_of_contents(&self) -> &[TocEntry] {
+        &self.table_of_contents
+    }
+
+    /// Reads the fields of each image chunk, without reading any of their
+    /// pixels. Other kinds of chunk are skipped.
+    ///
+    /// This fails if any image exceeds the [`Limits`] this reader was
+    /// created with.
+    pub fn image_infos(&mut self) -> Result<Vec<ImageInfo>> {
+        let mut res = Vec::new();
+        let mut bytes = [0; Image::HEADER_SIZE as usize];
+        let mut total_pixels = 0;
+
+        for index in 0..self.table_of_contents.len() {
+            let toc_entry = self.table_of_contents[index];
+
+            if toc_entry.entry_type != Image::TYPE {
+                continue;
+            }
+
+            let position = u64::from(toc_entry.position);
+
+            if position >= self.len {
+                return Err(parse_error(
+                    to_offset(toc_position_offset(self.header, index)),
+                    ParseErrorKind::ChunkOutOfBounds { index },
+                ));
+            }
+
+            if position + u64::from(Image::HEADER_SIZE) > self.len {
+                return Err(parse_error(
+                    to_offset(self.len),
+                    ParseErrorKind::UnexpectedEof("an image"),
+                ));
+            }
+
+            self.reader.seek(SeekFrom::Start(position))?;
+            self.reader.read_exact(&mut bytes)?;
+
+            let field = |index| read_u32(&bytes, index * SIZE_OF_U32, "an image");
+
+            let header = ChunkHeader {
+                header_size: field(0)?,
+                chunk_type: field(1)?,
+                subtype: field(2)?,
+                version: field(3)?,
+            };
+
+            check_chunk_header(header, toc_entry, index)?;
+
+            let width = field(4)?;
+            let height = field(5)?;
+
+            self.limits.check_image(
+                to_offset(position),
+                index,
+                width,
+                height,
+                &mut total_pixels,
+            )?;
+
+            res.push(ImageInfo {
+                position: toc_entry.position,
+                header,
+                width,
+                height,
+                xhot: field(6)?,
+                yhot: field(7)?,
+                delay: field(8)?,
+            });
+        }
+
+        Ok(res)
+    }
+
+    /// Reads every chunk that isn't an image, such as comments, so that they
+    /// can be written out again.
+    ///
+    /// As with [`UnknownChunk`], chunks of unknown types are assumed to
+    /// extend to the start of the next chunk (or the end of the file).
+    pub fn raw_chunks(&mut self) -> Result<Vec<RawChunk>> {
+        let mut res = Vec::new();
+
+        for index in 0..self.table_of_contents.len() {
+            let toc_entry = self.table_of_contents[index];
+
+            if toc_entry.entry_type == Image::TYPE {
+                continue;
+            }
+
+            let position = u64::from(toc_entry.position);
+
+            if position >= self.len {
+                return Err(parse_error(
+                    to_offset(toc_position_offset(self.header, index)),
+                    ParseErrorKind::ChunkOutOfBounds { index },
+                ));
+            }
+
+            let end = if toc_entry.entry_type == ParsedComment::TYPE {
+                let text_start = position + u64::from(ParsedComment::HEADER_SIZE);
+
+                if text_start > self.len {
+                    return Err(parse_error(
+                        to_offset(self.len),
+                        ParseErrorKind::UnexpectedEof("a comment"),
+                    ));
+                }
+
+                let mut len = [0; SIZE_OF_U32];
+                self.reader.seek(SeekFrom::Start(text_start - 4))?;
+                self.reader.read_exact(&mut len)?;
+
+                let end = text_start + u64::from(u32::from_le_bytes(len));
+
+                if end > self.len {
+                    return Err(parse_error(
+                        to_offset(self.len),
+                        ParseErrorKind::UnexpectedEof("a comment"),
+                    ));
+                }
+
+                end
+            } else {
+                self.table_of_contents
+                    .iter()
+                    .map(|toc_entry| u64::from(toc_entry.position))
+                    .filter(|&other| other > position)
+                    .min()
+                    .unwrap_or(self.len)
+                    .min(self.len)
+            };
+
+            if end - position < 16 {
+                return Err(parse_error(
+                    to_offset(end),
+                    ParseErrorKind::UnexpectedEof("a chunk header"),
+                ));
+            }
+
+            // The checks above mean that this fits in the file
+            let mut bytes = vec![0; (end - position) as usize];
+            self.reader.seek(SeekFrom::Start(position))?;
+            self.reader.read_exact(&mut bytes)?;
+
+            let field = |index| read_u32(&bytes, index * SIZE_OF_U32, "a chunk header");
+
+            let header = ChunkHeader {
+                header_size: field(0)?,
+                chunk_type: field(1)?,
+                subtype: field(2)?,
+                version: field(3)?,
+            };
+
+            check_chunk_header(header, toc_entry, index)?;
+
+            res.push(RawChunk {
+                entry_type: header.chunk_type,
+                subtype: header.subtype,
+                bytes,
+            });
+        }
+
+        Ok(res)
+    }
+
+    /// Reads the pixels of an image returned by [`SeekReader::image_infos`]
+    /// into `buffer`, replacing its contents.
+    pub fn read_pixels(&mut self, info: &ImageInfo, buffer: &mut Vec<u8>) -> Result<()> {
+        let pixels_start = u64::from(info.position) + u64::from(Image::HEADER_SIZE);
+        let pixels_len = info.pixels_byte_length();
+
+        if pixels_start.saturating_add(pixels_len) > self.len {
+            return Err(parse_error(
+                to_offset(self.len),
+                ParseErrorKind::UnexpectedEof("an image's pixels"),
+            ));
+        }
+
+        // The check above means that this fits in memory, or at least in
+        // the file
+        buffer.clear();
+        buffer.resize(pixels_len as usize, 0);
+
+        self.reader.seek(SeekFrom::Start(pixels_start))?;
+        self.reader.read_exact(buffer)?;
+        Ok(())
+    }
+}
+
+/// The fields at the start of every chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkHeader {
+    /// The length of the chunk's header, in bytes.
+    pub header_size: u32,
+    pub chunk_type: u32,
+    pub subtype: u32,
+    pub version: u32,
+}
+
+/// A chunk read from an Xcursor file.
+#[derive(Clone, Debug)]
+pub enum Chunk<'a> {
+    Image(ParsedImage<'a>),
+    Comment(ParsedComment<'a>),
+    /// A chunk of a type this crate doesn't know about.
+    Unknown(UnknownChunk<'a>),
+}
+
+impl Chunk<'_> {
+    /// The offset of this chunk from the start of the file.
+    pub fn position(&self) -> u32 {
+        match self {
+            Self::Image(image) => image.info.position,
+            Self::Comment(comment) => comment.position,
+            Self::Unknown(chunk) => chunk.position,
+        }
+    }
+
+    /// Returns the fields at the start of this chunk.
+    pub fn header(&self) -> ChunkHeader {
+        match self {
+            Self::Image(image) => image.info.header,
+            Self::Comment(comment) => comment.header,
+            Self::Unknown(chunk) => chunk.header,
+        }
+    }
+
+    /// The number of bytes this chunk takes up in the file, including its
+    /// header.
+    pub fn byte_length(&self) -> u64 {
+        match self {
+            Self::Image(image) => u64::from(Image::HEADER_SIZE) + image.pixel_bytes.len() as u64,
+            Self::Comment(comment) => {
+                u64::from(ParsedComment::HEADER_SIZE) + comment.text.len() as u64
+            }
+            Self::Unknown(chunk) => chunk.bytes.len() as u64,
+        }
+    }
+}
+
+/// The fields of an image chunk, not including its pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageInfo {
+    /// The offset of this chunk from the start of the file.
+    pub position: u32,
+    pub header: ChunkHeader,
+    pub width: u32,
+    pub height: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    /// The number of milliseconds to show this image for when animating.
+    pub delay: u32,
+}
+
+impl ImageInfo {
+    /// The image's nominal size, which is stored in its subtype.
+    pub fn size(&self) -> u32 {
+        self.header.subtype
+    }
+
+    /// Returns the image's nominal size and dimensions.
+    pub fn layout(&self) -> ImageLayout {
+        ImageLayout {
+            size: self.size(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// The length of the image's pixel data, in bytes.
+    pub fn pixels_byte_length(&self) -> u64 {
+        // Only unlimited readers see dimensions this large, and they're
+        // compared with what's left of the file, so saturating is enough
+        (u64::from(self.width) * u64::from(self.height)).saturating_mul(SIZE_OF_U32 as u64)
+    }
+}
+
+/// An image read from an Xcursor file.
+#[derive(Clone, Debug)]
+pub struct ParsedImage<'a> {
+    pub info: ImageInfo,
+    /// The image's ARGB pixels, stored as little-endian `u32`s.
+    pub pixel_bytes: &'a [u8],
+}
+
+impl ParsedImage<'_> {
+    /// Returns an iterator over this image's ARGB pixels, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = u32> + '_ {
+        pixels_from_bytes(self.pixel_bytes)
+    }
+
+    /// Copies this image into an [`Image`] that can be written out again.
+    pub fn to_image(&self) -> Result<Image> {
+        let mut pixels = pool::take(self.pixel_bytes.len() / SIZE_OF_U32);
+        pixels.extend(self.pixels());
+
+        Image::new(
+            self.info.size(),
+            self.info.width,
+            self.info.height,
+            self.info.xhot,
+            self.info.yhot,
+            self.info.delay,
+            pixels,
+        )
+    }
+}
+
+/// A comment read from an Xcursor file.
+#[derive(Clone, Debug)]
+pub struct ParsedComment<'a> {
+    /// The offset of this chunk from the start of the file.
+    pub position: u32,
+    pub header: ChunkHeader,
+    /// The comment's text, which should be UTF-8.
+    pub text: &'a [u8],
+}
+
+impl ParsedComment<'_> {
+    /// The chunk type of comments.
+    pub const TYPE: u32 = 0xFFFE0001;
+    /// The length of a comment chunk's header, in bytes, which its text
+    /// follows.
+    pub const HEADER_SIZE: u32 = 20;
+    pub(crate) const VERSION: u32 = 1;
+
+    /// The kind of comment this is (copyright, license, or other).
+    pub fn kind(&self) -> CommentKind {
+        match self.header.subtype {
+            1 => CommentKind::Copyright,
+            2 => CommentKind::License,
+            _ => CommentKind::Other,
+        }
+    }
+
+    /// Returns the comment's text, replacing any invalid UTF-8.
+    pub fn text_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.text)
+    }
+
+    /// Copies this comment into a [`RawChunk`] that can be written out again.
+    pub fn to_raw_chunk(&self) -> RawChunk {
+        let mut bytes = Vec::with_capacity(Self::HEADER_SIZE as usize + self.text.len());
+
+        for field in [
+            self.header.header_size,
+            self.header.chunk_type,
+            self.header.subtype,
+            self.header.version,
+            // This was read from a `u32`, so it fits in one
+            self.text.len() as u32,
+        ] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(self.text);
+
+        RawChunk {
+            entry_type: self.header.chunk_type,
+            subtype: self.header.subtype,
+            bytes,
+        }
+    }
+}
+
+/// The kinds of comment that an Xcursor file can contain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentKind {
+    Copyright,
+    License,
+    Other,
+}
+
+impl CommentKind {
+    /// The subtype that comments of this kind are written with.
+    pub fn subtype(self) -> u32 {
+        match self {
+            Self::Copyright => 1,
+            Self::License => 2,
+            Self::Other => 3,
+        }
+    }
+}
+
+/// A chunk of a type this crate doesn't know about.
+#[derive(Clone, Debug)]
+pub struct UnknownChunk<'a> {
+    /// The offset of this chunk from the start of the file.
+    pub position: u32,
+    pub header: ChunkHeader,
+    /// The whole chunk, including its header.
+    ///
+    /// Since unknown chunks don't declare their length, this is assumed to
+    /// extend to the start of the next chunk (or the end of the file).
+    pub bytes: &'a [u8],
+}
+
+impl UnknownChunk<'_> {
+    /// Copies this chunk into a [`RawChunk`] that can be written out again.
+    pub fn to_raw_chunk(&self) -> RawChunk {
+        RawChunk {
+            entry_type: self.header.chunk_type,
+            subtype: self.header.subtype,
+            bytes: self.bytes.to_vec(),
+        }
+    }
+}
+
+/// Borrows a [`RawChunk`] as if it had been read from a file (at position
+/// 0), so that it can be passed along with parsed chunks to functions like
+/// [`transform_chunks_to`](crate::transform_chunks_to).
+impl<'a> From<&'a RawChunk> for Chunk<'a> {
+    fn from(chunk: &'a RawChunk) -> Self {
+        let field = |offset: usize| read_u32(&chunk.bytes, offset, "a chunk header").unwrap_or(0);
+
+        let header = ChunkHeader {
+            header_size: field(0),
+            chunk_type: chunk.entry_type,
+            subtype: chunk.subtype,
+            version: field(12),
+        };
+
+        match chunk.bytes.get(ParsedComment::HEADER_SIZE as usize..) {
+            Some(text) if chunk.entry_type == ParsedComment::TYPE => Self::Comment(ParsedComment {
+                position: 0,
+                header,
+                text,
+            }),
+
+            _ => Self::Unknown(UnknownChunk {
+                position: 0,
+                header,
+                bytes: &chunk.bytes,
+            }),
+        }
+    }
+}
+
+impl<'a> From<ParsedImage<'a>> for Chunk<'a> {
+    fn from(image: ParsedImage<'a>) -> Self {
+        Self::Image(image)
+    }
+}
+
+/// Parses every image out of an Xcursor file.
+pub fn parse(bytes: &[u8]) -> Result<Vec<ParsedImage<'_>>> {
+    XcursorReader::new(bytes)?.images().collect()
+}
+
+/// Parses every chunk out of an Xcursor file, including comments and
+/// chunks of unknown types.
+pub fn parse_chunks(bytes: &[u8]) -> Result<Vec<Chunk<'_>>> {
+    XcursorReader::new(bytes)?.chunks().collect()
+}
+
+/// Decodes little-endian ARGB pixels, ignoring any trailing partial pixel.
+pub(crate) fn pixels_from_bytes(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes
+        .chunks_exact(SIZE_OF_U32)
+        .map(|pixel| u32::from_le_bytes(pixel.try_into().unwrap()))
+}
+
+/// Returns the offset of the position field of the table of contents entry
+/// at `index`, which is where a position that's out of bounds is reported.
+fn toc_position_offset(header: FileHeader, index: usize) -> u64 {
+    u64::from(header.header_size) + index as u64 * u64::from(TocEntry::BYTE_LENGTH) + 8
+}
+
+/// Checks that a chunk's header matches the table of contents entry at
+/// `index`, which points to it.
+fn check_chunk_header(header: ChunkHeader, toc_entry: TocEntry, index: usize) -> Result<()> {
+    if header.chunk_type != toc_entry.entry_type || header.subtype != toc_entry.subtype {
+        return Err(parse_error(
+            toc_entry.position as usize + 4,
+            ParseErrorKind::ChunkMismatch {
+                index,
+                expected: toc_entry.entry_type,
+                expected_subtype: toc_entry.subtype,
+                found: header.chunk_type,
+                found_subtype: header.subtype,
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+/// Converts a position in a file into an offset for a [`ParseErrorKind`].
+fn to_offset(position: u64) -> usize {
+    position.try_into().unwrap_or(usize::MAX)
+}
+
+fn read_u32(bytes: &[u8], offset: usize, what: &'static str) -> Result<u32> {
+    match bytes.get(offset..offset.saturating_add(SIZE_OF_U32)) {
+        Some(field) => Ok(u32::from_le_bytes(field.try_into().unwrap())),
+        None => Err(parse_error(
+            bytes.len().min(offset),
+            ParseErrorKind::UnexpectedEof(what),
+        )),
+    }
+}
+
+fn parse_error(offset: usize, kind: ParseErrorKind) -> Error {
+    Error::Parse { offset, kind }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::XcursorBuilder;
+    use std::io::Cursor;
+
+    /// Where the first image starts in [`two_frames`], after the file header
+    /// and two table of contents entries.
+    const FIRST_IMAGE: usize = 40;
+
+    /// A valid animation with two 1x1 frames, for tests to break.
+    fn two_frames() -> Vec<u8> {
+        let mut cursor = XcursorBuilder::new();
+
+        for pixel in [0xFF000000, 0xFFFFFFFF] {
+            cursor
+                .add_image(Image::new(1, 1, 1, 0, 0, 50, vec![pixel]).unwrap())
+                .unwrap();
+        }
+
+        let mut bytes = Vec::new();
+        cursor.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn set_field(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn parse_error_kind<T>(res: Result<T>) -> (usize, ParseErrorKind) {
+        match res {
+            Err(Error::Parse { offset, kind }) => (offset, kind),
+            Err(err) => panic!("expected a parse error, got {err}"),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn truncated_toc() {
+        let mut bytes = two_frames();
+        bytes.truncate(30);
+
+        assert_eq!(
+            parse_error_kind(XcursorReader::new(&bytes)),
+            (30, ParseErrorKind::UnexpectedEof("the table of contents"))
+        );
+        assert_eq!(
+            parse_error_kind(SeekReader::new(Cursor::new(&bytes))).1,
+            ParseErrorKind::UnexpectedEof("the table of contents")
+        );
+    }
+
+    #[test]
+    fn toc_longer_than_file() {
+        let mut bytes = two_frames();
+        let len = bytes.len();
+
+        // Even without limits, this is rejected before anything is allocated
+        for toc_length in [1000, u32::MAX] {
+            set_field(&mut bytes, 12, toc_length);
+
+            assert_eq!(
+                parse_error_kind(XcursorReader::with_limits(&bytes, Limits::none())),
+                (len, ParseErrorKind::UnexpectedEof("the table of contents"))
+            );
+            assert_eq!(
+                parse_error_kind(SeekReader::with_limits(Cursor::new(&bytes), Limits::none())).1,
+                ParseErrorKind::UnexpectedEof("the table of contents")
+            );
+        }
+    }
+
+    #[test]
+    fn too_many_chunks() {
+        let bytes = two_frames();
+        let limits = Limits {
+            max_chunks: 1,
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            parse_error_kind(XcursorReader::with_limits(&bytes, limits)),
+            (12, ParseErrorKind::TooManyChunks { count: 2, max: 1 })
+        );
+    }
+
+    #[test]
+    fn oversized_image_header() {
+        let mut bytes = two_frames();
+        set_field(&mut bytes, FIRST_IMAGE + 16, u32::MAX);
+        set_field(&mut bytes, FIRST_IMAGE + 20, u32::MAX);
+
+        assert_eq!(
+            parse_error_kind(XcursorReader::new(&bytes)),
+            (
+                FIRST_IMAGE + 16,
+                ParseErrorKind::ImageDimensionsExceeded {
+                    index: 0,
+                    width: u32::MAX,
+                    height: u32::MAX,
+                    max: Limits::default().max_dimension,
+                }
+            )
+        );
+
+        // Without limits, the pixels are found to be missing instead of
+        // being allocated
+        let reader = XcursorReader::with_limits(&bytes, Limits::none()).unwrap();
+        assert_eq!(
+            parse_error_kind(reader.chunk(0)),
+            (
+                bytes.len(),
+                ParseErrorKind::UnexpectedEof("an image's pixels")
+            )
+        );
+    }
+
+    #[test]
+    fn total_pixels_exceeded() {
+        let bytes = two_frames();
+        let limits = Limits {
+            max_total_pixels: 1,
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            parse_error_kind(XcursorReader::with_limits(&bytes, limits)),
+            (
+                FIRST_IMAGE + 40 + 16,
+                ParseErrorKind::TotalPixelsExceeded { max: 1 }
+            )
+        );
+    }
+
+    #[test]
+    fn oversized_chunk_header_size() {
+        // Image pixels always start right after the fields libXcursor knows
+        // about, so a chunk's header size is never used to find them
+        let mut bytes = two_frames();
+        set_field(&mut bytes, FIRST_IMAGE, u32::MAX);
+
+        let reader = XcursorReader::new(&bytes).unwrap();
+        let Chunk::Image(image) = reader.chunk(0).unwrap() else {
+            panic!("expected an image");
+        };
+
+        assert_eq!(image.info.header.header_size, u32::MAX);
+        assert!(image.pixels().eq([0xFF000000]));
+    }
+
+    #[test]
+    fn overlapping_chunks() {
+        // Both entries point to the first image
+        let mut bytes = two_frames();
+        set_field(&mut bytes, 16 + 12 + 8, FIRST_IMAGE as u32);
+
+        let images = parse(&bytes).unwrap();
+        assert_eq!(images.len(), 2);
+        assert!(images.iter().all(|image| image.pixels().eq([0xFF000000])));
+    }
+
+    #[test]
+    fn chunk_out_of_bounds() {
+        let mut bytes = two_frames();
+        let len = bytes.len() as u32;
+        set_field(&mut bytes, 16 + 12 + 8, len);
+
+        assert_eq!(
+            parse_error_kind(parse(&bytes)),
+            (16 + 12 + 8, ParseErrorKind::ChunkOutOfBounds { index: 1 })
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Implementation of `repair`.
+
+use crate::{input, output, DedupeSizes};
+use anyhow::{bail, Context};
+use clap::Args;
+use resize_xcursor::validation::{dedupe_sizes, repair, salvage, validate, ViolationKind};
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+use tracing::{info, warn};
+
+#[derive(Args)]
+pub(crate) struct RepairArgs {
+    /// The Xcursor file to repair.
+    #[clap(value_parser)]
+    cursor: PathBuf,
+
+    /// Where to write the repaired cursor, instead of replacing the original.
+    /// `-` writes it to stdout.
+    #[clap(short, long, value_parser, verbatim_doc_comment)]
+    output: Option<PathBuf>,
+
+    /// If given, recovers what it can from files that are truncated or
+    /// otherwise damaged, leaving out any chunks that can't be repaired.
+    #[clap(long, verbatim_doc_comment)]
+    salvage: bool,
+
+    /// Which images to keep when a nominal size is split into separate
+    /// groups. Files with these duplicates can't be repaired without it.
+    #[clap(long, value_enum, value_name = "KEEP", verbatim_doc_comment)]
+    dedupe_sizes: Option<DedupeSizes>,
+}
+
+pub(crate) fn run(args: RepairArgs) -> anyhow::Result<()> {
+    let bytes = input::read(&args.cursor)
+        .with_context(|| format!("couldn't read {}", args.cursor.display()))?;
+    let violations = validate(&bytes);
+
+    if violations.is_empty() {
+        info!("{} is already valid", args.cursor.display());
+        return Ok(());
+    }
+
+    for violation in &violations {
+        warn!("{}: {}", args.cursor.display(), violation);
+    }
+
+    // Which duplicates to throw away is up to the user
+    if args.dedupe_sizes.is_none()
+        && violations
+            .iter()
+            .any(|violation| matches!(violation.kind, ViolationKind::DuplicateSize { .. }))
+    {
+        bail!(
+            "{} has nominal sizes that are split into separate groups (use --dedupe-sizes to pick which to keep)",
+            args.cursor.display()
+        );
+    }
+
+    let mut cursor = if args.salvage {
+        salvage(&bytes).map(|salvaged| {
+            for err in &salvaged.lost {
+                warn!("{}: left out a chunk: {err}", args.cursor.display());
+            }
+
+            salvaged.cursor
+        })
+    } else {
+        repair(&bytes)
+    }
+    .with_context(|| format!("couldn't repair {}", args.cursor.display()))?;
+
+    if let Some(keep) = args.dedupe_sizes {
+        dedupe_sizes(&mut cursor, keep.into());
+    }
+
+    // The repaired cursor is checked before it replaces anything
+    let mut repaired = Vec::new();
+    cursor.write_to(&mut repaired)?;
+
+    if let Some(violation) = validate(&repaired).first() {
+        bail!(
+            "couldn't repair {} ({violation} after repairing)",
+            args.cursor.display()
+        );
+    }
+
+    let output = args.output.as_deref().unwrap_or(&args.cursor);
+
+    if output::is_stdout(output) && io::stdout().is_terminal() {
+        bail!("refusing to write a cursor to a terminal (redirect stdout to a file instead)");
+    }
+
+    // The input is memory-mapped with the `mmap` feature, so it has to be
+    // released before the original file is replaced
+    drop(bytes);
+
+    output::write(output, |output| Ok(output.write_all(&repaired)?))
+        .with_context(|| format!("couldn't write {}", output.display()))?;
+
+    info!(
+        "repaired {} problems in {}",
+        violations.len(),
+        args.cursor.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Algorithms for scaling an image's pixels.
+
+use crate::pool;
+use std::{fmt::Debug, iter};
+
+/// An image's ARGB pixels, row by row, along with its dimensions.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: &'a [u32],
+}
+
+/// An algorithm for scaling an image's pixels.
+///
+/// Implementing this allows a custom algorithm to be used by
+/// [`resize`](crate::resize) and friends through
+/// [`ResizeOptions::resampler`](crate::ResizeOptions::resampler).
+pub trait Resampler: Debug + Send + Sync {
+    /// Scales `frame` to `width` by `height` pixels, returning ARGB pixels
+    /// row by row.
+    ///
+    /// The result must have exactly `width * height` pixels, or resizing
+    /// will fail with [`Error::DimensionMismatch`](crate::Error::DimensionMismatch).
+    fn resample(&self, frame: Frame<'_>, width: u32, height: u32) -> Vec<u32>;
+}
+
+/// Nearest-neighbour scaling, which keeps pixel art crisp. This is the
+/// default [`Resampler`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NearestNeighbor;
+
+impl Resampler for NearestNeighbor {
+    fn resample(&self, frame: Frame<'_>, width: u32, height: u32) -> Vec<u32> {
+        let mut output = pool::take(width as usize * height as usize);
+
+        if frame.width == 0 || frame.height == 0 || width == 0 || height == 0 {
+            return output;
+        }
+
+        let src_width = frame.width as usize;
+
+        if width % frame.width == 0 && height % frame.height == 0 {
+            // Integer scales can just duplicate pixels and rows
+            let x_scale = (width / frame.width) as usize;
+            let y_scale = (height / frame.height) as usize;
+
+            for row in frame.pixels.chunks_exact(src_width) {
+                let row_start = output.len();
+
+                for &pixel in row {
+                    output.extend(iter::repeat_n(pixel, x_scale));
+                }
+
+                for _ in 1..y_scale {
+                    output.extend_from_within(row_start..row_start + width as usize);
+                }
+            }
+        } else {
+            for y in 0..u64::from(height) {
+                let src_y = (y * u64::from(frame.height) / u64::from(height)) as usize;
+                let row = &frame.pixels[src_y * src_width..(src_y + 1) * src_width];
+
+                output.extend(
+                    (0..u64::from(width))
+                        .map(|x| row[(x * u64::from(frame.width) / u64::from(width)) as usize]),
+                );
+            }
+        }
+
+        output
+    }
+}
@@ -0,0 +1,184 @@
+//! Facilities for resizing Xcursors.
+
+use crate::{
+    pool,
+    resample::{Frame, NearestNeighbor, Resampler},
+    transform::{transform_images, transform_images_to, Transform},
+    Chunk, Error, Image, ImageLayout, Result, XcursorBuilder, XcursorReader,
+};
+use std::{io::Write, sync::Arc};
+
+/// Options controlling how a cursor is resized.
+///
+/// These can also be used as a [`Transform`] in a
+/// [`Pipeline`](crate::transform::Pipeline).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ResizeOptions {
+    /// The scale factor to apply to each image.
+    ///
+    /// For example, a scale of 2 applied to a 32x32 pixel image will
+    /// result in a 64x64 pixel image.
+    pub scale: u32,
+    /// The algorithm used to scale each image's pixels.
+    /// Defaults to [`NearestNeighbor`].
+    pub resampler: Arc<dyn Resampler>,
+    /// The largest nominal size to scale images to, if any. See
+    /// [`ResizeOptions::clamp_size`].
+    pub clamp_size: Option<u32>,
+}
+
+impl ResizeOptions {
+    /// Creates options for resizing a cursor by the given scale factor.
+    pub fn new(scale: u32) -> Self {
+        Self {
+            scale,
+            resampler: Arc::new(NearestNeighbor),
+            clamp_size: None,
+        }
+    }
+
+    /// Sets the algorithm used to scale each image's pixels.
+    pub fn resampler(&mut self, resampler: impl Resampler + 'static) -> &mut Self {
+        self.resampler = Arc::new(resampler);
+        self
+    }
+
+    /// Limits the nominal size that images are scaled to. Images that would
+    /// be larger are scaled by the largest factor that keeps them within
+    /// `size` instead, but are never scaled by less than 1.
+    ///
+    /// ```
+    /// use resize_xcursor::ResizeOptions;
+    ///
+    /// let mut options = ResizeOptions::new(4);
+    /// options.clamp_size(100);
+    /// assert_eq!(options.scale_for(24), 4);
+    /// assert_eq!(options.scale_for(32), 3);
+    /// assert_eq!(options.scale_for(128), 1);
+    /// ```
+    pub fn clamp_size(&mut self, size: u32) -> &mut Self {
+        self.clamp_size = Some(size);
+        self
+    }
+
+    /// Returns the factor that images with the given nominal size are
+    /// scaled by, which is less than `scale` if they would be larger than
+    /// [`ResizeOptions::clamp_size`].
+    pub fn scale_for(&self, size: u32) -> u32 {
+        match self.clamp_size {
+            Some(max)
+                if size
+                    .checked_mul(self.scale)
+                    .is_none_or(|scaled| scaled > max) =>
+            {
+                (max / size).clamp(1, self.scale.max(1))
+            }
+            _ => self.scale,
+        }
+    }
+}
+
+impl Transform for ResizeOptions {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let scale = self.scale_for(image.size());
+        let layout = scaled_layout(image.layout(), scale)?;
+        let xhot = scale_field(image.xhot(), "hotspot x coordinate", scale)?;
+        let yhot = scale_field(image.yhot(), "hotspot y coordinate", scale)?;
+
+        let frame = Frame {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.pixels(),
+        };
+
+        // `scaled_layout` checked the dimensions, so the resampler can't be asked
+        // for an image that's too large
+        let scaled_pixels = self.resampler.resample(frame, layout.width, layout.height);
+        let delay = image.delay();
+        pool::give(image.into_pixels());
+
+        Image::new(
+            layout.size,
+            layout.width,
+            layout.height,
+            xhot,
+            yhot,
+            delay,
+            scaled_pixels,
+        )
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        scaled_layout(layout, self.scale_for(layout.size)).map(Some)
+    }
+}
+
+/// Parses an Xcursor file and resizes every image in it. The file header is
+/// kept, and other chunks, such as comments, are kept as they are, in their
+/// original places among the images.
+pub fn resize(cursor_bytes: &[u8], options: &ResizeOptions) -> Result<XcursorBuilder> {
+    let reader = XcursorReader::new(cursor_bytes)?;
+    let cursor_chunks = reader.chunks().collect::<Result<Vec<_>>>()?;
+
+    let mut cursor = resize_images(cursor_chunks, options)?;
+    cursor.file_header(reader.header())?;
+    Ok(cursor)
+}
+
+/// Resizes every image in a parsed Xcursor. Other chunks, such as comments,
+/// are kept as they are, in their original places among the images. The
+/// file header isn't part of `cursor_chunks`, so the result has the default
+/// one unless [`XcursorBuilder::file_header`] is called on it.
+///
+/// With the `parallel` feature, images are resized in parallel,
+/// but are still added to the output in their original order.
+pub fn resize_images<'a>(
+    cursor_chunks: impl IntoIterator<Item = impl Into<Chunk<'a>>>,
+    options: &ResizeOptions,
+) -> Result<XcursorBuilder> {
+    transform_images(cursor_chunks, options)
+}
+
+/// Resizes every image in a parsed Xcursor, writing each one to `writer`
+/// as soon as it's ready. Returns `writer` once every image has been written.
+///
+/// Unlike [`resize_images`], this never holds every resized image in memory
+/// at once. With the `parallel` feature, a batch of images is resized in
+/// parallel, then written before the next batch is started.
+pub fn resize_images_to<'a, W: Write>(
+    cursor_chunks: impl IntoIterator<Item = impl Into<Chunk<'a>>>,
+    options: &ResizeOptions,
+    writer: W,
+) -> Result<W> {
+    transform_images_to(cursor_chunks, options, writer)
+}
+
+/// Scales a field of an image, making sure that it doesn't overflow.
+fn scale_field(value: u32, field: &'static str, scale: u32) -> Result<u32> {
+    value.checked_mul(scale).ok_or(Error::Overflow {
+        field,
+        value,
+        scale,
+    })
+}
+
+/// Returns the nominal size and dimensions of an image once it's been
+/// resized.
+fn scaled_layout(layout: ImageLayout, scale: u32) -> Result<ImageLayout> {
+    let size = scale_field(layout.size, "nominal size", scale)?;
+    let width = scale_field(layout.width, "width", scale)?;
+    let height = scale_field(layout.height, "height", scale)?;
+
+    // `Image::new` would reject this too, but only after the pixels
+    // were allocated
+    if width > Image::MAX_DIMENSION || height > Image::MAX_DIMENSION {
+        return Err(Error::ImageTooLarge { width, height });
+    }
+
+    Ok(ImageLayout {
+        size,
+        width,
+        height,
+    })
+}
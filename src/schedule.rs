@@ -0,0 +1,301 @@
+//! Resizing several files in parallel.
+//!
+//! Files are started largest first, but are reported in the order they were
+//! given, so that logs and outputs written to stdout are the same from one
+//! run to the next. The only exception is files written after one given
+//! before them failed, which are reported as soon as they finish.
+
+use crate::{
+    input, interrupt, report_file, resize_file, timings::Stages, FileResult, ParseOptions,
+};
+use anyhow::{bail, Context};
+use resize_xcursor::Pipeline;
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+};
+use tracing::{error, info, info_span};
+
+/// A file that's been processed, but might not have been reported yet.
+struct Finished {
+    input_filename: PathBuf,
+    output_filename: PathBuf,
+    res: anyhow::Result<FileResult>,
+}
+
+/// Files waiting to be reported.
+#[derive(Default)]
+struct Reports {
+    /// The index of the next file to report.
+    next: usize,
+    /// Files that finished before some file given before them.
+    pending: BTreeMap<usize, Finished>,
+    /// The number of files that have been reported successfully.
+    processed: usize,
+    /// The error from the first file (in input order) that failed.
+    error: Option<anyhow::Error>,
+    /// Whether files that fail are only logged, for `--keep-going`.
+    keep_going: bool,
+    /// The number of files that failed with `--keep-going`.
+    failures: usize,
+    /// The number of files given after the first one that failed, but that
+    /// were written anyway, since they were started before it failed.
+    written_after_failure: usize,
+}
+
+impl Reports {
+    /// Adds the file at `index`, then reports every file that's ready,
+    /// stopping at the first error.
+    ///
+    /// Once a file has failed, the files after it are dropped instead,
+    /// except for the ones that were already written in place, since those
+    /// have been changed even though they'd never have been started when
+    /// resizing one file at a time.
+    fn finish(&mut self, index: usize, finished: Finished) {
+        self.pending.insert(index, finished);
+
+        while self.error.is_none() {
+            let Some(finished) = self.pending.remove(&self.next) else {
+                break;
+            };
+
+            self.next += 1;
+
+            let _span = info_span!("file", path = %finished.input_filename.display()).entered();
+
+            match report_file(&finished.output_filename, finished.res) {
+                Ok(()) => self.processed += 1,
+                Err(err) if self.keep_going => {
+                    error!("{err:#}");
+                    self.failures += 1;
+                }
+                Err(err) => self.error = Some(err),
+            }
+        }
+
+        if self.error.is_some() {
+            for finished in std::mem::take(&mut self.pending).into_values() {
+                // Outputs to stdout are only written once they're reported
+                if let Ok(FileResult::Resized(None)) = finished.res {
+                    let _span =
+                        info_span!("file", path = %finished.input_filename.display()).entered();
+
+                    info!(
+                        output = %finished.output_filename.display(),
+                        "resized before an earlier file failed"
+                    );
+                    self.written_after_failure += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Resizes every file in parallel, starting with the largest.
+///
+/// Files are weighed by their size, so that big animated cursors are started
+/// first, with their images spread across every thread, while small cursors
+/// fill in around them. Otherwise, a big cursor that happens to come last
+/// would leave every other thread idle while it's resized.
+///
+/// If a file fails, files given after it aren't started. Files aren't started
+/// in the order they were given, though, so some of them might already have
+/// been written by then, unlike when resizing one file at a time. Those are
+/// still reported, and the error says how many there were. Outputs to stdout
+/// are only written in order, so they never are. With `keep_going`, failures
+/// are logged instead, and every file is resized.
+pub(crate) fn resize_all(
+    files: Vec<(PathBuf, PathBuf)>,
+    io_backend: input::IoBackend,
+    pipeline: &Pipeline,
+    parse_options: ParseOptions<'_>,
+    keep_going: bool,
+) -> anyhow::Result<()> {
+    let mut files = files.into_iter().enumerate().collect::<Vec<_>>();
+
+    // Files that can't be read are reported once they're processed
+    files.sort_by_cached_key(|(_, (input_filename, _))| {
+        Reverse(fs::metadata(input_filename).map_or(0, |metadata| metadata.len()))
+    });
+
+    let file_count = files.len();
+    let input_paths = files
+        .iter()
+        .map(|(_, (input_filename, _))| input_filename.clone())
+        .collect();
+    let inputs = input::read_all(input_paths, io_backend);
+
+    // Each task takes the next file from the queue, rather than being given
+    // one, so that files are started largest first even if tasks aren't
+    let queue = Mutex::new(files.into_iter().zip(inputs));
+    let first_failure = AtomicUsize::new(usize::MAX);
+    let reports = Mutex::new(Reports {
+        keep_going,
+        ..Reports::default()
+    });
+
+    rayon::scope_fifo(|scope| {
+        for _ in 0..file_count {
+            scope.spawn_fifo(|_| {
+                if interrupt::interrupted() {
+                    return;
+                }
+
+                // Files are only read once the lock is released (unless they
+                // were read ahead), so that tasks don't wait on each other
+                let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+
+                let Some(((index, (input_filename, output_filename)), input)) = next else {
+                    return;
+                };
+
+                // Files after one that failed wouldn't have been started when
+                // resizing one file at a time
+                if index > first_failure.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                let res = {
+                    let _span = info_span!("file", path = %input_filename.display()).entered();
+
+                    input
+                        .read()
+                        .with_context(|| format!("couldn't read {}", input_filename.display()))
+                        .and_then(|cursor_bytes| {
+                            resize_file(
+                                &input_filename,
+                                &output_filename,
+                                &cursor_bytes,
+                                pipeline,
+                                parse_options,
+                                &mut Stages::default(),
+                            )
+                        })
+                };
+
+                if res.is_err() && !keep_going {
+                    first_failure.fetch_min(index, Ordering::Relaxed);
+                }
+
+                let finished = Finished {
+                    input_filename,
+                    output_filename,
+                    res,
+                };
+
+                reports
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .finish(index, finished);
+            });
+        }
+    });
+
+    let reports = reports.into_inner().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(err) = reports.error {
+        return Err(match reports.written_after_failure {
+            0 => err,
+            1 => err.context("a file given after the one that failed was already resized"),
+            count => err.context(format!(
+                "{count} files given after the one that failed were already resized"
+            )),
+        });
+    }
+
+    let processed = reports.processed + reports.failures;
+
+    if processed < file_count {
+        // Files are only skipped after an error or Ctrl-C
+        bail!("interrupted after processing {processed} of {file_count} files");
+    }
+
+    if reports.failures > 0 {
+        bail!("couldn't resize {} of {file_count} files", reports.failures);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn resized() -> anyhow::Result<FileResult> {
+        Ok(FileResult::Resized(None))
+    }
+
+    fn finished(index: usize, res: anyhow::Result<FileResult>) -> Finished {
+        Finished {
+            input_filename: PathBuf::from(format!("{index}.in")),
+            output_filename: PathBuf::from(format!("{index}.out")),
+            res,
+        }
+    }
+
+    #[test]
+    fn reports_in_input_order() {
+        let mut reports = Reports::default();
+
+        for index in [2, 0, 3, 1] {
+            reports.finish(index, finished(index, resized()));
+        }
+
+        assert_eq!(reports.next, 4);
+        assert_eq!(reports.processed, 4);
+        assert!(reports.pending.is_empty());
+        assert!(reports.error.is_none());
+    }
+
+    #[test]
+    fn counts_files_written_after_failure() {
+        let mut reports = Reports::default();
+
+        reports.finish(2, finished(2, resized()));
+        reports.finish(3, finished(3, Ok(FileResult::Skipped)));
+        reports.finish(1, finished(1, Err(anyhow!("broken"))));
+        // Nothing is reported until the first file finishes
+        assert_eq!(reports.pending.len(), 3);
+        assert!(reports.error.is_none());
+
+        reports.finish(0, finished(0, resized()));
+        assert_eq!(reports.processed, 1);
+        assert_eq!(reports.error.as_ref().unwrap().to_string(), "broken");
+        // Skipped files weren't written, so they aren't counted
+        assert_eq!(reports.written_after_failure, 1);
+        assert!(reports.pending.is_empty());
+
+        // Files that were started before the failure can still finish
+        // after it
+        reports.finish(4, finished(4, resized()));
+        reports.finish(5, finished(5, Err(anyhow!("also broken"))));
+        assert_eq!(reports.processed, 1);
+        assert_eq!(reports.written_after_failure, 2);
+        assert_eq!(reports.error.unwrap().to_string(), "broken");
+        assert!(reports.pending.is_empty());
+    }
+
+    #[test]
+    fn keep_going_reports_every_file() {
+        let mut reports = Reports {
+            keep_going: true,
+            ..Reports::default()
+        };
+
+        reports.finish(1, finished(1, resized()));
+        reports.finish(0, finished(0, Err(anyhow!("broken"))));
+        reports.finish(2, finished(2, resized()));
+
+        assert_eq!(reports.processed, 2);
+        assert_eq!(reports.failures, 1);
+        assert_eq!(reports.written_after_failure, 0);
+        assert!(reports.error.is_none());
+    }
+}
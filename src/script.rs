@@ -0,0 +1,110 @@
+//! Custom transforms written in [Rhai](https://rhai.rs), enabled by the
+//! `scripting` feature.
+
+use crate::{Error, Image, Result, Transform};
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use std::fmt;
+
+/// The function that scripts must define.
+const ENTRY_POINT: &str = "transform";
+
+/// A [`Transform`] that runs a Rhai script on each image.
+///
+/// The script must define a `transform` function, which is given a map with
+/// the image's `size`, `width`, `height`, `xhot`, `yhot`, and `delay`, as well
+/// as an array of its ARGB `pixels`, row by row. It should return a map with
+/// the same fields, which is used as the new image:
+///
+/// ```rhai
+/// // Makes every pixel fully opaque
+/// fn transform(frame) {
+///     frame.pixels = frame.pixels.map(|pixel| pixel | 0xFF000000);
+///     frame
+/// }
+/// ```
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+impl Script {
+    /// Compiles a script.
+    ///
+    /// This fails if the script has a syntax error.
+    pub fn new(source: &str) -> Result<Self> {
+        let engine = Engine::new();
+        let ast = engine
+            .compile(source)
+            .map_err(|err| Error::Script(err.to_string()))?;
+
+        Ok(Self { engine, ast })
+    }
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Script").finish_non_exhaustive()
+    }
+}
+
+impl Transform for Script {
+    fn apply(&self, image: Image) -> Result<Image> {
+        let mut frame = Map::new();
+        frame.insert("size".into(), i64::from(image.size()).into());
+        frame.insert("width".into(), i64::from(image.width()).into());
+        frame.insert("height".into(), i64::from(image.height()).into());
+        frame.insert("xhot".into(), i64::from(image.xhot()).into());
+        frame.insert("yhot".into(), i64::from(image.yhot()).into());
+        frame.insert("delay".into(), i64::from(image.delay()).into());
+
+        let pixels = image
+            .into_pixels()
+            .into_iter()
+            .map(|pixel| Dynamic::from(i64::from(pixel)))
+            .collect::<Array>();
+        frame.insert("pixels".into(), pixels.into());
+
+        let frame = self
+            .engine
+            .call_fn::<Map>(&mut Scope::new(), &self.ast, ENTRY_POINT, (frame,))
+            .map_err(|err| Error::Script(err.to_string()))?;
+
+        let pixels = frame
+            .get("pixels")
+            .and_then(|pixels| pixels.read_lock::<Array>())
+            .ok_or_else(|| Error::Script("`pixels` must be an array".to_owned()))?
+            .iter()
+            .map(|pixel| to_u32(pixel, "each of `pixels`"))
+            .collect::<Result<Vec<_>>>()?;
+
+        let field = |name| match frame.get(name) {
+            Some(value) => to_u32(value, &format!("`{name}`")),
+            None => Err(Error::Script(format!("`{name}` is missing"))),
+        };
+
+        Image::new(
+            field("size")?,
+            field("width")?,
+            field("height")?,
+            field("xhot")?,
+            field("yhot")?,
+            field("delay")?,
+            pixels,
+        )
+    }
+}
+
+/// Converts a value returned by a script, where `what` describes the value
+/// for error messages.
+fn to_u32(value: &Dynamic, what: &str) -> Result<u32> {
+    value
+        .as_int()
+        .ok()
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or_else(|| {
+            Error::Script(format!(
+                "{what} must be an integer between 0 and {}",
+                u32::MAX
+            ))
+        })
+}
@@ -0,0 +1,117 @@
+//! Destinations for written cursors, such as files or memory.
+//!
+//! Code that writes cursors can take an [`OutputSink`] instead of opening
+//! files itself, so that the same code can write to disk, to memory (for
+//! WebAssembly builds), or anywhere else.
+
+use crate::Result;
+use std::{
+    io::{self, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+/// Somewhere that written cursors can go.
+///
+/// Sinks are shared between threads, so that several files can be written
+/// at once.
+pub trait OutputSink: Sync {
+    /// Starts writing the output called `name`.
+    ///
+    /// The output should only appear once [`SinkWriter::commit`] is called.
+    /// If the writer is dropped without being committed, the output should
+    /// be thrown away.
+    fn create(&self, name: &Path) -> Result<Box<dyn SinkWriter + '_>>;
+}
+
+/// An output that's being written to an [`OutputSink`].
+///
+/// Writers can be sent between threads, so that an output can be committed
+/// by a different thread than the one that wrote it.
+pub trait SinkWriter: Write + Send {
+    /// Finishes writing this output, making it appear in its sink.
+    fn commit(self: Box<Self>) -> Result<()>;
+}
+
+/// An [`OutputSink`] that keeps every output in memory.
+///
+/// ```
+/// use resize_xcursor::{Image, MemorySink, XcursorBuilder};
+/// use std::path::Path;
+///
+/// let mut cursor = XcursorBuilder::new();
+/// cursor.add_image(Image::new(1, 1, 1, 0, 0, 0, vec![0xFF000000])?)?;
+///
+/// let sink = MemorySink::new();
+/// cursor.write_to_sink(&sink, Path::new("tiny-cursor"))?;
+///
+/// let outputs = sink.into_outputs();
+/// assert_eq!(outputs[0].0, Path::new("tiny-cursor"));
+/// assert_eq!(outputs[0].1.len(), cursor.byte_length() as usize);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    outputs: Mutex<Vec<(PathBuf, Vec<u8>)>>,
+}
+
+impl MemorySink {
+    /// Creates a sink with no outputs in it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes every output that's been committed so far, returning their
+    /// names and contents in the order they were committed.
+    pub fn take_outputs(&self) -> Vec<(PathBuf, Vec<u8>)> {
+        mem::take(&mut *self.outputs())
+    }
+
+    /// Consumes this sink, returning the name and contents of every output
+    /// in the order they were committed.
+    pub fn into_outputs(self) -> Vec<(PathBuf, Vec<u8>)> {
+        self.outputs
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn outputs(&self) -> MutexGuard<'_, Vec<(PathBuf, Vec<u8>)>> {
+        // Outputs are only ever pushed, so the list is still fine to use if
+        // another thread panicked
+        self.outputs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn create(&self, name: &Path) -> Result<Box<dyn SinkWriter + '_>> {
+        Ok(Box::new(MemoryWriter {
+            sink: self,
+            name: name.to_owned(),
+            bytes: Vec::new(),
+        }))
+    }
+}
+
+struct MemoryWriter<'a> {
+    sink: &'a MemorySink,
+    name: PathBuf,
+    bytes: Vec<u8>,
+}
+
+impl Write for MemoryWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SinkWriter for MemoryWriter<'_> {
+    fn commit(self: Box<Self>) -> Result<()> {
+        self.sink.outputs().push((self.name, self.bytes));
+        Ok(())
+    }
+}
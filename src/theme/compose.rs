@@ -0,0 +1,193 @@
+//! Implementation of `theme compose`.
+
+use super::{check_cursor_name, cursors_dir, home_dir, icon_dirs, remove_if_exists, theme_files};
+use crate::{input, output};
+use anyhow::{anyhow, bail, Context};
+use clap::Args;
+use resize_xcursor::{resize, Error, ResizeOptions};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, iter,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+};
+
+#[derive(Args)]
+pub(crate) struct ComposeArgs {
+    /// The TOML file describing the theme to build.
+    #[clap(value_parser)]
+    spec: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    output: PathBuf,
+    name: Option<String>,
+    #[serde(default = "default_scale")]
+    scale: u32,
+    sources: BTreeMap<String, String>,
+    #[serde(default, rename = "cursor")]
+    cursors: Vec<CursorSpec>,
+}
+
+fn default_scale() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CursorSpec {
+    source: String,
+    name: String,
+    rename: Option<String>,
+    scale: Option<u32>,
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+impl CursorSpec {
+    /// The name of this cursor in the output theme.
+    fn output_name(&self) -> &String {
+        self.rename.as_ref().unwrap_or(&self.name)
+    }
+}
+
+pub(crate) fn run(args: ComposeArgs) -> anyhow::Result<()> {
+    let spec_text = fs::read_to_string(&args.spec)
+        .with_context(|| format!("couldn't read {}", args.spec.display()))?;
+    let spec: Spec = toml::from_str(&spec_text)
+        .with_context(|| format!("{} isn't a valid theme spec", args.spec.display()))?;
+
+    let spec_dir = args.spec.parent().unwrap_or(Path::new(""));
+
+    let sources = spec
+        .sources
+        .iter()
+        .map(|(key, source)| Ok((key.as_str(), find_source(spec_dir, source)?)))
+        .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+    // Check the whole spec before writing anything
+    let mut output_names = BTreeSet::new();
+
+    for cursor in &spec.cursors {
+        if !sources.contains_key(cursor.source.as_str()) {
+            bail!(
+                "cursor `{}` uses the source `{}`, which isn't listed in `[sources]`",
+                cursor.name,
+                cursor.source
+            );
+        }
+
+        check_cursor_name(&cursor.name)?;
+
+        for name in cursor.rename.iter().chain(&cursor.aliases) {
+            check_cursor_name(name)?;
+        }
+
+        for name in iter::once(cursor.output_name()).chain(&cursor.aliases) {
+            if !output_names.insert(name.as_str()) {
+                bail!("the output theme would contain `{}` more than once", name);
+            }
+        }
+    }
+
+    let output = spec_dir.join(&spec.output);
+    let output_cursors_dir = output.join("cursors");
+    fs::create_dir_all(&output_cursors_dir)
+        .with_context(|| format!("couldn't create {}", output_cursors_dir.display()))?;
+
+    for cursor in &spec.cursors {
+        let source_path = sources[cursor.source.as_str()].join(&cursor.name);
+        let output_name = cursor.output_name();
+        let output_path = output_cursors_dir.join(output_name);
+        let scale = cursor.scale.unwrap_or(spec.scale);
+
+        // Anything left over from a previous run (possibly a symlink) is replaced
+        remove_if_exists(&output_path)?;
+
+        if scale == 1 {
+            output::copy(&source_path, &output_path).with_context(|| {
+                format!(
+                    "couldn't copy {} to {}",
+                    source_path.display(),
+                    output_path.display()
+                )
+            })?;
+        } else {
+            let cursor_bytes = input::read(&source_path)
+                .with_context(|| format!("couldn't read {}", source_path.display()))?;
+
+            let cursor =
+                resize(&cursor_bytes, &ResizeOptions::new(scale)).map_err(|err| match err {
+                    Error::Parse { .. } => anyhow!(
+                        "{} isn't a valid Xcursor file: {err}",
+                        source_path.display()
+                    ),
+                    err => err.into(),
+                })?;
+
+            output::write(&output_path, |output| cursor.write_to(output))
+                .with_context(|| format!("couldn't write {}", output_path.display()))?;
+        }
+
+        for alias in &cursor.aliases {
+            let alias_path = output_cursors_dir.join(alias);
+            remove_if_exists(&alias_path)?;
+            symlink(output_name, &alias_path)
+                .with_context(|| format!("couldn't create {}", alias_path.display()))?;
+        }
+    }
+
+    // Cursors left over from running this with a different spec would
+    // otherwise stay in the theme. Hidden files (such as temporary files
+    // that are still being written) are left alone, like in `mirror-sync`.
+    for file in theme_files(&output_cursors_dir)? {
+        if !output_names.contains(file.name.as_str()) && !file.name.starts_with('.') {
+            fs::remove_file(&file.path)
+                .with_context(|| format!("couldn't remove {}", file.path.display()))?;
+            println!("removed {}", file.name);
+        }
+    }
+
+    let name = match spec.name {
+        Some(name) => name,
+        None => output
+            .canonicalize()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+
+    let index_theme = output.join("index.theme");
+    output::write_bytes(&index_theme, format!("[Icon Theme]\nName={}\n", name))
+        .with_context(|| format!("couldn't write {}", index_theme.display()))?;
+
+    Ok(())
+}
+
+/// Finds the `cursors` directory for a source theme, which is either given
+/// by path or by the name of an installed theme.
+fn find_source(spec_dir: &Path, source: &str) -> anyhow::Result<PathBuf> {
+    let path = match source.strip_prefix("~/") {
+        Some(rest) => home_dir()?.join(rest),
+        None => spec_dir.join(source),
+    };
+
+    if path.is_dir() {
+        return Ok(cursors_dir(&path));
+    }
+
+    if !source.contains('/') {
+        for dir in icon_dirs()? {
+            let cursors = dir.join(source).join("cursors");
+
+            if cursors.is_dir() {
+                return Ok(cursors);
+            }
+        }
+    }
+
+    bail!("couldn't find a theme at `{}`", source);
+}
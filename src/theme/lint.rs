@@ -0,0 +1,159 @@
+//! Implementation of `theme lint`.
+
+use super::{theme_files, ThemeDir};
+use crate::{
+    input,
+    lint::{self, Finding, Report, ReportFormat, Severity},
+};
+use anyhow::{bail, Context};
+use clap::Args;
+use resize_xcursor::XcursorReader;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+};
+
+#[derive(Args)]
+pub(crate) struct LintArgs {
+    #[clap(flatten)]
+    theme: ThemeDir,
+
+    /// Also checks that each cursor is written back out byte for byte after
+    /// it's read, like `validate --round-trip`.
+    #[clap(long, verbatim_doc_comment)]
+    round_trip: bool,
+
+    /// How to print what's found. `json` prints a single report with a
+    /// stable rule id for each problem, for tools such as CI checks.
+    #[clap(long, value_enum, default_value = "text", verbatim_doc_comment)]
+    format: ReportFormat,
+}
+
+/// A problem with a theme as a whole, rather than with a single cursor.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ThemeProblem {
+    /// A symlink to a cursor that doesn't exist.
+    BrokenAlias { target: String },
+    /// A nominal size that most other cursors have, but this one doesn't.
+    MissingSize { size: u32 },
+}
+
+pub(crate) fn run(args: LintArgs) -> anyhow::Result<()> {
+    let dir = args.theme.cursors_dir();
+    let files = theme_files(&dir)?;
+    // Each file's findings, so that the ones about the whole theme can be
+    // printed along with the rest
+    let mut findings = files.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    // The index and nominal sizes of each cursor that could be read
+    let mut cursor_sizes = Vec::new();
+
+    for (index, file) in files.iter().enumerate() {
+        if file.is_alias {
+            // Aliases are the same file as the cursor they point to, so
+            // they're only checked for pointing to one that exists
+            if fs::metadata(&file.path).is_err() {
+                let target = fs::read_link(&file.path)
+                    .with_context(|| format!("couldn't read {}", file.path.display()))?;
+
+                findings[index].push(Finding::with_message(
+                    "theme",
+                    Severity::Error,
+                    &file.path,
+                    &file.name,
+                    format!("points to {}, which doesn't exist", target.display()),
+                    &ThemeProblem::BrokenAlias {
+                        target: target.display().to_string(),
+                    },
+                ));
+            }
+
+            continue;
+        }
+
+        let bytes = input::read(&file.path)
+            .with_context(|| format!("couldn't read {}", file.path.display()))?;
+        findings[index].extend(lint::check_cursor(
+            &file.path,
+            &file.name,
+            &bytes,
+            args.round_trip,
+        ));
+
+        // Only image headers are needed, so pixels don't have to be read
+        let images = XcursorReader::new(&bytes)
+            .and_then(|reader| reader.image_infos().collect::<Result<Vec<_>, _>>());
+
+        if let Ok(images) = images {
+            let sizes = images
+                .iter()
+                .map(|image| image.size())
+                .collect::<BTreeSet<_>>();
+            cursor_sizes.push((index, sizes));
+        }
+    }
+
+    // Sizes that only some cursors have are probably missing from the rest,
+    // so that those cursors change size as the pointer moves between them
+    let mut size_counts = BTreeMap::<u32, usize>::new();
+
+    for (_, sizes) in &cursor_sizes {
+        for &size in sizes {
+            *size_counts.entry(size).or_default() += 1;
+        }
+    }
+
+    for (index, sizes) in &cursor_sizes {
+        let file = &files[*index];
+
+        for (&size, &count) in &size_counts {
+            if count * 2 > cursor_sizes.len() && !sizes.contains(&size) {
+                findings[*index].push(Finding::with_message(
+                    "theme",
+                    Severity::Warning,
+                    &file.path,
+                    &file.name,
+                    format!(
+                        "doesn't have the {size}px images that {count} of the theme's {} cursors have",
+                        cursor_sizes.len()
+                    ),
+                    &ThemeProblem::MissingSize { size },
+                ));
+            }
+        }
+    }
+
+    let findings = findings.into_iter().flatten().collect::<Vec<_>>();
+
+    if args.format == ReportFormat::Text {
+        findings.iter().for_each(Finding::print);
+    }
+
+    let report = Report::new(files.len(), findings);
+
+    if args.format == ReportFormat::Json {
+        report.print_json();
+    }
+
+    let failures = report.failed_files();
+
+    if failures > 0 {
+        bail!(
+            "{} of {} files in {} have errors",
+            failures,
+            files.len(),
+            dir.display()
+        );
+    }
+
+    if args.format == ReportFormat::Text {
+        println!(
+            "no errors in the {} files in {}",
+            files.len(),
+            dir.display()
+        );
+    }
+
+    Ok(())
+}
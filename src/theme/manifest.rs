@@ -0,0 +1,129 @@
+//! Implementation of `theme manifest` and `theme verify`.
+//!
+//! Manifests use the same format as `sha256sum`, so they can also be
+//! checked with `sha256sum -c` from inside the `cursors` directory.
+
+use super::{theme_files, ThemeDir};
+use crate::output;
+use anyhow::{bail, Context};
+use clap::Args;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeSet,
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+#[derive(Args)]
+pub(crate) struct ManifestArgs {
+    #[clap(flatten)]
+    theme: ThemeDir,
+
+    /// The file to write the manifest to. Defaults to stdout.
+    #[clap(short, long, value_parser)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub(crate) struct VerifyArgs {
+    /// A manifest created by `theme manifest`.
+    #[clap(value_parser)]
+    manifest: PathBuf,
+
+    #[clap(flatten)]
+    theme: ThemeDir,
+}
+
+pub(crate) fn run_manifest(args: ManifestArgs) -> anyhow::Result<()> {
+    let dir = args.theme.cursors_dir();
+    let mut manifest = String::new();
+
+    for file in theme_files(&dir)? {
+        if file.name.contains('\n') {
+            bail!("{} can't be written to a manifest", file.path.display());
+        }
+
+        // Aliases are hashed too (by following the symlink),
+        // so that a broken or retargeted alias is noticed
+        writeln!(manifest, "{}  {}", hash_file(&file.path)?, file.name)?;
+    }
+
+    match args.output {
+        Some(path) => output::write_bytes(&path, manifest)
+            .with_context(|| format!("couldn't write {}", path.display()))?,
+        None => io::stdout().lock().write_all(manifest.as_bytes())?,
+    }
+
+    Ok(())
+}
+
+pub(crate) fn run_verify(args: VerifyArgs) -> anyhow::Result<()> {
+    let dir = args.theme.cursors_dir();
+    let manifest = fs::read_to_string(&args.manifest)
+        .with_context(|| format!("couldn't read {}", args.manifest.display()))?;
+
+    let mut listed = BTreeSet::new();
+    let mut failures = 0;
+
+    for (line_number, line) in manifest.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // `sha256sum` uses `*` before the name to indicate binary mode
+        let Some((expected, name)) = line.split_once("  ").or_else(|| line.split_once(" *")) else {
+            bail!(
+                "{}:{}: expected a hash and a file name",
+                args.manifest.display(),
+                line_number + 1
+            );
+        };
+
+        listed.insert(name.to_owned());
+
+        let path = dir.join(name);
+        let status = match hash_file(&path) {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected) => continue,
+            Ok(_) => "FAILED",
+            Err(_) => "MISSING",
+        };
+
+        println!("{}: {}", name, status);
+        failures += 1;
+    }
+
+    let extra = theme_files(&dir)?
+        .into_iter()
+        .filter(|file| !listed.contains(&file.name))
+        .collect::<Vec<_>>();
+
+    // Extra files don't cause verification to fail, since they can't
+    // affect any of the cursors in the manifest
+    for file in &extra {
+        warn!("{} isn't listed in the manifest", file.name);
+    }
+
+    if failures > 0 {
+        bail!(
+            "{} of {} files didn't match the manifest",
+            failures,
+            listed.len()
+        );
+    }
+
+    println!("all {} files match the manifest", listed.len());
+    Ok(())
+}
+
+/// Returns the SHA-256 hash of the given file as a lowercase hex string.
+fn hash_file(path: &Path) -> anyhow::Result<String> {
+    Ok(Sha256::digest(fs::read(path)?)
+        .iter()
+        .fold(String::with_capacity(64), |mut res, byte| {
+            let _ = write!(res, "{:02x}", byte);
+            res
+        }))
+}
@@ -0,0 +1,245 @@
+//! Implementation of `theme mirror-sync`.
+
+use super::{cursors_dir, remove_if_exists, theme_files};
+use crate::{input, interrupt, output};
+use anyhow::{bail, Context};
+use clap::Args;
+use resize_xcursor::{resize, Error, ResizeOptions};
+use std::{
+    collections::BTreeSet,
+    fs,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+/// The longest that `--watch` sleeps for before checking for Ctrl-C.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Args)]
+pub(crate) struct MirrorSyncArgs {
+    /// The theme to resize.
+    #[clap(value_parser)]
+    source: PathBuf,
+
+    /// The directory to keep the resized theme in.
+    #[clap(value_parser)]
+    destination: PathBuf,
+
+    /// The scale factor to apply to each cursor.
+    #[clap(short, long)]
+    scale: u32,
+
+    /// If given, keeps watching the source theme for changes
+    /// instead of exiting after syncing once.
+    #[clap(short, long, verbatim_doc_comment)]
+    watch: bool,
+
+    /// How often to check for changes when using `--watch`, in milliseconds.
+    #[clap(long, default_value_t = 1000)]
+    interval: u64,
+}
+
+pub(crate) fn run(args: MirrorSyncArgs) -> anyhow::Result<()> {
+    let source_dir = cursors_dir(&args.source);
+
+    // Mirror the source theme's layout
+    let destination_dir = if source_dir == args.source {
+        args.destination.clone()
+    } else {
+        args.destination.join("cursors")
+    };
+
+    if fs::canonicalize(&source_dir).ok() == fs::canonicalize(&destination_dir).ok() {
+        bail!("the destination can't be the same as the source");
+    }
+
+    fs::create_dir_all(&destination_dir)
+        .with_context(|| format!("couldn't create {}", destination_dir.display()))?;
+
+    interrupt::stop_between_files();
+
+    loop {
+        sync(&args, &source_dir, &destination_dir)?;
+
+        if !args.watch {
+            return Ok(());
+        }
+
+        // Ctrl-C is how watching is meant to stop, so it isn't an error
+        // between syncs
+        let next_sync = Instant::now() + Duration::from_millis(args.interval);
+
+        while let Some(remaining) = next_sync.checked_duration_since(Instant::now()) {
+            if interrupt::interrupted() {
+                return Ok(());
+            }
+
+            thread::sleep(remaining.min(INTERRUPT_POLL_INTERVAL));
+        }
+    }
+}
+
+fn sync(args: &MirrorSyncArgs, source_dir: &Path, destination_dir: &Path) -> anyhow::Result<()> {
+    let index_theme = args.source.join("index.theme");
+    let destination_index_theme = args.destination.join("index.theme");
+
+    // The mirror's `index.theme` is only copied once, so that it can be
+    // edited (for instance, to give the mirror a different name)
+    if source_dir != args.source && index_theme.is_file() && !destination_index_theme.exists() {
+        output::copy(&index_theme, &destination_index_theme)?;
+    }
+
+    let mut names = BTreeSet::new();
+
+    for file in theme_files(source_dir)? {
+        if interrupt::interrupted() {
+            bail!("interrupted while syncing {}", args.source.display());
+        }
+
+        names.insert(file.name.clone());
+        let destination_path = destination_dir.join(&file.name);
+
+        if file.is_alias {
+            let (target, is_inside) = mirrored_link_target(source_dir, &file.path)?;
+
+            if fs::read_link(&destination_path).ok().as_ref() != Some(&target) {
+                if !is_inside {
+                    warn!(
+                        "{} points outside of {}, so its mirror points to the original cursor",
+                        file.path.display(),
+                        source_dir.display()
+                    );
+                }
+
+                remove_if_exists(&destination_path)?;
+                symlink(&target, &destination_path)?;
+                println!("updated {}", file.name);
+            }
+
+            continue;
+        }
+
+        if !is_outdated(&file.path, &destination_path)? {
+            continue;
+        }
+
+        let cursor_bytes = input::read(&file.path)?;
+
+        let cursor = match resize(&cursor_bytes, &ResizeOptions::new(args.scale)) {
+            Ok(cursor) => cursor,
+            // This file might still be being written, so it may be
+            // valid by the next sync
+            Err(err @ Error::Parse { .. }) => {
+                warn!(
+                    "{} isn't a valid Xcursor file, skipping: {err}",
+                    file.path.display()
+                );
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        // Nothing ever sees a partially written cursor, since outputs are
+        // written to a temporary file first
+        output::write(&destination_path, |output| cursor.write_to(output))?;
+        println!("updated {}", file.name);
+    }
+
+    for file in theme_files(destination_dir)? {
+        if !names.contains(&file.name) && !file.name.starts_with('.') {
+            fs::remove_file(&file.path)?;
+            println!("removed {}", file.name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns what the mirror of the symlink at `path` in `source_dir` should
+/// point to, and whether that's inside the source theme.
+///
+/// Symlinks to cursors in the source theme are made relative, even if they
+/// were absolute, so that the mirrored symlink points to the resized cursor.
+/// Symlinks that leave the theme are made absolute instead, since the
+/// mirrored symlink can only point to the same unscaled cursor.
+fn mirrored_link_target(source_dir: &Path, path: &Path) -> anyhow::Result<(PathBuf, bool)> {
+    let target = source_dir.join(fs::read_link(path)?);
+
+    // Only directories are resolved, so that symlinks to other symlinks
+    // are kept
+    let target = match (target.parent().map(fs::canonicalize), target.file_name()) {
+        (Some(Ok(parent)), Some(name)) => parent.join(name),
+        _ => target,
+    };
+
+    match target.strip_prefix(fs::canonicalize(source_dir)?) {
+        Ok(relative) if relative.file_name().is_some() => Ok((relative.to_owned(), true)),
+        _ => Ok((std::path::absolute(&target)?, false)),
+    }
+}
+
+/// Returns whether `destination` is missing or older than `source`.
+fn is_outdated(source: &Path, destination: &Path) -> anyhow::Result<bool> {
+    let Ok(destination_metadata) = fs::symlink_metadata(destination) else {
+        return Ok(true);
+    };
+
+    if destination_metadata.file_type().is_symlink() {
+        return Ok(true);
+    }
+
+    Ok(fs::metadata(source)?.modified()? > destination_metadata.modified()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_targets() {
+        let dir =
+            std::env::temp_dir().join(format!("resize-xcursor-mirror-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let source_dir = dir.join("theme/cursors");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::create_dir_all(dir.join("other/cursors")).unwrap();
+        let source_dir = fs::canonicalize(source_dir).unwrap();
+
+        let target = |name: &str, target: &Path| {
+            let path = source_dir.join(name);
+            symlink(target, &path).unwrap();
+            mirrored_link_target(&source_dir, &path).unwrap()
+        };
+
+        assert_eq!(
+            target("a", Path::new("default")),
+            (PathBuf::from("default"), true)
+        );
+        assert_eq!(
+            target("b", Path::new("./default")),
+            (PathBuf::from("default"), true)
+        );
+        assert_eq!(
+            target("c", Path::new("../cursors/default")),
+            (PathBuf::from("default"), true)
+        );
+        assert_eq!(
+            target("d", &source_dir.join("default")),
+            (PathBuf::from("default"), true)
+        );
+
+        let other = fs::canonicalize(dir.join("other/cursors"))
+            .unwrap()
+            .join("default");
+        assert_eq!(target("e", &other), (other.clone(), false));
+        assert_eq!(
+            target("f", Path::new("../../other/cursors/default")),
+            (other, false)
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+//! Subcommands that operate on entire cursor themes rather than
+//! individual Xcursor files.
+
+// Information about cursor theme directories can be found here:
+// https://www.freedesktop.org/wiki/Specifications/cursor-spec/
+
+mod compose;
+mod lint;
+mod manifest;
+mod mirror;
+mod remap;
+mod set_default;
+mod stats;
+mod subset;
+
+use anyhow::{bail, Context};
+use clap::{Args, Subcommand};
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Subcommand)]
+pub(crate) enum ThemeCommand {
+    /// Sets a cursor theme as the default for the current user.
+    ///
+    /// This writes `~/.icons/default/index.theme` so that the default
+    /// theme inherits from the given theme.
+    #[clap(verbatim_doc_comment)]
+    SetDefault(set_default::SetDefaultArgs),
+
+    /// Renames or copies cursors according to a mapping file.
+    ///
+    /// The mapping file is a TOML file with `[copy]` and `[rename]` tables,
+    /// each mapping an existing cursor name to a new one:
+    ///
+    ///     [copy]
+    ///     copy = "dnd-copy"
+    ///
+    ///     [rename]
+    ///     xterm = "text"
+    ///
+    /// Copies are made before anything is renamed. Symlinks pointing to a
+    /// renamed cursor are updated to point to its new name. A cursor can't
+    /// be both copied or renamed and replaced by the same mapping, and two
+    /// cursors can't be given the same name, since one of them would be
+    /// lost.
+    #[clap(verbatim_doc_comment)]
+    Remap(remap::RemapArgs),
+
+    /// Copies some of a theme's cursors into a new theme.
+    ///
+    /// Symlinks to other included cursors are preserved, while any other
+    /// symlinks are replaced with a copy of the cursor they point to.
+    #[clap(verbatim_doc_comment)]
+    Subset(subset::SubsetArgs),
+
+    /// Prints statistics about a theme's size.
+    ///
+    /// This includes a per-cursor breakdown and an estimate of how large
+    /// the theme would become after resizing it.
+    #[clap(verbatim_doc_comment)]
+    Stats(stats::StatsArgs),
+
+    /// Checks every cursor in a theme like `validate`, and the theme as a
+    /// whole.
+    ///
+    /// Aliases that point to cursors that don't exist are errors. Cursors
+    /// without a nominal size that most of the theme's other cursors have
+    /// are warned about, since they change size as the pointer moves onto
+    /// them. `--format json` prints a report that CI checks can use.
+    #[clap(verbatim_doc_comment)]
+    Lint(lint::LintArgs),
+
+    /// Writes a manifest of SHA-256 hashes for every file in a theme.
+    ///
+    /// The manifest can later be checked with `theme verify`.
+    #[clap(verbatim_doc_comment)]
+    Manifest(manifest::ManifestArgs),
+
+    /// Checks a theme against a manifest written by `theme manifest`.
+    ///
+    /// This exits with an error if any file is missing or has changed.
+    #[clap(verbatim_doc_comment)]
+    Verify(manifest::VerifyArgs),
+
+    /// Keeps a resized copy of a theme up to date.
+    ///
+    /// Only cursors that have changed since the last sync are resized again.
+    /// Cursors that were removed from the source theme are also removed
+    /// from the mirror.
+    #[clap(verbatim_doc_comment)]
+    MirrorSync(mirror::MirrorSyncArgs),
+
+    /// Builds a theme out of cursors from other themes.
+    ///
+    /// The theme is described by a TOML file like the following:
+    ///
+    ///     # Paths are relative to this file
+    ///     output = "my-theme"
+    ///     # Optional; defaults to the output directory's name
+    ///     name = "My Theme"
+    ///     # Optional; the scale used for cursors that don't specify one
+    ///     scale = 2
+    ///
+    ///     [sources]
+    ///     # Either a path or the name of an installed theme
+    ///     adwaita = "Adwaita"
+    ///     retro = "../retro-theme"
+    ///
+    ///     [[cursor]]
+    ///     source = "adwaita"
+    ///     name = "default"
+    ///     aliases = ["left_ptr", "arrow"]
+    ///
+    ///     [[cursor]]
+    ///     source = "retro"
+    ///     name = "xterm"
+    ///     # Optional; renames the cursor in the output theme
+    ///     rename = "text"
+    ///     scale = 3
+    ///
+    /// Cursors are scaled with nearest-neighbour scaling, which keeps pixel
+    /// art crisp. Running this again with the same file regenerates the same
+    /// theme.
+    /// Cursors in the output theme that the file doesn't list (such as ones
+    /// from an earlier version of it) are removed.
+    #[clap(verbatim_doc_comment)]
+    Compose(compose::ComposeArgs),
+}
+
+impl ThemeCommand {
+    pub(crate) fn run(self) -> anyhow::Result<()> {
+        match self {
+            Self::SetDefault(args) => set_default::run(args),
+            Self::Remap(args) => remap::run(args),
+            Self::Subset(args) => subset::run(args),
+            Self::Stats(args) => stats::run(args),
+            Self::Lint(args) => lint::run(args),
+            Self::Manifest(args) => manifest::run_manifest(args),
+            Self::Verify(args) => manifest::run_verify(args),
+            Self::MirrorSync(args) => mirror::run(args),
+            Self::Compose(args) => compose::run(args),
+        }
+    }
+}
+
+/// The location of a theme to operate on.
+#[derive(Args)]
+struct ThemeDir {
+    /// The theme directory to operate on.
+    ///
+    /// This can either be the theme's directory (containing `index.theme`
+    /// and `cursors/`), or the `cursors` directory itself.
+    #[clap(
+        short = 'd',
+        long = "dir",
+        value_parser,
+        default_value = ".",
+        verbatim_doc_comment
+    )]
+    dir: PathBuf,
+}
+
+impl ThemeDir {
+    /// Returns the directory containing the theme's Xcursor files.
+    fn cursors_dir(&self) -> PathBuf {
+        cursors_dir(&self.dir)
+    }
+}
+
+/// Returns the directory containing the Xcursor files for the theme in `dir`.
+fn cursors_dir(dir: &Path) -> PathBuf {
+    let cursors = dir.join("cursors");
+
+    if cursors.is_dir() {
+        cursors
+    } else {
+        dir.to_owned()
+    }
+}
+
+/// A file in a theme's `cursors` directory.
+struct ThemeFile {
+    name: String,
+    path: PathBuf,
+    /// Whether this file is a symlink to another cursor.
+    is_alias: bool,
+}
+
+/// Returns every file in the given `cursors` directory, sorted by name.
+fn theme_files(dir: &Path) -> anyhow::Result<Vec<ThemeFile>> {
+    let mut res = Vec::new();
+
+    for entry in fs::read_dir(dir).with_context(|| format!("couldn't read {}", dir.display()))? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            continue;
+        }
+
+        res.push(ThemeFile {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            path: entry.path(),
+            is_alias: file_type.is_symlink(),
+        });
+    }
+
+    res.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(res)
+}
+
+/// Formats a number of bytes using binary prefixes (for example, `1.5 KiB`).
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+
+    for next_unit in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+
+        value /= 1024.0;
+        unit = next_unit;
+    }
+
+    format!("{:.1} {}", value, unit)
+}
+
+/// Checks that `name` can be used as a file name inside a theme.
+fn check_cursor_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        bail!("`{}` isn't a valid cursor name", name);
+    }
+
+    Ok(())
+}
+
+/// Removes `path` if it exists and `force` is set, or returns an error
+/// if it exists and `force` isn't set.
+fn prepare_destination(path: &Path, force: bool) -> anyhow::Result<()> {
+    if fs::symlink_metadata(path).is_err() {
+        return Ok(());
+    }
+
+    if !force {
+        bail!(
+            "{} already exists (use `--force` to overwrite it)",
+            path.display()
+        );
+    }
+
+    fs::remove_file(path).with_context(|| format!("couldn't remove {}", path.display()))
+}
+
+/// Removes the file (or symlink) at `path`, if there is one.
+fn remove_if_exists(path: &Path) -> anyhow::Result<()> {
+    if fs::symlink_metadata(path).is_ok() {
+        fs::remove_file(path).with_context(|| format!("couldn't remove {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// If `path` is a symlink to another file in the same directory (for
+/// instance, `left_ptr -> default` or `left_ptr -> ./default`), returns
+/// the name of that file.
+fn sibling_link_target(path: &Path) -> Option<OsString> {
+    let target = fs::read_link(path).ok()?;
+
+    let is_sibling = target
+        .parent()
+        .is_none_or(|parent| parent.as_os_str().is_empty() || parent == Path::new("."));
+
+    if is_sibling {
+        target.file_name().map(ToOwned::to_owned)
+    } else {
+        None
+    }
+}
+
+fn home_dir() -> anyhow::Result<PathBuf> {
+    match env::var_os("HOME") {
+        Some(home) if !home.is_empty() => Ok(PathBuf::from(home)),
+        _ => bail!("couldn't find your home directory (is `$HOME` set?)"),
+    }
+}
+
+/// Returns the directories that are searched for cursor themes, in order.
+fn icon_dirs() -> anyhow::Result<Vec<PathBuf>> {
+    let home = home_dir()?;
+
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local/share"));
+
+    Ok(vec![
+        home.join(".icons"),
+        data_home.join("icons"),
+        PathBuf::from("/usr/share/icons"),
+        PathBuf::from("/usr/share/pixmaps"),
+    ])
+}
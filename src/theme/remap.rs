@@ -0,0 +1,187 @@
+//! Implementation of `theme remap`.
+
+use super::{check_cursor_name, prepare_destination, sibling_link_target, ThemeDir};
+use crate::output;
+use anyhow::{bail, Context};
+use clap::Args;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+#[derive(Args)]
+pub(crate) struct RemapArgs {
+    /// The TOML file describing which cursors to rename or copy.
+    #[clap(value_parser)]
+    mapping: PathBuf,
+
+    #[clap(flatten)]
+    theme: ThemeDir,
+
+    /// If given, overwrites existing cursors with the same name as a
+    /// copied or renamed cursor.
+    #[clap(short, long, verbatim_doc_comment)]
+    force: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Mapping {
+    /// Maps existing cursor names to the names of new copies.
+    #[serde(default)]
+    copy: BTreeMap<String, String>,
+    /// Maps existing cursor names to their new names.
+    #[serde(default)]
+    rename: BTreeMap<String, String>,
+}
+
+pub(crate) fn run(args: RemapArgs) -> anyhow::Result<()> {
+    let mapping_text = fs::read_to_string(&args.mapping)
+        .with_context(|| format!("couldn't read {}", args.mapping.display()))?;
+    let mapping: Mapping = toml::from_str(&mapping_text)
+        .with_context(|| format!("{} isn't a valid mapping file", args.mapping.display()))?;
+
+    check_mapping(&mapping)?;
+
+    let dir = args.theme.cursors_dir();
+
+    for (from, to) in &mapping.copy {
+        let Some(from_path) = existing_cursor(&dir, from) else {
+            continue;
+        };
+
+        let to_path = dir.join(to);
+        prepare_destination(&to_path, args.force)?;
+        // This follows symlinks, so the copy is always a regular file
+        output::copy(&from_path, &to_path).with_context(|| {
+            format!(
+                "couldn't copy {} to {}",
+                from_path.display(),
+                to_path.display()
+            )
+        })?;
+    }
+
+    for (from, to) in &mapping.rename {
+        let Some(from_path) = existing_cursor(&dir, from) else {
+            continue;
+        };
+
+        let to_path = dir.join(to);
+        prepare_destination(&to_path, args.force)?;
+        fs::rename(&from_path, &to_path).with_context(|| {
+            format!(
+                "couldn't rename {} to {}",
+                from_path.display(),
+                to_path.display()
+            )
+        })?;
+
+        retarget_symlinks(&dir, from, to)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that every name in `mapping` is valid, and that applying it one
+/// entry at a time doesn't lose any cursors.
+///
+/// With `--force`, a destination that's also a source (as in a chain of
+/// renames like `a = "b"` and `b = "c"`) would be replaced before it's moved,
+/// and a destination given twice would be replaced by the second entry.
+fn check_mapping(mapping: &Mapping) -> anyhow::Result<()> {
+    let entries = || mapping.copy.iter().chain(&mapping.rename);
+    let sources = entries().map(|(from, _)| from).collect::<BTreeSet<_>>();
+    let mut destinations = BTreeSet::new();
+
+    for (from, to) in entries() {
+        check_cursor_name(from)?;
+        check_cursor_name(to)?;
+
+        if sources.contains(to) {
+            bail!("can't map `{from}` to `{to}`, since `{to}` is being copied or renamed too");
+        }
+
+        if !destinations.insert(to) {
+            bail!("more than one cursor is mapped to `{to}`");
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the path to the given cursor, or `None` (with a warning) if it
+/// doesn't exist.
+fn existing_cursor(dir: &Path, name: &str) -> Option<PathBuf> {
+    let path = dir.join(name);
+
+    // `symlink_metadata` is used so that dangling symlinks can still be renamed
+    if fs::symlink_metadata(&path).is_ok() {
+        Some(path)
+    } else {
+        // Mapping files are likely to be shared between themes, so a missing
+        // cursor isn't an error
+        warn!("{} doesn't exist, skipping", path.display());
+        None
+    }
+}
+
+/// Updates every symlink in `dir` that points to the cursor `from` so that
+/// it points to `to` instead.
+fn retarget_symlinks(dir: &Path, from: &str, to: &str) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("couldn't read {}", dir.display()))? {
+        let path = entry?.path();
+
+        if sibling_link_target(&path).as_deref() == Some(from.as_ref()) {
+            fs::remove_file(&path)?;
+            symlink(to, &path)
+                .with_context(|| format!("couldn't update symlink {}", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(mapping: &str) -> anyhow::Result<()> {
+        check_mapping(&toml::from_str(mapping).unwrap())
+    }
+
+    #[test]
+    fn valid_mapping() {
+        check(r#"copy = { copy = "dnd-copy", xterm = "ibeam" }"#).unwrap();
+        check(r#"rename = { xterm = "text", copy = "dnd-copy" }"#).unwrap();
+        // Copies are made before the original is renamed
+        check("copy = { a = \"b\" }\nrename = { a = \"c\" }").unwrap();
+    }
+
+    #[test]
+    fn rename_chain() {
+        let err = check(r#"rename = { a = "b", b = "c" }"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "can't map `a` to `b`, since `b` is being copied or renamed too"
+        );
+
+        check(r#"rename = { a = "a" }"#).unwrap_err();
+        check(r#"rename = { a = "b", b = "a" }"#).unwrap_err();
+        check(r#"copy = { a = "b", b = "c" }"#).unwrap_err();
+        // The copy would replace `b` before it's renamed
+        check("copy = { a = \"b\" }\nrename = { b = \"c\" }").unwrap_err();
+    }
+
+    #[test]
+    fn same_destination() {
+        let err = check(r#"rename = { a = "c", b = "c" }"#).unwrap_err();
+        assert_eq!(err.to_string(), "more than one cursor is mapped to `c`");
+
+        check("copy = { a = \"c\" }\nrename = { b = \"c\" }").unwrap_err();
+    }
+}
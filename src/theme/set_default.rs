@@ -0,0 +1,106 @@
+//! Implementation of `theme set-default`.
+
+use super::{home_dir, icon_dirs};
+use crate::output;
+use anyhow::{bail, Context};
+use clap::Args;
+use std::fs;
+use tracing::warn;
+
+#[derive(Args)]
+pub(crate) struct SetDefaultArgs {
+    /// The name of the theme to use (for example, `Adwaita`).
+    name: String,
+
+    /// If given, also sets `Xcursor.size` in `~/.Xresources`.
+    ///
+    /// This is useful after resizing a theme, since many applications
+    /// will otherwise keep using the old size.
+    #[clap(long, verbatim_doc_comment)]
+    size: Option<u32>,
+}
+
+pub(super) fn run(SetDefaultArgs { name, size }: SetDefaultArgs) -> anyhow::Result<()> {
+    if name.is_empty() || name.contains('/') {
+        bail!("`{}` isn't a valid theme name", name);
+    }
+
+    // Setting a theme that isn't installed (yet) isn't necessarily a mistake,
+    // so only warn about it
+    if !icon_dirs()?
+        .iter()
+        .any(|dir| dir.join(&name).join("cursors").is_dir())
+    {
+        warn!("couldn't find an installed cursor theme named `{}`", name);
+    }
+
+    let home = home_dir()?;
+    let default_dir = home.join(".icons/default");
+    fs::create_dir_all(&default_dir)
+        .with_context(|| format!("couldn't create {}", default_dir.display()))?;
+
+    let index_theme = default_dir.join("index.theme");
+    output::write_bytes(
+        &index_theme,
+        format!(
+            "[Icon Theme]\n\
+            Name=Default\n\
+            Comment=Default Cursor Theme\n\
+            Inherits={}\n",
+            name
+        ),
+    )
+    .with_context(|| format!("couldn't write {}", index_theme.display()))?;
+
+    if let Some(size) = size {
+        let xresources = home.join(".Xresources");
+
+        let contents = match fs::read_to_string(&xresources) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("couldn't read {}", xresources.display()));
+            }
+        };
+
+        output::write_bytes(&xresources, set_xresource(&contents, "Xcursor.size", size))
+            .with_context(|| format!("couldn't write {}", xresources.display()))?;
+
+        println!(
+            "note: run `xrdb -merge {}` (or log in again) for the new size to take effect",
+            xresources.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Replaces every definition of `key` in the given X resources, or appends
+/// a new definition if there weren't any.
+fn set_xresource(contents: &str, key: &str, value: impl std::fmt::Display) -> String {
+    let mut res = String::with_capacity(contents.len());
+    let mut found = false;
+
+    for line in contents.lines() {
+        let is_key = line
+            .trim_start()
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with(':'));
+
+        if is_key {
+            if !found {
+                res.push_str(&format!("{}: {}\n", key, value));
+                found = true;
+            }
+        } else {
+            res.push_str(line);
+            res.push('\n');
+        }
+    }
+
+    if !found {
+        res.push_str(&format!("{}: {}\n", key, value));
+    }
+
+    res
+}
@@ -0,0 +1,225 @@
+//! Implementation of `theme stats`.
+
+use super::{format_bytes, theme_files, ThemeDir};
+use crate::input;
+use clap::Args;
+use resize_xcursor::{read_xcursor::ImageInfo, XcursorReader};
+use std::collections::{BTreeMap, HashSet};
+use tracing::warn;
+
+/// The number of animated cursors to list.
+const BIGGEST_ANIMATED_COUNT: usize = 5;
+
+#[derive(Args)]
+pub(crate) struct StatsArgs {
+    #[clap(flatten)]
+    theme: ThemeDir,
+
+    /// If given, estimates the theme's size after being resized by this
+    /// scale factor.
+    #[clap(short, long, verbatim_doc_comment)]
+    scale: Option<u32>,
+}
+
+struct CursorStats {
+    name: String,
+    bytes: u64,
+    /// The number of bytes taken up by pixel data.
+    pixel_bytes: u64,
+    sizes: usize,
+    /// The number of frames in the longest animation in this cursor.
+    frames: usize,
+}
+
+/// The number of bytes taken up by the pixels of `images`.
+///
+/// Table of contents entries can share a chunk, and libXcursor loads it for
+/// each of them, but it's only stored once, so it's only counted once.
+fn pixel_bytes(images: &[ImageInfo]) -> u64 {
+    let mut positions = HashSet::new();
+
+    images
+        .iter()
+        .filter(|image| positions.insert(image.position))
+        .map(|image| image.pixels_byte_length())
+        .fold(0, u64::saturating_add)
+}
+
+pub(crate) fn run(args: StatsArgs) -> anyhow::Result<()> {
+    let dir = args.theme.cursors_dir();
+    let mut cursors = Vec::new();
+    let mut aliases = 0;
+
+    for file in theme_files(&dir)? {
+        if file.is_alias {
+            aliases += 1;
+            continue;
+        }
+
+        let bytes = input::read(&file.path)?;
+
+        // Only image headers are needed, so pixels don't have to be read
+        let images = XcursorReader::new(&bytes)
+            .and_then(|reader| reader.image_infos().collect::<Result<Vec<_>, _>>());
+
+        let images = match images {
+            Ok(images) => images,
+            Err(err) => {
+                warn!(
+                    "{} isn't a valid Xcursor file, skipping: {err}",
+                    file.path.display()
+                );
+                continue;
+            }
+        };
+
+        // Images with the same nominal size are frames of the same animation
+        let mut frames_per_size = BTreeMap::<u32, usize>::new();
+
+        for image in &images {
+            *frames_per_size.entry(image.size()).or_default() += 1;
+        }
+
+        cursors.push(CursorStats {
+            name: file.name,
+            bytes: bytes.len() as u64,
+            pixel_bytes: pixel_bytes(&images),
+            sizes: frames_per_size.len(),
+            frames: frames_per_size.values().copied().max().unwrap_or(0),
+        });
+    }
+
+    if cursors.is_empty() {
+        println!("no cursors found in {}", dir.display());
+        return Ok(());
+    }
+
+    cursors.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+
+    let name_width = cursors
+        .iter()
+        .map(|cursor| cursor.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("cursor".len());
+
+    println!(
+        "{:<name_width$}  {:>10}  {:>5}  {:>6}",
+        "cursor", "size", "sizes", "frames"
+    );
+
+    for cursor in &cursors {
+        println!(
+            "{:<name_width$}  {:>10}  {:>5}  {:>6}",
+            cursor.name,
+            format_bytes(cursor.bytes),
+            cursor.sizes,
+            cursor.frames,
+        );
+    }
+
+    let total_bytes = cursors.iter().map(|cursor| cursor.bytes).sum::<u64>();
+    let total_frames = cursors.iter().map(|cursor| cursor.frames).sum::<usize>();
+
+    println!();
+    println!(
+        "total: {} in {} cursors ({} aliases)",
+        format_bytes(total_bytes),
+        cursors.len(),
+        aliases
+    );
+    println!(
+        "average frames per cursor: {:.1}",
+        total_frames as f64 / cursors.len() as f64
+    );
+
+    let animated = cursors
+        .iter()
+        .filter(|cursor| cursor.frames > 1)
+        .take(BIGGEST_ANIMATED_COUNT)
+        .collect::<Vec<_>>();
+
+    if !animated.is_empty() {
+        println!("biggest animated cursors:");
+
+        for cursor in animated {
+            println!(
+                "    {} ({}, {} frames)",
+                cursor.name,
+                format_bytes(cursor.bytes),
+                cursor.frames
+            );
+        }
+    }
+
+    if let Some(scale) = args.scale {
+        // Scaling only affects pixel data; headers and the table of contents
+        // stay the same size
+        let factor = u64::from(scale).saturating_mul(u64::from(scale));
+        let scaled_bytes = cursors
+            .iter()
+            .map(|cursor| {
+                // Chunks can still overlap without starting at the same place
+                cursor
+                    .bytes
+                    .saturating_sub(cursor.pixel_bytes)
+                    .saturating_add(cursor.pixel_bytes.saturating_mul(factor))
+            })
+            .fold(0u64, u64::saturating_add);
+
+        println!(
+            "estimated total with --scale {}: {} ({:.1}x)",
+            scale,
+            format_bytes(scaled_bytes),
+            scaled_bytes as f64 / total_bytes as f64
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resize_xcursor::{Image, XcursorBuilder};
+
+    fn image_infos(bytes: &[u8]) -> Vec<ImageInfo> {
+        XcursorReader::new(bytes)
+            .unwrap()
+            .image_infos()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn set_field(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn shared_chunks_counted_once() {
+        let mut cursor = XcursorBuilder::new();
+
+        for size in [32, 48] {
+            let pixels = vec![0; (size * size) as usize];
+            cursor
+                .add_image(Image::new(size, size, size, 0, 0, 0, pixels).unwrap())
+                .unwrap();
+        }
+
+        let mut bytes = Vec::new();
+        cursor.write_to(&mut bytes).unwrap();
+        assert_eq!(pixel_bytes(&image_infos(&bytes)), (32 * 32 + 48 * 48) * 4);
+
+        // Point the second entry at the first image, and drop the second
+        // image, so that the pixels of both entries combined are larger
+        // than the file
+        let first_image = 16 + 2 * 12;
+        set_field(&mut bytes, 16 + 12 + 4, 32);
+        set_field(&mut bytes, 16 + 12 + 8, first_image);
+        bytes.truncate(first_image as usize + 36 + 32 * 32 * 4);
+
+        let images = image_infos(&bytes);
+        assert_eq!(images.len(), 2);
+        assert_eq!(pixel_bytes(&images), 32 * 32 * 4);
+    }
+}
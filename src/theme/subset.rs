@@ -0,0 +1,83 @@
+//! Implementation of `theme subset`.
+
+use super::{check_cursor_name, prepare_destination, sibling_link_target, ThemeDir};
+use crate::output;
+use anyhow::{bail, Context};
+use clap::Args;
+use std::{fs, os::unix::fs::symlink, path::PathBuf};
+use tracing::warn;
+
+#[derive(Args)]
+pub(crate) struct SubsetArgs {
+    /// A comma-separated list of cursors to include.
+    #[clap(short, long, required = true, value_delimiter = ',')]
+    names: Vec<String>,
+
+    #[clap(flatten)]
+    theme: ThemeDir,
+
+    /// The directory to create the new theme in.
+    ///
+    /// The theme's name is taken from the name of this directory.
+    #[clap(short, long, value_parser, verbatim_doc_comment)]
+    output: PathBuf,
+
+    /// If given, overwrites cursors that already exist in the output theme.
+    #[clap(short, long)]
+    force: bool,
+}
+
+pub(crate) fn run(args: SubsetArgs) -> anyhow::Result<()> {
+    for name in &args.names {
+        check_cursor_name(name)?;
+    }
+
+    let source_dir = args.theme.cursors_dir();
+    let output_cursors_dir = args.output.join("cursors");
+
+    if output_cursors_dir == source_dir {
+        bail!("the output theme can't be the same as the input theme");
+    }
+
+    fs::create_dir_all(&output_cursors_dir)
+        .with_context(|| format!("couldn't create {}", output_cursors_dir.display()))?;
+
+    for name in &args.names {
+        let source_path = source_dir.join(name);
+        let output_path = output_cursors_dir.join(name);
+
+        if fs::metadata(&source_path).is_err() {
+            warn!("{} doesn't exist, skipping", source_path.display());
+            continue;
+        }
+
+        prepare_destination(&output_path, args.force)?;
+
+        // Aliases of other included cursors are kept as symlinks;
+        // anything else is copied so that the new theme is self-contained
+        match sibling_link_target(&source_path) {
+            Some(target) if args.names.iter().any(|name| target == name.as_str()) => {
+                symlink(target, &output_path).map_err(Into::into)
+            }
+
+            _ => output::copy(&source_path, &output_path),
+        }
+        .with_context(|| format!("couldn't create {}", output_path.display()))?;
+    }
+
+    let index_theme = args.output.join("index.theme");
+
+    if fs::symlink_metadata(&index_theme).is_err() {
+        let name = args
+            .output
+            .canonicalize()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        output::write_bytes(&index_theme, format!("[Icon Theme]\nName={}\n", name))
+            .with_context(|| format!("couldn't write {}", index_theme.display()))?;
+    }
+
+    Ok(())
+}
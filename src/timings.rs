@@ -0,0 +1,130 @@
+//! Per-stage timing reports for `--timings`.
+
+use std::{
+    io::{self, Write},
+    ops::AddAssign,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// How long each stage of resizing a file took.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Stages {
+    pub(crate) read: Duration,
+    pub(crate) parse: Duration,
+    /// Resizing and encoding every image in the file, since each image is
+    /// written as soon as it's been resized.
+    pub(crate) scale: Duration,
+    /// Writing the encoded images to the output.
+    pub(crate) write: Duration,
+}
+
+impl Stages {
+    fn total(&self) -> Duration {
+        self.read + self.parse + self.scale + self.write
+    }
+}
+
+impl AddAssign for Stages {
+    fn add_assign(&mut self, rhs: Self) {
+        self.read += rhs.read;
+        self.parse += rhs.parse;
+        self.scale += rhs.scale;
+        self.write += rhs.write;
+    }
+}
+
+/// The timings for every file that was resized.
+#[derive(Default)]
+pub(crate) struct Report {
+    files: Vec<(PathBuf, Stages)>,
+}
+
+impl Report {
+    pub(crate) fn push(&mut self, path: PathBuf, stages: Stages) {
+        self.files.push((path, stages));
+    }
+
+    /// Prints a table of every file's timings, followed by their totals,
+    /// to stderr.
+    pub(crate) fn print(&self) {
+        let names = self
+            .files
+            .iter()
+            .map(|(path, _)| path.display().to_string())
+            .collect::<Vec<_>>();
+
+        let name_width = names
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("total".len());
+
+        eprintln!(
+            "{:<name_width$}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}",
+            "file", "read", "parse", "scale", "write", "total"
+        );
+
+        let mut total = Stages::default();
+
+        for (name, (_, stages)) in names.iter().zip(&self.files) {
+            print_row(name, name_width, stages);
+            total += *stages;
+        }
+
+        if self.files.len() > 1 {
+            print_row("total", name_width, &total);
+        }
+    }
+}
+
+fn print_row(name: &str, name_width: usize, stages: &Stages) {
+    eprintln!(
+        "{:<name_width$}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}",
+        name,
+        format_duration(stages.read),
+        format_duration(stages.parse),
+        format_duration(stages.scale),
+        format_duration(stages.write),
+        format_duration(stages.total()),
+    );
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Runs `f`, adding the time it took to `duration`.
+pub(crate) fn time<T>(duration: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let res = f();
+    *duration += start.elapsed();
+    res
+}
+
+/// A writer that adds the time spent writing to `inner` to a duration.
+pub(crate) struct TimedWriter<'a, W> {
+    inner: W,
+    duration: &'a mut Duration,
+}
+
+impl<'a, W: Write> TimedWriter<'a, W> {
+    pub(crate) fn new(inner: W, duration: &'a mut Duration) -> Self {
+        Self { inner, duration }
+    }
+}
+
+impl<W: Write> Write for TimedWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        time(self.duration, || self.inner.write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        time(self.duration, || self.inner.write_all(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        time(self.duration, || self.inner.flush())
+    }
+}
@@ -0,0 +1,540 @@
+//! Per-image transforms, which can be chained together into a [`Pipeline`].
+
+use crate::{
+    pool, write_xcursor::check_layout, Chunk, Error, FileHeader, Image, ImageInfo, ImageLayout,
+    ParsedImage, RawChunk, Result, SeekReader, StreamingWriter, TocEntry, XcursorBuilder,
+};
+use std::{
+    fmt::Debug,
+    io::{Read, Seek, Write},
+};
+use tracing::{debug, debug_span};
+
+/// An operation applied to each image in a cursor, such as resizing it.
+pub trait Transform: Debug + Send + Sync {
+    /// Transforms a single image.
+    fn apply(&self, image: Image) -> Result<Image>;
+
+    /// Returns the nominal size and dimensions that [`Transform::apply`]
+    /// would give an image with the given layout, or `None` if that can't be
+    /// known without looking at the image's pixels.
+    ///
+    /// When every transform's layout is known, images can be written out as
+    /// soon as they're transformed (see [`transform_images_to`]).
+    /// The default implementation returns `None`.
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        let _ = layout;
+        Ok(None)
+    }
+}
+
+/// A sequence of transforms, applied to each image in order.
+///
+/// ```no_run
+/// use resize_xcursor::{transform::Pipeline, ResizeOptions};
+/// use std::fs::{self, File};
+///
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(ResizeOptions::new(2));
+///
+/// let cursor_bytes = fs::read("left_ptr")?;
+/// let images = resize_xcursor::parse(&cursor_bytes)?;
+/// resize_xcursor::transform_images(images, &pipeline)?
+///     .write_to(File::create("left_ptr-big")?)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Pipeline {
+    /// Creates a pipeline with no transforms, which leaves images unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a transform to the end of this pipeline.
+    pub fn push(&mut self, transform: impl Transform + 'static) -> &mut Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Returns the number of transforms in this pipeline.
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    /// Returns whether this pipeline has no transforms.
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Like [`Transform::layout`], but assumes that transforms whose layout
+    /// depends on an image's pixels keep its layout as it is, instead of
+    /// giving up. Also returns whether the layout is exact.
+    pub fn approximate_layout(&self, layout: ImageLayout) -> Result<(ImageLayout, bool)> {
+        let mut layout = layout;
+        let mut exact = true;
+
+        for transform in &self.transforms {
+            match transform.layout(layout)? {
+                Some(new_layout) => layout = new_layout,
+                None => exact = false,
+            }
+        }
+
+        Ok((layout, exact))
+    }
+}
+
+impl Transform for Pipeline {
+    fn apply(&self, image: Image) -> Result<Image> {
+        self.transforms
+            .iter()
+            .try_fold(image, |image, transform| transform.apply(image))
+    }
+
+    fn layout(&self, layout: ImageLayout) -> Result<Option<ImageLayout>> {
+        let mut layout = layout;
+
+        for transform in &self.transforms {
+            match transform.layout(layout)? {
+                Some(new_layout) => layout = new_layout,
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(layout))
+    }
+}
+
+/// A prediction of how large a cursor will be once it's transformed, from
+/// [`estimate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Estimate {
+    /// The length of the transformed file, in bytes. This can be larger than
+    /// an Xcursor file can be, in which case transforming it would fail.
+    pub output_bytes: u64,
+    /// Roughly how much memory [`transform_chunks_to`] needs at once to
+    /// transform the cursor, in bytes, not counting the input file itself.
+    pub peak_memory: u64,
+    /// Whether the layout of every transformed image was known. If not,
+    /// transforms whose layout depends on pixels (such as
+    /// [`Trim`](crate::filters::Trim)) were assumed to keep each image's
+    /// layout.
+    pub exact: bool,
+}
+
+/// Predicts how large a parsed Xcursor will be once `pipeline` is applied to
+/// it, and how much memory that will take, without transforming anything.
+///
+/// ```
+/// use resize_xcursor::{transform::{estimate, Pipeline}, FileHeader, Image, ResizeOptions, XcursorBuilder};
+///
+/// let mut cursor = XcursorBuilder::new();
+/// cursor.add_image(Image::new(8, 8, 8, 0, 0, 0, vec![0; 64])?)?;
+/// let mut bytes = Vec::new();
+/// cursor.write_to(&mut bytes)?;
+///
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(ResizeOptions::new(3));
+///
+/// let chunks = resize_xcursor::parse_chunks(&bytes)?;
+/// let estimate = estimate(FileHeader::default(), &chunks, &pipeline)?;
+/// assert_eq!(estimate.output_bytes, 16 + 12 + 36 + 24 * 24 * 4);
+/// assert!(estimate.exact);
+/// # Ok::<(), resize_xcursor::Error>(())
+/// ```
+///
+/// Like [`transform_images`], this fails if an image's layout can't be
+/// transformed, such as when scaling it would overflow.
+pub fn estimate(
+    header: FileHeader,
+    cursor_chunks: &[Chunk<'_>],
+    pipeline: &Pipeline,
+) -> Result<Estimate> {
+    let pixel_bytes = |layout: ImageLayout| u64::from(layout.width) * u64::from(layout.height) * 4;
+
+    let mut output_bytes = u64::from(header.header_size)
+        + cursor_chunks.len() as u64 * u64::from(TocEntry::BYTE_LENGTH);
+    let mut raw_bytes = 0;
+    let mut exact = true;
+    // The most memory any one image needs while it's being transformed,
+    // and the memory every transformed image needs together
+    let mut largest_image = 0;
+    let mut largest_input = 0;
+    let mut output_pixels = 0;
+    let mut image_count = 0;
+
+    for chunk in cursor_chunks {
+        let Chunk::Image(image) = chunk else {
+            raw_bytes += chunk.byte_length();
+            continue;
+        };
+
+        let (layout, layout_exact) = pipeline
+            .approximate_layout(image.info.layout())
+            .map_err(|err| Error::in_image(image_count, image.info.size(), err))?;
+
+        let input = pixel_bytes(image.info.layout());
+        let output = pixel_bytes(layout);
+
+        output_bytes += u64::from(Image::HEADER_SIZE) + output;
+        exact &= layout_exact;
+        largest_image = largest_image.max(input + output);
+        largest_input = largest_input.max(input);
+        output_pixels += output;
+        image_count += 1;
+    }
+
+    output_bytes += raw_bytes;
+
+    // See `transform_chunks_to`: a batch of images is transformed at once,
+    // unless every image has to be transformed before anything is written
+    let batch = batch_size().min(image_count) as u64;
+    let peak_memory = raw_bytes
+        + match exact {
+            true => batch * largest_image,
+            false => output_pixels + batch * largest_input,
+        };
+
+    Ok(Estimate {
+        output_bytes,
+        peak_memory,
+        exact,
+    })
+}
+
+/// Applies `transform` to every image in a parsed Xcursor.
+///
+/// Other chunks, such as comments, are copied to the output unchanged, in
+/// their original places among the images. With the `parallel` feature,
+/// images are transformed in parallel, but are still added to the output in
+/// their original order.
+///
+/// Errors caused by an image are wrapped in an [`Error::InImage`] naming it.
+/// If [`Transform::layout`] is known for every image, images that would be
+/// too large are found before any of them are transformed.
+pub fn transform_images<'a>(
+    cursor_chunks: impl IntoIterator<Item = impl Into<Chunk<'a>>>,
+    transform: &dyn Transform,
+) -> Result<XcursorBuilder> {
+    let (cursor_images, raw_chunks, positions) = split_chunks(cursor_chunks);
+
+    if let Some(layout) = transformed_layout(&cursor_images, transform)? {
+        let sizes = cursor_images.iter().map(|image| image.info.size());
+        check_transformed_layout(FileHeader::default(), &raw_chunks, &layout, sizes)?;
+    }
+
+    let mut cursor = XcursorBuilder::new();
+    let mut raw_chunks = raw_chunks.into_iter().zip(positions).peekable();
+
+    for (index, output_image) in apply_all(0, &cursor_images, transform)
+        .into_iter()
+        .enumerate()
+    {
+        while let Some((chunk, _)) = raw_chunks.next_if(|&(_, position)| position <= index) {
+            cursor.add_raw_chunk(chunk)?;
+        }
+
+        let output_image = output_image?;
+        let size = output_image.size();
+
+        cursor
+            .add_image(output_image)
+            .map_err(|err| Error::in_image(index, size, err))?;
+    }
+
+    for (chunk, _) in raw_chunks {
+        cursor.add_raw_chunk(chunk)?;
+    }
+
+    Ok(cursor)
+}
+
+/// Applies `transform` to every image in a parsed Xcursor, writing each one
+/// to `writer` as soon as it's ready. Returns `writer` once every image has
+/// been written. Other chunks are copied as in [`transform_images`].
+///
+/// If [`Transform::layout`] is known for every image, this never holds every
+/// transformed image in memory at once. With the `parallel` feature, a batch
+/// of images is transformed in parallel, then written before the next batch
+/// is started.
+pub fn transform_images_to<'a, W: Write>(
+    cursor_chunks: impl IntoIterator<Item = impl Into<Chunk<'a>>>,
+    transform: &dyn Transform,
+    writer: W,
+) -> Result<W> {
+    transform_chunks_to(FileHeader::default(), cursor_chunks, transform, writer)
+}
+
+/// Like [`transform_images_to`], but writes the given file header instead
+/// of the default one, so that an input file's header can be reproduced.
+pub fn transform_chunks_to<'a, W: Write>(
+    header: FileHeader,
+    cursor_chunks: impl IntoIterator<Item = impl Into<Chunk<'a>>>,
+    transform: &dyn Transform,
+    writer: W,
+) -> Result<W> {
+    let (cursor_images, raw_chunks, positions) = split_chunks(cursor_chunks);
+
+    let layout = transformed_layout(&cursor_images, transform)?;
+
+    if let Some(layout) = &layout {
+        let sizes = cursor_images.iter().map(|image| image.info.size());
+        check_transformed_layout(header, &raw_chunks, layout, sizes)?;
+    }
+
+    let Some(layout) = layout else {
+        // The table of contents comes first, so every image has to be
+        // transformed before anything can be written
+        debug!("layout depends on pixels, transforming every image before writing");
+        let output_images = apply_all(0, &cursor_images, transform)
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut writer = StreamingWriter::with_interleaved_chunks(
+            writer,
+            header,
+            &raw_chunks,
+            &positions,
+            output_images.iter().map(Image::layout),
+        )?;
+
+        for output_image in output_images {
+            writer.write_image(&output_image)?;
+            pool::give(output_image.into_pixels());
+        }
+
+        return writer.finish();
+    };
+
+    let mut writer =
+        StreamingWriter::with_interleaved_chunks(writer, header, &raw_chunks, &positions, layout)?;
+
+    let batch_size = batch_size();
+
+    for (batch_index, batch) in cursor_images.chunks(batch_size).enumerate() {
+        for output_image in apply_all(batch_index * batch_size, batch, transform) {
+            let output_image = output_image?;
+            writer.write_image(&output_image)?;
+            pool::give(output_image.into_pixels());
+        }
+    }
+
+    writer.finish()
+}
+
+/// Applies `transform` to every image in an Xcursor file, reading,
+/// transforming, and writing one image at a time. Returns `writer` once
+/// every image has been written. The input's file header is kept.
+///
+/// Unlike [`transform_images_to`], the input doesn't have to be read into
+/// memory, and only one image is held in memory at once (although other
+/// chunks, such as comments, are all read upfront). If
+/// [`Transform::layout`] isn't known for every image, each image is
+/// transformed twice: once to lay out the table of contents, and again to
+/// write it.
+pub fn transform_seekable_to<R: Read + Seek, W: Write>(
+    mut reader: SeekReader<R>,
+    transform: &dyn Transform,
+    writer: W,
+) -> Result<W> {
+    let header = reader.header();
+    let infos = reader.image_infos()?;
+    let raw_chunks = reader.raw_chunks()?;
+    let positions = raw_chunk_positions(reader.table_of_contents());
+    let mut buffer = Vec::new();
+    let mut layout = Vec::with_capacity(infos.len());
+
+    for (index, info) in infos.iter().enumerate() {
+        let image_layout = match transform
+            .layout(info.layout())
+            .map_err(|err| Error::in_image(index, info.size(), err))?
+        {
+            Some(image_layout) => image_layout,
+
+            None => {
+                debug!(
+                    position = info.position,
+                    "layout depends on pixels, transforming image to lay it out"
+                );
+                reader.read_pixels(info, &mut buffer)?;
+
+                let image = ParsedImage {
+                    info: *info,
+                    pixel_bytes: &buffer,
+                };
+
+                apply(index, &image, transform)?.layout()
+            }
+        };
+
+        layout.push(image_layout);
+    }
+
+    let sizes = infos.iter().map(ImageInfo::size);
+    check_transformed_layout(header, &raw_chunks, &layout, sizes)?;
+
+    let mut writer =
+        StreamingWriter::with_interleaved_chunks(writer, header, &raw_chunks, &positions, layout)?;
+
+    for (index, info) in infos.iter().enumerate() {
+        reader.read_pixels(info, &mut buffer)?;
+
+        let image = ParsedImage {
+            info: *info,
+            pixel_bytes: &buffer,
+        };
+
+        let output_image = apply(index, &image, transform)?;
+        writer.write_image(&output_image)?;
+        pool::give(output_image.into_pixels());
+    }
+
+    writer.finish()
+}
+
+/// Splits chunks into images, which are transformed, and other chunks,
+/// which are copied as they are. Also returns how many images come before
+/// each of the other chunks.
+fn split_chunks<'a>(
+    chunks: impl IntoIterator<Item = impl Into<Chunk<'a>>>,
+) -> (Vec<ParsedImage<'a>>, Vec<RawChunk>, Vec<usize>) {
+    let mut images = Vec::new();
+    let mut raw_chunks = Vec::new();
+    let mut positions = Vec::new();
+
+    for chunk in chunks {
+        let raw_chunk = match chunk.into() {
+            Chunk::Image(image) => {
+                images.push(image);
+                continue;
+            }
+            Chunk::Comment(comment) => comment.to_raw_chunk(),
+            Chunk::Unknown(chunk) => chunk.to_raw_chunk(),
+        };
+
+        raw_chunks.push(raw_chunk);
+        positions.push(images.len());
+    }
+
+    (images, raw_chunks, positions)
+}
+
+/// Returns how many images come before each chunk that isn't an image in
+/// a table of contents.
+fn raw_chunk_positions(table_of_contents: &[TocEntry]) -> Vec<usize> {
+    let mut images = 0;
+    let mut positions = Vec::new();
+
+    for entry in table_of_contents {
+        match entry.entry_type == Image::TYPE {
+            true => images += 1,
+            false => positions.push(images),
+        }
+    }
+
+    positions
+}
+
+/// Returns the layout of each image once `transform` is applied to it, or
+/// `None` if that depends on any image's pixels.
+fn transformed_layout(
+    images: &[ParsedImage<'_>],
+    transform: &dyn Transform,
+) -> Result<Option<Vec<ImageLayout>>> {
+    images
+        .iter()
+        .enumerate()
+        .map(|(index, image)| {
+            transform
+                .layout(image.info.layout())
+                .map_err(|err| Error::in_image(index, image.info.size(), err))
+        })
+        .collect()
+}
+
+/// The number of images that [`transform_chunks_to`] transforms at once.
+fn batch_size() -> usize {
+    #[cfg(feature = "parallel")]
+    return rayon::current_num_threads();
+    #[cfg(not(feature = "parallel"))]
+    return 1;
+}
+
+/// Like [`check_layout`], but errors name each image by the nominal size it
+/// had before it was transformed, from `sizes`.
+fn check_transformed_layout(
+    header: FileHeader,
+    raw_chunks: &[RawChunk],
+    layout: &[ImageLayout],
+    sizes: impl IntoIterator<Item = u32>,
+) -> Result<()> {
+    match check_layout(header, raw_chunks, layout) {
+        Err(Error::InImage { index, error, .. }) => Err(Error::InImage {
+            index,
+            size: sizes.into_iter().nth(index).unwrap_or_default(),
+            error,
+        }),
+        res => res,
+    }
+}
+
+/// Applies `transform` to each image, in parallel with the `parallel` feature.
+/// `first_index` is the index of the first image among the cursor's images.
+fn apply_all(
+    first_index: usize,
+    images: &[ParsedImage<'_>],
+    transform: &dyn Transform,
+) -> Vec<Result<Image>> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        // Rayon's threads don't inherit the current span
+        let span = tracing::Span::current();
+
+        images
+            .par_iter()
+            .enumerate()
+            .map(|(index, image)| span.in_scope(|| apply(first_index + index, image, transform)))
+            .collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    images
+        .iter()
+        .enumerate()
+        .map(|(index, image)| apply(first_index + index, image, transform))
+        .collect()
+}
+
+/// Applies `transform` to a single image, inside a span describing it.
+/// Errors are wrapped in an [`Error::InImage`] with the image's `index`.
+fn apply(index: usize, image: &ParsedImage<'_>, transform: &dyn Transform) -> Result<Image> {
+    let _span = debug_span!(
+        "image",
+        position = image.info.position,
+        size = image.info.size(),
+        width = image.info.width,
+        height = image.info.height,
+    )
+    .entered();
+
+    let output_image = image
+        .to_image()
+        .and_then(|image| transform.apply(image))
+        .map_err(|err| Error::in_image(index, image.info.size(), err))?;
+
+    debug!(
+        size = output_image.size(),
+        width = output_image.width(),
+        height = output_image.height(),
+        "transformed image"
+    );
+
+    Ok(output_image)
+}
@@ -0,0 +1,81 @@
+//! Implementation of `validate`.
+
+use crate::{
+    input,
+    lint::{self, Report, ReportFormat},
+};
+use anyhow::{bail, Context};
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub(crate) struct ValidateArgs {
+    /// One or more Xcursor files to check.
+    #[clap(value_parser, required = true)]
+    cursors: Vec<PathBuf>,
+
+    /// Also checks that each file is written back out byte for byte after
+    /// it's read, as it is when resizing with a scale of 1. Files that
+    /// would change fail, with where they first change.
+    #[clap(long, verbatim_doc_comment)]
+    round_trip: bool,
+
+    /// How to print what's found. `json` prints a single report with a
+    /// stable rule id for each problem, for tools such as CI checks.
+    #[clap(long, value_enum, default_value = "text", verbatim_doc_comment)]
+    format: ReportFormat,
+}
+
+pub(crate) fn run(args: ValidateArgs) -> anyhow::Result<()> {
+    let mut findings = Vec::new();
+
+    for path in &args.cursors {
+        let bytes =
+            input::read(path).with_context(|| format!("couldn't read {}", path.display()))?;
+        let cursor = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        for finding in lint::check_cursor(path, &cursor, &bytes, args.round_trip) {
+            if args.format == ReportFormat::Text {
+                finding.print();
+            }
+
+            findings.push(finding);
+        }
+    }
+
+    let report = Report::new(args.cursors.len(), findings);
+
+    if args.format == ReportFormat::Json {
+        report.print_json();
+    }
+
+    let failures = report.failed_files();
+
+    if failures > 0 {
+        bail!(
+            "{} of {} files aren't valid Xcursor files{}",
+            failures,
+            args.cursors.len(),
+            match args.round_trip {
+                true => ", or change when rewritten",
+                false => "",
+            }
+        );
+    }
+
+    if args.format == ReportFormat::Text {
+        println!(
+            "all {} files are valid{}",
+            args.cursors.len(),
+            match args.round_trip {
+                true => ", and are rewritten byte for byte",
+                false => "",
+            }
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1411 @@
+//! Strict checks of an Xcursor file's structure, and repairs for files
+//! that fail them.
+//!
+//! [`XcursorReader`](crate::XcursorReader) accepts some files that don't
+//! quite follow the format, such as ones with overlapping chunks or junk
+//! after the last chunk, since libXcursor loads them too. Other tools can
+//! be pickier, so [`validate`] reports everything that isn't exactly right.
+//! [`repair`] reads files with some of these problems, including some that
+//! can't be read at all otherwise, so that they can be written out again.
+//! [`round_trip`] checks that rewriting a file doesn't change it.
+
+use crate::{
+    read_xcursor::{pixels_from_bytes, ChunkHeader, ParsedComment},
+    Chunk, Error, FileHeader, Image, ImageLayout, Limits, ParseErrorKind, RawChunk, Result,
+    TocEntry, XcursorBuilder, XcursorReader,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Range,
+};
+
+const XCURSOR_MAGIC: &[u8] = b"Xcur";
+const FILE_HEADER_SIZE: u32 = 16;
+
+/// The longest delay, in milliseconds, that [`unusual_delays`] doesn't
+/// report. Animations with longer frames look like they've frozen.
+pub const MAX_USUAL_DELAY: u32 = 10_000;
+
+/// The largest nominal size that most toolkits and cursor themes handle.
+/// Larger cursors may be ignored, or may be cut off.
+pub const MAX_USUAL_SIZE: u32 = 256;
+
+/// A way that an Xcursor file breaks the format, as found by [`validate`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+#[error("{kind} (at byte {offset})")]
+pub struct Violation {
+    /// The offset into the file where the problem was found.
+    pub offset: u64,
+    pub kind: ViolationKind,
+}
+
+/// The ways that [`validate`] can find an Xcursor file to be broken.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ViolationKind {
+    #[error("file doesn't start with `Xcur`")]
+    BadMagic,
+
+    #[error("file header size is {0}, which is smaller than the minimum of 16")]
+    BadFileHeaderSize(u32),
+
+    #[error("{0} unused bytes at the end of the file header")]
+    UnusedHeaderBytes(u32),
+
+    #[error("file is only {0} bytes long, too short for a file header")]
+    MissingFileHeader(u64),
+
+    #[error("table of contents has {count} entries, which don't fit in the file")]
+    TocOutOfBounds { count: u32 },
+
+    #[error("table of contents entry {index} points into the file header or table of contents")]
+    ChunkInHeader { index: usize },
+
+    #[error("table of contents entry {index} points past the end of the file")]
+    ChunkOutOfBounds { index: usize },
+
+    #[error("table of contents entry {index} comes before the chunk of the entry preceding it")]
+    TocUnsorted { index: usize },
+
+    #[error(
+        "chunk {index} has type {found:#010x} and subtype {found_subtype}, \
+        but its table of contents entry says type {expected:#010x} and subtype {expected_subtype}"
+    )]
+    ChunkMismatch {
+        index: usize,
+        expected: u32,
+        expected_subtype: u32,
+        found: u32,
+        found_subtype: u32,
+    },
+
+    #[error("chunk {index} has a header size of {found}, but should have {expected}")]
+    BadChunkHeaderSize {
+        index: usize,
+        expected: u32,
+        found: u32,
+    },
+
+    #[error("chunk {index} has version {found}, but should have version {expected}")]
+    BadChunkVersion {
+        index: usize,
+        expected: u32,
+        found: u32,
+    },
+
+    #[error(
+        "image chunk {index} is {width}x{height}, which is larger than the maximum of {max}x{max}",
+        max = Image::MAX_DIMENSION
+    )]
+    ImageTooLarge {
+        index: usize,
+        width: u32,
+        height: u32,
+    },
+
+    #[error("image chunk {index}'s hotspot ({xhot},{yhot}) is outside of it ({width}x{height})")]
+    HotspotOutOfBounds {
+        index: usize,
+        xhot: u32,
+        yhot: u32,
+        width: u32,
+        height: u32,
+    },
+
+    #[error("chunk {index} is {declared} bytes long, but the file ends {available} bytes after it starts")]
+    ChunkTruncated {
+        index: usize,
+        declared: u64,
+        available: u64,
+    },
+
+    #[error("chunk {index} overlaps chunk {other}")]
+    ChunksOverlap { index: usize, other: usize },
+
+    #[error("{count} unused bytes after chunk {index}")]
+    UnusedBytes { index: usize, count: u64 },
+
+    #[error("{0} unused bytes after the last chunk")]
+    TrailingBytes(u64),
+
+    #[error("the images with a nominal size of {} are actually {}px", .0.size, .0.actual_size)]
+    SizeMismatch(SizeMismatch),
+
+    #[error(
+        "the images with a nominal size of {size} are split into {groups} separate groups, \
+        which libXcursor plays as a single animation"
+    )]
+    DuplicateSize { size: u32, groups: usize },
+}
+
+/// A nominal size whose images have different dimensions, as found by
+/// [`size_mismatches`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SizeMismatch {
+    /// The nominal size the images claim to have.
+    pub size: u32,
+    /// The largest width or height of any image with this nominal size.
+    pub actual_size: u32,
+}
+
+/// Finds the nominal sizes whose images don't match their dimensions, such
+/// as 32x32 images that claim to be 24 pixels, in order of nominal size.
+///
+/// Each nominal size is compared to the larger dimension of its largest
+/// image, since an animation's frames can be trimmed to be smaller than
+/// its nominal size. Images without any pixels are ignored. Resizing these
+/// images by their nominal size makes the mismatch larger.
+///
+/// ```
+/// use resize_xcursor::{validation::{size_mismatches, SizeMismatch}, ImageLayout};
+///
+/// let layouts = [(24, 32, 32), (32, 32, 32), (48, 40, 48)].map(|(size, width, height)| {
+///     ImageLayout { size, width, height }
+/// });
+///
+/// assert_eq!(
+///     size_mismatches(layouts),
+///     [SizeMismatch { size: 24, actual_size: 32 }]
+/// );
+/// ```
+pub fn size_mismatches(layouts: impl IntoIterator<Item = ImageLayout>) -> Vec<SizeMismatch> {
+    let mut actual_sizes = BTreeMap::<u32, u32>::new();
+
+    for layout in layouts {
+        let actual_size = actual_sizes.entry(layout.size).or_default();
+        *actual_size = (*actual_size).max(layout.width.max(layout.height));
+    }
+
+    actual_sizes
+        .into_iter()
+        .filter(|&(size, actual_size)| actual_size != size && actual_size != 0)
+        .map(|(size, actual_size)| SizeMismatch { size, actual_size })
+        .collect()
+}
+
+/// An image whose dimensions are allowed by the format, but that can look
+/// wrong once it's scaled, as found by [`unusual_geometries`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, thiserror::Error, Serialize, Deserialize)]
+#[error("image {index} ({width}x{height}, with a nominal size of {size}) {kind}")]
+pub struct UnusualGeometry {
+    /// The image's index among the cursor's images, starting from 0.
+    pub index: usize,
+    pub size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub kind: GeometryKind,
+}
+
+/// The ways that [`unusual_geometries`] can find an image to be unusual.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, thiserror::Error, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum GeometryKind {
+    /// The image is wider than it is tall, or taller than it is wide.
+    #[error("isn't square")]
+    NotSquare,
+    /// The image is wider or taller than its nominal size.
+    #[error("is larger than its nominal size")]
+    LargerThanNominal,
+    /// The image has a width or height of 0.
+    #[error("has no pixels")]
+    Empty,
+}
+
+/// Finds the images with unusual dimensions, in order. An image can be
+/// unusual in more than one way, but images without any pixels are only
+/// reported as [`GeometryKind::Empty`].
+///
+/// ```
+/// use resize_xcursor::{validation::{unusual_geometries, GeometryKind}, ImageLayout};
+///
+/// let layouts = [(24, 24, 24), (24, 24, 20), (16, 24, 24), (32, 0, 0)].map(|(size, width, height)| {
+///     ImageLayout { size, width, height }
+/// });
+///
+/// let kinds = unusual_geometries(layouts).into_iter().map(|geometry| geometry.kind);
+/// assert!(kinds.eq([
+///     GeometryKind::NotSquare,
+///     GeometryKind::LargerThanNominal,
+///     GeometryKind::Empty,
+/// ]));
+/// ```
+pub fn unusual_geometries(layouts: impl IntoIterator<Item = ImageLayout>) -> Vec<UnusualGeometry> {
+    let mut geometries = Vec::new();
+
+    for (index, layout) in layouts.into_iter().enumerate() {
+        let mut unusual = |kind| {
+            geometries.push(UnusualGeometry {
+                index,
+                size: layout.size,
+                width: layout.width,
+                height: layout.height,
+                kind,
+            })
+        };
+
+        if layout.width == 0 || layout.height == 0 {
+            unusual(GeometryKind::Empty);
+            continue;
+        }
+
+        if layout.width != layout.height {
+            unusual(GeometryKind::NotSquare);
+        }
+
+        if layout.width.max(layout.height) > layout.size {
+            unusual(GeometryKind::LargerThanNominal);
+        }
+    }
+
+    geometries
+}
+
+/// A frame of an animation whose delay is likely to cause problems, as found
+/// by [`unusual_delays`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, thiserror::Error, Serialize, Deserialize)]
+#[error("image {index} (with a nominal size of {size}) has a delay of {delay}ms, {kind}")]
+pub struct UnusualDelay {
+    /// The image's index among the cursor's images, starting from 0.
+    pub index: usize,
+    pub size: u32,
+    pub delay: u32,
+    pub kind: DelayKind,
+}
+
+/// The ways that [`unusual_delays`] can find a delay to be unusual.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, thiserror::Error, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum DelayKind {
+    /// The frame has no delay, so compositors redraw the cursor as fast as
+    /// they can.
+    #[error("which makes the animation run as fast as it can")]
+    Zero,
+    /// The frame's delay is longer than [`MAX_USUAL_DELAY`].
+    #[error("which makes the animation look frozen")]
+    TooLong,
+}
+
+/// Finds the frames of animations whose delay is 0 or longer than
+/// [`MAX_USUAL_DELAY`], given the nominal size and delay of each image.
+///
+/// Images that are the only one with their nominal size aren't animated, so
+/// their delays are never used and aren't reported.
+///
+/// ```
+/// use resize_xcursor::validation::{unusual_delays, DelayKind};
+///
+/// let frames = [(24, 0), (32, 50), (32, 0), (48, 60_000), (48, 50)];
+/// let delays = unusual_delays(frames).into_iter().map(|delay| (delay.index, delay.kind));
+/// assert!(delays.eq([(2, DelayKind::Zero), (3, DelayKind::TooLong)]));
+/// ```
+pub fn unusual_delays(frames: impl IntoIterator<Item = (u32, u32)>) -> Vec<UnusualDelay> {
+    let frames = frames.into_iter().collect::<Vec<_>>();
+    let mut frame_counts = BTreeMap::<u32, usize>::new();
+
+    for &(size, _) in &frames {
+        *frame_counts.entry(size).or_default() += 1;
+    }
+
+    frames
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, (size, _))| frame_counts[&size] > 1)
+        .filter_map(|(index, (size, delay))| {
+            let kind = match delay {
+                0 => DelayKind::Zero,
+                _ if delay > MAX_USUAL_DELAY => DelayKind::TooLong,
+                _ => return None,
+            };
+
+            Some(UnusualDelay {
+                index,
+                size,
+                delay,
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// A nominal size whose images are split into separate groups, with images
+/// of other nominal sizes between them, as found by [`duplicate_sizes`].
+///
+/// This usually means that a tool added the same size twice. libXcursor
+/// loads every image with the nominal size it picks, so each group ends up
+/// as part of one animation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DuplicateSize {
+    pub size: u32,
+    /// The indices of the images in each group, in order.
+    pub groups: Vec<Range<usize>>,
+}
+
+/// Which group of images [`dedupe_sizes`] keeps for each [`DuplicateSize`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum KeepDuplicate {
+    /// Keeps the group that comes first.
+    First,
+    /// Keeps the group that comes last.
+    Last,
+    /// Keeps the group with the largest image, by area. If several groups
+    /// are just as large, the first of them is kept.
+    Largest,
+}
+
+/// Finds the nominal sizes whose images are split into separate groups, in
+/// order of nominal size.
+///
+/// ```
+/// use resize_xcursor::{validation::{duplicate_sizes, DuplicateSize}, ImageLayout};
+///
+/// let layouts = [24, 32, 24, 24].map(|size| ImageLayout { size, width: size, height: size });
+///
+/// assert_eq!(
+///     duplicate_sizes(layouts),
+///     [DuplicateSize { size: 24, groups: vec![0..1, 2..4] }]
+/// );
+/// ```
+pub fn duplicate_sizes(layouts: impl IntoIterator<Item = ImageLayout>) -> Vec<DuplicateSize> {
+    let mut groups = BTreeMap::<u32, Vec<Range<usize>>>::new();
+    let mut previous_size = None;
+
+    for (index, layout) in layouts.into_iter().enumerate() {
+        let size_groups = groups.entry(layout.size).or_default();
+
+        match size_groups.last_mut() {
+            Some(group) if previous_size == Some(layout.size) => group.end = index + 1,
+            _ => size_groups.push(index..index + 1),
+        }
+
+        previous_size = Some(layout.size);
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, groups)| groups.len() > 1)
+        .map(|(size, groups)| DuplicateSize { size, groups })
+        .collect()
+}
+
+/// Removes every group of images but one for each [`DuplicateSize`] in a
+/// cursor, returning the duplicates that were found.
+pub fn dedupe_sizes(cursor: &mut XcursorBuilder, keep: KeepDuplicate) -> Vec<DuplicateSize> {
+    let layouts = cursor
+        .images()
+        .iter()
+        .map(Image::layout)
+        .collect::<Vec<_>>();
+    let (duplicates, dropped) = duplicate_images(&layouts, keep);
+
+    if dropped.is_empty() {
+        return duplicates;
+    }
+
+    let images = cursor
+        .images()
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !dropped.contains(index))
+        .map(|(_, image)| image.clone())
+        .collect::<Vec<_>>();
+
+    for size in cursor.sizes() {
+        cursor.remove_size(size);
+    }
+
+    for image in images {
+        cursor
+            .add_image(image)
+            .expect("removing images shouldn't stop the rest from being added back");
+    }
+
+    duplicates
+}
+
+/// Like [`dedupe_sizes`], but for chunks that are about to be resized.
+/// Chunks other than images are kept.
+pub fn dedupe_chunks<'a>(
+    cursor_chunks: impl IntoIterator<Item = Chunk<'a>>,
+    keep: KeepDuplicate,
+) -> Vec<Chunk<'a>> {
+    let cursor_chunks = cursor_chunks.into_iter().collect::<Vec<_>>();
+    let layouts = cursor_chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            Chunk::Image(image) => Some(image.info.layout()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let (_, dropped) = duplicate_images(&layouts, keep);
+    let mut image_index = 0;
+
+    cursor_chunks
+        .into_iter()
+        .filter(|chunk| {
+            if !matches!(chunk, Chunk::Image(_)) {
+                return true;
+            }
+
+            image_index += 1;
+            !dropped.contains(&(image_index - 1))
+        })
+        .collect()
+}
+
+/// Finds the duplicate sizes among `layouts`, along with the indices of the
+/// images that `keep` throws away.
+fn duplicate_images(
+    layouts: &[ImageLayout],
+    keep: KeepDuplicate,
+) -> (Vec<DuplicateSize>, HashSet<usize>) {
+    let duplicates = duplicate_sizes(layouts.iter().copied());
+    let mut dropped = HashSet::new();
+
+    for duplicate in &duplicates {
+        let area = |group: &Range<usize>| {
+            layouts[group.clone()]
+                .iter()
+                .map(|layout| u64::from(layout.width) * u64::from(layout.height))
+                .max()
+        };
+
+        // `max_by_key` picks the last of equal groups, so the groups are
+        // searched backwards to keep the first
+        let kept = match keep {
+            KeepDuplicate::First => duplicate.groups.first(),
+            KeepDuplicate::Last => duplicate.groups.last(),
+            KeepDuplicate::Largest => duplicate
+                .groups
+                .iter()
+                .rev()
+                .max_by_key(|group| area(group)),
+        };
+
+        for group in &duplicate.groups {
+            if Some(group) != kept {
+                dropped.extend(group.clone());
+            }
+        }
+    }
+
+    (duplicates, dropped)
+}
+
+/// Checks the structure of an Xcursor file, returning every violation of the
+/// format that was found, in the order that they appear in the file.
+///
+/// An empty list means that the file is valid. Pixels themselves aren't
+/// checked, since any pixel is valid.
+///
+/// ```
+/// use resize_xcursor::{validation::{validate, ViolationKind}, Image, XcursorBuilder};
+///
+/// let mut cursor = XcursorBuilder::new();
+/// cursor.add_image(Image::new(1, 1, 1, 0, 0, 0, vec![0])?)?;
+///
+/// let mut bytes = Vec::new();
+/// cursor.write_to(&mut bytes)?;
+/// assert!(validate(&bytes).is_empty());
+///
+/// bytes.extend([0; 3]);
+/// assert_eq!(validate(&bytes)[0].kind, ViolationKind::TrailingBytes(3));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn validate(bytes: &[u8]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut violation = |offset: u64, kind| violations.push(Violation { offset, kind });
+    let file_length = bytes.len() as u64;
+
+    if !bytes.starts_with(XCURSOR_MAGIC) {
+        violation(0, ViolationKind::BadMagic);
+        return violations;
+    }
+
+    let (Some(header_size), Some(toc_length)) = (field(bytes, 4), field(bytes, 12)) else {
+        violation(0, ViolationKind::MissingFileHeader(file_length));
+        return violations;
+    };
+
+    let toc_start = match header_size {
+        // The rest of the file can still be checked by assuming that the
+        // header is the usual size
+        ..FILE_HEADER_SIZE => {
+            violation(4, ViolationKind::BadFileHeaderSize(header_size));
+            u64::from(FILE_HEADER_SIZE)
+        }
+        FILE_HEADER_SIZE => u64::from(header_size),
+        _ => {
+            violation(
+                u64::from(FILE_HEADER_SIZE),
+                ViolationKind::UnusedHeaderBytes(header_size - FILE_HEADER_SIZE),
+            );
+            u64::from(header_size)
+        }
+    };
+
+    let toc_end = toc_start + u64::from(toc_length) * u64::from(TocEntry::BYTE_LENGTH);
+
+    if toc_end > file_length {
+        violation(12, ViolationKind::TocOutOfBounds { count: toc_length });
+        return violations;
+    }
+
+    // The start and end of each chunk that's in bounds, by index
+    let mut extents = Vec::new();
+    let mut previous_position = None;
+    // The position and layout of each image, for checking nominal sizes
+    let mut layouts = Vec::new();
+
+    for index in 0..toc_length as usize {
+        let entry_offset = toc_start + index as u64 * u64::from(TocEntry::BYTE_LENGTH);
+        let toc_field = |offset| field(bytes, entry_offset + offset).unwrap_or(0);
+        let (entry_type, subtype) = (toc_field(0), toc_field(4));
+        let position = u64::from(toc_field(8));
+
+        if position < toc_end {
+            violation(entry_offset + 8, ViolationKind::ChunkInHeader { index });
+            continue;
+        }
+
+        if previous_position.is_some_and(|previous| position <= previous) {
+            violation(entry_offset + 8, ViolationKind::TocUnsorted { index });
+        }
+
+        previous_position = Some(position);
+
+        let header_field = |offset| field(bytes, position + offset);
+
+        let (Some(chunk_header_size), Some(chunk_type), Some(chunk_subtype), Some(version)) = (
+            header_field(0),
+            header_field(4),
+            header_field(8),
+            header_field(12),
+        ) else {
+            violation(entry_offset + 8, ViolationKind::ChunkOutOfBounds { index });
+            continue;
+        };
+
+        if chunk_type != entry_type || chunk_subtype != subtype {
+            violation(
+                position + 4,
+                ViolationKind::ChunkMismatch {
+                    index,
+                    expected: entry_type,
+                    expected_subtype: subtype,
+                    found: chunk_type,
+                    found_subtype: chunk_subtype,
+                },
+            );
+        }
+
+        // Only images and comments have a known layout
+        let (expected_header_size, expected_version, length) = match chunk_type {
+            Image::TYPE => {
+                let (width, height) = (header_field(16), header_field(20));
+
+                if let (Some(width), Some(height)) = (width, height) {
+                    let layout = ImageLayout {
+                        size: chunk_subtype,
+                        width,
+                        height,
+                    };
+                    layouts.push((position, layout));
+
+                    if width > Image::MAX_DIMENSION || height > Image::MAX_DIMENSION {
+                        violation(
+                            position + 16,
+                            ViolationKind::ImageTooLarge {
+                                index,
+                                width,
+                                height,
+                            },
+                        );
+                    }
+
+                    if let (Some(xhot), Some(yhot)) = (header_field(24), header_field(28)) {
+                        if xhot >= width || yhot >= height {
+                            violation(
+                                position + 24,
+                                ViolationKind::HotspotOutOfBounds {
+                                    index,
+                                    xhot,
+                                    yhot,
+                                    width,
+                                    height,
+                                },
+                            );
+                        }
+                    }
+                }
+
+                // Saturating, since images can claim to be far larger than
+                // any file
+                let pixels = width.zip(height).map(|(width, height)| {
+                    (u64::from(width) * u64::from(height)).saturating_mul(4)
+                });
+
+                (
+                    Image::HEADER_SIZE,
+                    Image::VERSION,
+                    pixels.map(|pixels| pixels.saturating_add(u64::from(Image::HEADER_SIZE))),
+                )
+            }
+
+            ParsedComment::TYPE => (
+                ParsedComment::HEADER_SIZE,
+                ParsedComment::VERSION,
+                header_field(16).map(|len| u64::from(ParsedComment::HEADER_SIZE) + u64::from(len)),
+            ),
+
+            _ => {
+                extents.push((index, position, None));
+                continue;
+            }
+        };
+
+        if chunk_header_size != expected_header_size {
+            violation(
+                position,
+                ViolationKind::BadChunkHeaderSize {
+                    index,
+                    expected: expected_header_size,
+                    found: chunk_header_size,
+                },
+            );
+        }
+
+        if version != expected_version {
+            violation(
+                position + 12,
+                ViolationKind::BadChunkVersion {
+                    index,
+                    expected: expected_version,
+                    found: version,
+                },
+            );
+        }
+
+        // A chunk whose length field is missing is as truncated as one too
+        // short for its pixels or text
+        let declared = length.unwrap_or(u64::from(expected_header_size));
+        let available = file_length - position;
+
+        if declared > available {
+            violation(
+                position,
+                ViolationKind::ChunkTruncated {
+                    index,
+                    declared,
+                    available,
+                },
+            );
+        }
+
+        extents.push((index, position, Some(position.saturating_add(declared))));
+    }
+
+    // Chunks don't have to be in table of contents order, so overlaps and
+    // gaps are found in the order the chunks appear in the file
+    extents.sort_by_key(|&(index, position, _)| (position, index));
+
+    for pair in extents.windows(2) {
+        let [(index, _, end), (other, next_position, _)] = *pair else {
+            unreachable!("windows should have two chunks");
+        };
+
+        match end {
+            Some(end) if end > next_position => {
+                violation(next_position, ViolationKind::ChunksOverlap { index, other });
+            }
+            Some(end) if end < next_position => violation(
+                end,
+                ViolationKind::UnusedBytes {
+                    index,
+                    count: next_position - end,
+                },
+            ),
+            _ => {}
+        }
+    }
+
+    let end = match extents.last() {
+        Some(&(_, _, Some(end))) => end,
+        Some(_) => file_length,
+        None => toc_end,
+    };
+
+    if end < file_length {
+        violation(end, ViolationKind::TrailingBytes(file_length - end));
+    }
+
+    for mismatch in size_mismatches(layouts.iter().map(|&(_, layout)| layout)) {
+        // Mismatches are reported at the first image with their size
+        let position = layouts
+            .iter()
+            .find(|(_, layout)| layout.size == mismatch.size)
+            .map_or(0, |&(position, _)| position);
+
+        violation(position + 8, ViolationKind::SizeMismatch(mismatch));
+    }
+
+    for duplicate in duplicate_sizes(layouts.iter().map(|&(_, layout)| layout)) {
+        // Duplicates are reported at the first image of the second group
+        let (position, _) = layouts[duplicate.groups[1].start];
+
+        violation(
+            position + 8,
+            ViolationKind::DuplicateSize {
+                size: duplicate.size,
+                groups: duplicate.groups.len(),
+            },
+        );
+    }
+
+    violations.sort_by_key(|violation| violation.offset);
+    violations
+}
+
+/// Reads an Xcursor file, working around problems that would stop an
+/// [`XcursorReader`](crate::XcursorReader) from reading it, so that it can be
+/// written out again as a valid file.
+///
+/// Table of contents entries that don't point to a chunk of the right type
+/// and subtype are matched up with the first chunk of that type and subtype
+/// found elsewhere in the file, which no other entry points to. A file header
+/// that's too small is assumed to be the usual 16 bytes, and one that's too
+/// large is shortened to them. Header sizes and versions in chunk headers are
+/// ignored, since images and comments always have the same layout, and are
+/// corrected when the cursor is written. Hotspots outside of their image are moved onto the nearest pixel inside of
+/// it, like [`ClampHotspot`](crate::filters::ClampHotspot) does. Bytes that
+/// aren't part of any chunk are left out.
+///
+/// This still fails if the file doesn't start with `Xcur`, if its table of
+/// contents doesn't fit in it, or if a chunk can't be found or is
+/// truncated; [`salvage`] drops those chunks instead. The file is checked
+/// against the default [`Limits`].
+pub fn repair(bytes: &[u8]) -> Result<XcursorBuilder> {
+    read_leniently(bytes, false).map(|salvaged| salvaged.cursor)
+}
+
+/// A cursor recovered from a damaged file by [`salvage`].
+#[non_exhaustive]
+pub struct Salvaged {
+    pub cursor: XcursorBuilder,
+    /// Why each chunk that couldn't be recovered was dropped.
+    pub lost: Vec<Error>,
+}
+
+/// Like [`repair`], but recovers what it can from files that are truncated
+/// or otherwise damaged, instead of failing.
+///
+/// Chunks that can't be found, or that are cut off, are dropped, as are
+/// table of contents entries past the end of the file. This only fails if
+/// the file can't be recognized as an Xcursor file, or if none of its
+/// images could be recovered.
+///
+/// ```
+/// use resize_xcursor::{validation::salvage, Image, XcursorBuilder};
+///
+/// let mut cursor = XcursorBuilder::new();
+/// cursor.add_image(Image::new(1, 1, 1, 0, 0, 0, vec![0])?)?;
+/// cursor.add_image(Image::new(2, 2, 2, 0, 0, 0, vec![0; 4])?)?;
+///
+/// let mut bytes = Vec::new();
+/// cursor.write_to(&mut bytes)?;
+/// bytes.truncate(bytes.len() - 1);
+///
+/// let salvaged = salvage(&bytes)?;
+/// assert_eq!(salvaged.cursor.sizes(), [1]);
+/// assert_eq!(salvaged.lost.len(), 1);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn salvage(bytes: &[u8]) -> Result<Salvaged> {
+    read_leniently(bytes, true)
+}
+
+/// Where rewriting an Xcursor file first changes it, as found by
+/// [`round_trip`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+#[error("{kind} (at byte {offset})")]
+pub struct Divergence {
+    /// The offset into the original file where the rewritten file first
+    /// differs from it.
+    pub offset: u64,
+    pub kind: DivergenceKind,
+}
+
+/// The ways that [`round_trip`] can find a rewritten file to differ.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum DivergenceKind {
+    #[error("file header changes")]
+    FileHeader,
+
+    #[error("table of contents entry {index} changes")]
+    TocEntry { index: usize },
+
+    /// A byte in the chunk that the given table of contents entry points
+    /// to changes, or a byte between it and the next chunk.
+    #[error("chunk {index} changes")]
+    Chunk { index: usize },
+
+    #[error("file is {original} bytes long, but {rewritten} bytes once rewritten")]
+    Length { original: u64, rewritten: u64 },
+}
+
+/// Parses an Xcursor file and writes it out again with [`XcursorBuilder`],
+/// returning where the rewritten file first differs from the original, if
+/// it does at all.
+///
+/// The file header and every chunk are kept, so files written by
+/// libXcursor, `xcursorgen`, or this crate are rewritten byte for byte.
+/// Files with unused bytes between chunks, unused bytes in the file header
+/// that aren't zero, or chunk headers with unusual sizes or versions, are
+/// changed by rewriting them.
+///
+/// ```
+/// use resize_xcursor::{
+///     read_xcursor::CommentKind,
+///     validation::{round_trip, DivergenceKind},
+///     Image, XcursorBuilder,
+/// };
+///
+/// let mut cursor = XcursorBuilder::new();
+/// cursor.add_comment(CommentKind::Copyright, "(c) someone")?;
+/// cursor.add_image(Image::new(1, 1, 1, 0, 0, 0, vec![0xFF000000])?)?;
+///
+/// let mut bytes = Vec::new();
+/// cursor.write_to(&mut bytes)?;
+/// assert_eq!(round_trip(&bytes)?, None);
+///
+/// bytes.push(0);
+/// let divergence = round_trip(&bytes)?.unwrap();
+/// assert!(matches!(divergence.kind, DivergenceKind::Length { .. }));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn round_trip(bytes: &[u8]) -> Result<Option<Divergence>> {
+    let reader = XcursorReader::new(bytes)?;
+    let mut rewritten = Vec::new();
+    XcursorBuilder::parse(bytes)?.write_to(&mut rewritten)?;
+
+    let toc = reader.table_of_contents();
+    let header_size = u64::from(reader.header().header_size);
+    let toc_entry_offset =
+        |index: usize| header_size + index as u64 * u64::from(TocEntry::BYTE_LENGTH);
+
+    let Some(offset) = bytes
+        .iter()
+        .zip(&rewritten)
+        .position(|(original, rewritten)| original != rewritten)
+    else {
+        if bytes.len() == rewritten.len() {
+            return Ok(None);
+        }
+
+        return Ok(Some(Divergence {
+            offset: bytes.len().min(rewritten.len()) as u64,
+            kind: DivergenceKind::Length {
+                original: bytes.len() as u64,
+                rewritten: rewritten.len() as u64,
+            },
+        }));
+    };
+
+    let offset = offset as u64;
+    let kind = if offset < header_size {
+        DivergenceKind::FileHeader
+    } else if offset < toc_entry_offset(toc.len()) {
+        DivergenceKind::TocEntry {
+            index: ((offset - header_size) / u64::from(TocEntry::BYTE_LENGTH)) as usize,
+        }
+    } else {
+        // Chunks can be in any order, so this finds the closest one that
+        // starts before the offset
+        let index = toc
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| u64::from(entry.position) <= offset)
+            .max_by_key(|(_, entry)| entry.position)
+            .map_or(0, |(index, _)| index);
+
+        DivergenceKind::Chunk { index }
+    };
+
+    Ok(Some(Divergence { offset, kind }))
+}
+
+/// The implementation of [`repair`] and [`salvage`], which only drops
+/// chunks if `salvage` is true.
+fn read_leniently(bytes: &[u8], salvage: bool) -> Result<Salvaged> {
+    let file_length = bytes.len() as u64;
+    let limits = Limits::default();
+    let read = |offset: u64, what| {
+        field(bytes, offset).ok_or_else(|| {
+            parse_error(
+                to_offset(offset.min(file_length)),
+                ParseErrorKind::UnexpectedEof(what),
+            )
+        })
+    };
+
+    let mut lost = Vec::new();
+
+    // Problems with individual chunks are only fatal when not salvaging
+    let mut lose = |err: Error| match err {
+        Error::Parse { .. } if salvage => {
+            lost.push(err);
+            Ok(())
+        }
+        err => Err(err),
+    };
+
+    if !bytes.starts_with(XCURSOR_MAGIC) {
+        return Err(parse_error(0, ParseErrorKind::BadMagic));
+    }
+
+    let header_size = read(4, "the file header")?;
+    let version = read(8, "the file header")?;
+    let mut toc_length = read(12, "the file header")?;
+
+    // Real files never have longer headers, so a header that doesn't fit
+    // has the wrong size too
+    let toc_start = match header_size {
+        FILE_HEADER_SIZE.. if u64::from(header_size) <= file_length => u64::from(header_size),
+        _ => u64::from(FILE_HEADER_SIZE),
+    };
+
+    if toc_length > limits.max_chunks {
+        return Err(parse_error(
+            12,
+            ParseErrorKind::TooManyChunks {
+                count: toc_length,
+                max: limits.max_chunks,
+            },
+        ));
+    }
+
+    let entries_in_file = (file_length - toc_start) / u64::from(TocEntry::BYTE_LENGTH);
+
+    if u64::from(toc_length) > entries_in_file {
+        lose(parse_error(
+            bytes.len(),
+            ParseErrorKind::UnexpectedEof("the table of contents"),
+        ))?;
+
+        toc_length = entries_in_file as u32;
+    }
+
+    let toc_end = toc_start + u64::from(toc_length) * u64::from(TocEntry::BYTE_LENGTH);
+
+    let table_of_contents = (0..u64::from(toc_length))
+        .map(|index| {
+            let offset = toc_start + index * u64::from(TocEntry::BYTE_LENGTH);
+
+            Ok(TocEntry {
+                entry_type: read(offset, "the table of contents")?,
+                subtype: read(offset + 4, "the table of contents")?,
+                position: read(offset + 8, "the table of contents")?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let is_chunk = |position: u64, toc_entry: &TocEntry| {
+        position >= toc_end
+            && field(bytes, position + 4) == Some(toc_entry.entry_type)
+            && field(bytes, position + 8) == Some(toc_entry.subtype)
+    };
+
+    // Entries that point to the right kind of chunk are trusted first, so
+    // that lost entries can only be matched with chunks nothing points to
+    let mut positions = table_of_contents
+        .iter()
+        .map(|toc_entry| {
+            let position = u64::from(toc_entry.position);
+            is_chunk(position, toc_entry).then_some(position)
+        })
+        .collect::<Vec<_>>();
+
+    let mut claimed = positions.iter().flatten().copied().collect::<HashSet<_>>();
+
+    for (index, toc_entry) in table_of_contents.iter().enumerate() {
+        if positions[index].is_some() {
+            continue;
+        }
+
+        // Comments don't have to be a multiple of 4 bytes long, so chunks
+        // can start at any offset
+        let found = (toc_end..file_length.saturating_sub(15))
+            .find(|&position| !claimed.contains(&position) && is_chunk(position, toc_entry));
+
+        match found {
+            Some(position) => {
+                claimed.insert(position);
+                positions[index] = Some(position);
+            }
+
+            None => lose(parse_error(
+                to_offset(u64::from(toc_entry.position)),
+                ParseErrorKind::ChunkNotFound { index },
+            ))?,
+        }
+    }
+
+    let mut cursor = XcursorBuilder::new();
+    cursor.file_header(FileHeader {
+        header_size: FILE_HEADER_SIZE,
+        version,
+    })?;
+
+    for (index, (toc_entry, position)) in table_of_contents.iter().zip(positions).enumerate() {
+        // Chunks that weren't found have already been lost
+        let Some(position) = position else {
+            continue;
+        };
+
+        let res =
+            read_chunk(bytes, index, toc_entry, position, &claimed, limits).and_then(|chunk| {
+                match chunk {
+                    LenientChunk::Image(image) => cursor.add_image(image),
+                    LenientChunk::Raw(chunk) => cursor.add_raw_chunk(chunk),
+                }
+            });
+
+        if let Err(err) = res {
+            lose(err)?;
+        }
+    }
+
+    if cursor.images().is_empty() && !lost.is_empty() {
+        return Err(lost.swap_remove(0));
+    }
+
+    Ok(Salvaged { cursor, lost })
+}
+
+/// A chunk read by [`read_chunk`].
+enum LenientChunk {
+    Image(Image),
+    Raw(RawChunk),
+}
+
+/// Reads the chunk for the table of contents entry at `index`, which was
+/// found at `position`. Unknown chunks are assumed to extend to the next
+/// chunk in `claimed`, like they are when they're read normally.
+fn read_chunk(
+    bytes: &[u8],
+    index: usize,
+    toc_entry: &TocEntry,
+    position: u64,
+    claimed: &HashSet<u64>,
+    limits: Limits,
+) -> Result<LenientChunk> {
+    let file_length = bytes.len() as u64;
+    let read = |offset: u64, what| {
+        field(bytes, position + offset).ok_or_else(|| {
+            parse_error(
+                to_offset((position + offset).min(file_length)),
+                ParseErrorKind::UnexpectedEof(what),
+            )
+        })
+    };
+
+    match toc_entry.entry_type {
+        Image::TYPE => {
+            let field = |index: u64| read(16 + index * 4, "an image");
+            let (width, height) = (field(0)?, field(1)?);
+
+            if width > limits.max_dimension || height > limits.max_dimension {
+                return Err(parse_error(
+                    to_offset(position + 16),
+                    ParseErrorKind::ImageDimensionsExceeded {
+                        index,
+                        width,
+                        height,
+                        max: limits.max_dimension,
+                    },
+                ));
+            }
+
+            let pixels_start = position + u64::from(Image::HEADER_SIZE);
+            let pixels_end = pixels_start + u64::from(width) * u64::from(height) * 4;
+
+            if pixels_end > file_length {
+                return Err(parse_error(
+                    bytes.len(),
+                    ParseErrorKind::UnexpectedEof("an image's pixels"),
+                ));
+            }
+
+            // libXcursor won't load images whose hotspot is outside of them
+            Image::new(
+                toc_entry.subtype,
+                width,
+                height,
+                field(2)?.min(width.saturating_sub(1)),
+                field(3)?.min(height.saturating_sub(1)),
+                field(4)?,
+                pixels_from_bytes(&bytes[pixels_start as usize..pixels_end as usize]).collect(),
+            )
+            .map(LenientChunk::Image)
+        }
+
+        ParsedComment::TYPE => {
+            let len = read(16, "a comment")?;
+            let text_start = position + u64::from(ParsedComment::HEADER_SIZE);
+            let text_end = text_start + u64::from(len);
+
+            if text_end > file_length {
+                return Err(parse_error(
+                    bytes.len(),
+                    ParseErrorKind::UnexpectedEof("a comment"),
+                ));
+            }
+
+            let comment = ParsedComment {
+                position: position as u32,
+                header: ChunkHeader {
+                    header_size: ParsedComment::HEADER_SIZE,
+                    chunk_type: ParsedComment::TYPE,
+                    subtype: toc_entry.subtype,
+                    version: ParsedComment::VERSION,
+                },
+                text: &bytes[text_start as usize..text_end as usize],
+            };
+
+            Ok(LenientChunk::Raw(comment.to_raw_chunk()))
+        }
+
+        entry_type => {
+            let end = claimed
+                .iter()
+                .copied()
+                .filter(|&other| other > position)
+                .min()
+                .unwrap_or(file_length);
+
+            Ok(LenientChunk::Raw(RawChunk {
+                entry_type,
+                subtype: toc_entry.subtype,
+                bytes: bytes[position as usize..end as usize].to_vec(),
+            }))
+        }
+    }
+}
+
+fn parse_error(offset: usize, kind: ParseErrorKind) -> Error {
+    Error::Parse { offset, kind }
+}
+
+/// Converts a position in a file into an offset for an [`Error::Parse`].
+fn to_offset(position: u64) -> usize {
+    position.try_into().unwrap_or(usize::MAX)
+}
+
+/// Reads a little-endian `u32` at `offset`, if the file is long enough.
+fn field(bytes: &[u8], offset: u64) -> Option<u32> {
+    let offset = usize::try_from(offset).ok()?;
+    let field = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(field.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Where the first image starts in [`two_frames`], after the file header
+    /// and two table of contents entries.
+    const FIRST_IMAGE: usize = 40;
+    /// Where the second table of contents entry's position is.
+    const SECOND_POSITION: usize = 16 + 12 + 8;
+
+    /// A valid animation with two 1x1 frames, for tests to break.
+    fn two_frames() -> Vec<u8> {
+        let mut cursor = XcursorBuilder::new();
+
+        for pixel in [0xFF000000, 0xFFFFFFFF] {
+            cursor
+                .add_image(Image::new(1, 1, 1, 0, 0, 50, vec![pixel]).unwrap())
+                .unwrap();
+        }
+
+        let mut bytes = Vec::new();
+        cursor.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn set_field(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn kinds(bytes: &[u8]) -> Vec<ViolationKind> {
+        validate(bytes)
+            .into_iter()
+            .map(|violation| violation.kind)
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(kinds(&two_frames()), []);
+    }
+
+    #[test]
+    fn truncated_toc() {
+        let mut bytes = two_frames();
+        bytes.truncate(30);
+        assert_eq!(kinds(&bytes), [ViolationKind::TocOutOfBounds { count: 2 }]);
+
+        let mut bytes = two_frames();
+        set_field(&mut bytes, 12, u32::MAX);
+        assert_eq!(
+            kinds(&bytes),
+            [ViolationKind::TocOutOfBounds { count: u32::MAX }]
+        );
+        assert!(repair(&bytes).is_err());
+    }
+
+    #[test]
+    fn oversized_chunk_header_size() {
+        let mut bytes = two_frames();
+        set_field(&mut bytes, FIRST_IMAGE, u32::MAX);
+
+        assert_eq!(
+            kinds(&bytes),
+            [ViolationKind::BadChunkHeaderSize {
+                index: 0,
+                expected: Image::HEADER_SIZE,
+                found: u32::MAX,
+            }]
+        );
+
+        // The header size is corrected when the cursor is written again
+        let mut repaired = Vec::new();
+        repair(&bytes).unwrap().write_to(&mut repaired).unwrap();
+        assert_eq!(repaired, two_frames());
+    }
+
+    #[test]
+    fn oversized_image() {
+        let mut bytes = two_frames();
+        set_field(&mut bytes, FIRST_IMAGE + 16, u32::MAX);
+        set_field(&mut bytes, FIRST_IMAGE + 20, u32::MAX);
+
+        let violations = validate(&bytes);
+        assert!(violations.iter().any(|violation| violation.kind
+            == ViolationKind::ImageTooLarge {
+                index: 0,
+                width: u32::MAX,
+                height: u32::MAX,
+            }));
+        assert!(violations.iter().any(|violation| violation.kind
+            == ViolationKind::ChunkTruncated {
+                index: 0,
+                declared: u64::MAX,
+                available: (bytes.len() - FIRST_IMAGE) as u64,
+            }));
+
+        // Dropped rather than allocated
+        let salvaged = salvage(&bytes).unwrap();
+        assert_eq!(salvaged.cursor.images().len(), 1);
+        assert_eq!(salvaged.lost.len(), 1);
+    }
+
+    #[test]
+    fn hotspot_out_of_bounds() {
+        let mut bytes = two_frames();
+        set_field(&mut bytes, FIRST_IMAGE + 24, 1);
+        set_field(&mut bytes, FIRST_IMAGE + 28, 5);
+
+        assert_eq!(
+            validate(&bytes),
+            [Violation {
+                offset: FIRST_IMAGE as u64 + 24,
+                kind: ViolationKind::HotspotOutOfBounds {
+                    index: 0,
+                    xhot: 1,
+                    yhot: 5,
+                    width: 1,
+                    height: 1,
+                },
+            }]
+        );
+
+        // The hotspot is moved back onto the image
+        let mut repaired = Vec::new();
+        repair(&bytes).unwrap().write_to(&mut repaired).unwrap();
+        assert_eq!(repaired, two_frames());
+    }
+
+    #[test]
+    fn long_file_header() {
+        let mut bytes = two_frames();
+        bytes.splice(16..16, [1; 4]);
+        set_field(&mut bytes, 4, 20);
+        set_field(&mut bytes, 20 + 8, FIRST_IMAGE as u32 + 4);
+        set_field(&mut bytes, SECOND_POSITION + 4, FIRST_IMAGE as u32 + 44);
+
+        assert_eq!(
+            validate(&bytes),
+            [Violation {
+                offset: 16,
+                kind: ViolationKind::UnusedHeaderBytes(4),
+            }]
+        );
+
+        assert_eq!(
+            round_trip(&bytes).unwrap().unwrap().kind,
+            DivergenceKind::FileHeader
+        );
+
+        // Repairing the file leaves the unused bytes out
+        let mut repaired = Vec::new();
+        repair(&bytes).unwrap().write_to(&mut repaired).unwrap();
+        assert_eq!(repaired, two_frames());
+    }
+
+    #[test]
+    fn overlapping_chunks() {
+        // Both entries point to the first image
+        let mut bytes = two_frames();
+        set_field(&mut bytes, SECOND_POSITION, FIRST_IMAGE as u32);
+
+        assert_eq!(
+            validate(&bytes),
+            [
+                Violation {
+                    offset: SECOND_POSITION as u64,
+                    kind: ViolationKind::TocUnsorted { index: 1 },
+                },
+                Violation {
+                    offset: FIRST_IMAGE as u64,
+                    kind: ViolationKind::ChunksOverlap { index: 0, other: 1 },
+                },
+                Violation {
+                    offset: FIRST_IMAGE as u64 + 40,
+                    kind: ViolationKind::TrailingBytes(40),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn chunk_in_header() {
+        let mut bytes = two_frames();
+        set_field(&mut bytes, SECOND_POSITION, 16);
+
+        assert_eq!(
+            kinds(&bytes),
+            [
+                ViolationKind::ChunkInHeader { index: 1 },
+                ViolationKind::TrailingBytes(40),
+            ]
+        );
+    }
+}
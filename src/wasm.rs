@@ -0,0 +1,34 @@
+//! JavaScript bindings for WebAssembly builds, enabled by the `wasm` feature.
+//!
+//! These are meant to be built with `wasm-pack`:
+//!
+//! ```console
+//! $ wasm-pack build --no-default-features --features wasm
+//! ```
+
+use crate::{MemorySink, ResizeOptions, XcursorReader};
+use std::path::Path;
+use wasm_bindgen::prelude::*;
+
+/// Returns the header, table of contents, and image fields of an Xcursor
+/// file as an object, as described by `XcursorInfo`.
+#[wasm_bindgen(js_name = parse)]
+pub fn parse_js(bytes: &[u8]) -> Result<JsValue, JsError> {
+    let info = XcursorReader::new(bytes)?.info()?;
+    Ok(serde_wasm_bindgen::to_value(&info)?)
+}
+
+/// Resizes an Xcursor file by the given scale factor, returning the bytes
+/// of the resized file.
+#[wasm_bindgen(js_name = resize)]
+pub fn resize_js(bytes: &[u8], scale: u32) -> Result<Vec<u8>, JsError> {
+    let cursor = crate::resize(bytes, &ResizeOptions::new(scale))?;
+    let sink = MemorySink::new();
+    cursor.write_to_sink(&sink, Path::new("cursor"))?;
+
+    let (_, res) = sink
+        .into_outputs()
+        .pop()
+        .expect("the cursor should have been written");
+    Ok(res)
+}
@@ -1,37 +1,58 @@
-//! Facilities for writing an Xcursor to disk, which isn't
-//! currently supported by `xcursor`.
+//! Facilities for writing an Xcursor to disk.
 
 // Information about Xcursor files can be found here:
 // https://www.x.org/archive/X11R7.7/doc/man/man3/Xcursor.3.xhtml
 
+use crate::{
+    read_xcursor::{Chunk, CommentKind, FileHeader, ParsedComment, XcursorReader},
+    Error, OutputSink, Result,
+};
 use byteorder::{LittleEndian, WriteBytesExt};
-use std::{io::Write, mem::size_of};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    io::Write,
+    mem::{self, size_of},
+    path::Path,
+};
 
 const XCURSOR_MAGIC: &[u8] = b"Xcur";
 const SIZE_OF_U32: u32 = size_of::<u32>() as u32;
-// Four fields: `magic`, `header[_size]`, `version`, and `ntoc`.
+// Four fields: `magic`, `header[_size]`, `version`, and `ntoc`. Headers can
+// be longer than this, but not shorter.
 const HEADER_BYTE_LENGTH: u32 = SIZE_OF_U32 * 4;
+/// The number of pixels to encode before writing them out. Encoding into a
+/// buffer avoids making a tiny write for every pixel, which is very slow
+/// with unbuffered writers (or on network filesystems).
+const PIXELS_PER_WRITE: usize = 16 * 1024;
 
-#[derive(Clone, Copy)]
-pub(crate) struct TocEntry {
-    entry_type: u32,
-    subtype: u32,
-    position: u32,
+/// An entry in an Xcursor's table of contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TocEntry {
+    /// The type of the chunk this entry points to.
+    pub entry_type: u32,
+    /// The subtype of the chunk this entry points to. For images,
+    /// this is the nominal size.
+    pub subtype: u32,
+    /// The offset of the chunk from the start of the file.
+    pub position: u32,
 }
 
 impl TocEntry {
-    const BYTE_LENGTH: u32 = SIZE_OF_U32 * 3;
+    /// The length of each entry in a table of contents, in bytes.
+    pub const BYTE_LENGTH: u32 = SIZE_OF_U32 * 3;
 
-    fn write_to(&self, mut writer: impl Write) -> anyhow::Result<()> {
-        writer.write_u32::<LittleEndian>(self.entry_type)?;
-        writer.write_u32::<LittleEndian>(self.subtype)?;
-        writer.write_u32::<LittleEndian>(self.position)?;
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<()> {
+        buffer.write_u32::<LittleEndian>(self.entry_type)?;
+        buffer.write_u32::<LittleEndian>(self.subtype)?;
+        buffer.write_u32::<LittleEndian>(self.position)?;
         Ok(())
     }
 }
 
+/// A single image chunk in an Xcursor.
 #[derive(Clone)]
-pub(crate) struct Image {
+pub struct Image {
     // This is the `subtype` field
     size: u32,
     width: u32,
@@ -43,11 +64,25 @@ pub(crate) struct Image {
 }
 
 impl Image {
-    const TYPE: u32 = 0xFFFD0002;
-    const HEADER_SIZE: u32 = 36;
-    const VERSION: u32 = 1;
+    /// The chunk type of images.
+    pub const TYPE: u32 = 0xFFFD0002;
+    /// The length of an image chunk's header, in bytes, which its pixels
+    /// follow.
+    pub const HEADER_SIZE: u32 = 36;
+    pub(crate) const VERSION: u32 = 1;
+    /// The largest width or height that libXcursor will load.
+    pub const MAX_DIMENSION: u32 = 0x7FFF;
 
-    pub(crate) fn new(
+    /// Creates a new image.
+    ///
+    /// `size` is the image's nominal size, which is used to pick an image
+    /// for a given cursor size. `xhot` and `yhot` are the coordinates of the
+    /// cursor's hotspot, while `delay` is the number of milliseconds to show
+    /// this image for when animating. `pixels` are ARGB pixels, row by row.
+    ///
+    /// This fails if `width` or `height` are larger than [`Image::MAX_DIMENSION`],
+    /// or if the dimensions of the image don't match the number of pixels given.
+    pub fn new(
         size: u32,
         width: u32,
         height: u32,
@@ -55,15 +90,20 @@ impl Image {
         yhot: u32,
         delay: u32,
         pixels: Vec<u32>,
-    ) -> anyhow::Result<Self> {
-        if width * height != pixels.len().try_into()? {
-            panic!(
-                "image dimensions ({}x{} = {}) do not match the number of pixels given ({})",
+    ) -> Result<Self> {
+        if width > Self::MAX_DIMENSION || height > Self::MAX_DIMENSION {
+            return Err(Error::ImageTooLarge { width, height });
+        }
+
+        // This can't overflow, since both dimensions fit in a `u32`
+        let pixel_count = u64::from(width) * u64::from(height);
+
+        if u64::try_from(pixels.len()).ok() != Some(pixel_count) {
+            return Err(Error::DimensionMismatch {
                 width,
                 height,
-                width * height,
-                pixels.len()
-            );
+                pixel_count: pixels.len(),
+            });
         }
 
         Ok(Self {
@@ -77,84 +117,1059 @@ impl Image {
         })
     }
 
-    fn write_to(&self, mut writer: impl Write) -> anyhow::Result<()> {
-        writer.write_u32::<LittleEndian>(Self::HEADER_SIZE)?;
-        writer.write_u32::<LittleEndian>(Self::TYPE)?;
+    /// Writes this image to `writer`, using `buffer` to encode it.
+    fn write_to(&self, mut writer: impl Write, buffer: &mut Vec<u8>) -> Result<()> {
+        buffer.clear();
+        buffer.write_u32::<LittleEndian>(Self::HEADER_SIZE)?;
+        buffer.write_u32::<LittleEndian>(Self::TYPE)?;
         // `subtype`
-        writer.write_u32::<LittleEndian>(self.size)?;
-        writer.write_u32::<LittleEndian>(Self::VERSION)?;
-        writer.write_u32::<LittleEndian>(self.width)?;
-        writer.write_u32::<LittleEndian>(self.height)?;
-        writer.write_u32::<LittleEndian>(self.xhot)?;
-        writer.write_u32::<LittleEndian>(self.yhot)?;
-        writer.write_u32::<LittleEndian>(self.delay)?;
+        buffer.write_u32::<LittleEndian>(self.size)?;
+        buffer.write_u32::<LittleEndian>(Self::VERSION)?;
+        buffer.write_u32::<LittleEndian>(self.width)?;
+        buffer.write_u32::<LittleEndian>(self.height)?;
+        buffer.write_u32::<LittleEndian>(self.xhot)?;
+        buffer.write_u32::<LittleEndian>(self.yhot)?;
+        buffer.write_u32::<LittleEndian>(self.delay)?;
+
+        // The header is written along with the first block of pixels
+        for pixels in self.pixels.chunks(PIXELS_PER_WRITE) {
+            buffer.extend(pixels.iter().flat_map(|pixel| pixel.to_le_bytes()));
+            writer.write_all(buffer)?;
+            buffer.clear();
+        }
 
-        for pixel in &self.pixels {
-            writer.write_u32::<LittleEndian>(*pixel)?;
+        // Images without any pixels still need their header
+        if !buffer.is_empty() {
+            writer.write_all(buffer)?;
         }
 
         Ok(())
     }
 
-    fn byte_length(&self) -> anyhow::Result<u32> {
+    /// The image's nominal size.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The x coordinate of the image's hotspot.
+    pub fn xhot(&self) -> u32 {
+        self.xhot
+    }
+
+    /// The y coordinate of the image's hotspot.
+    pub fn yhot(&self) -> u32 {
+        self.yhot
+    }
+
+    /// Moves the image's hotspot to the given coordinates.
+    pub fn set_hotspot(&mut self, xhot: u32, yhot: u32) {
+        self.xhot = xhot;
+        self.yhot = yhot;
+    }
+
+    /// The number of milliseconds to show this image for when animating.
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    /// Sets the number of milliseconds to show this image for when animating.
+    pub fn set_delay(&mut self, delay: u32) {
+        self.delay = delay;
+    }
+
+    /// The image's ARGB pixels, row by row.
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// The image's ARGB pixels, row by row, so that they can be edited in
+    /// place.
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    /// Consumes this image, returning its pixels.
+    pub fn into_pixels(self) -> Vec<u32> {
+        self.pixels
+    }
+
+    /// Returns this image's nominal size and dimensions.
+    pub fn layout(&self) -> ImageLayout {
+        ImageLayout {
+            size: self.size,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Returns a hash of this image's nominal size, dimensions, hotspot,
+    /// delay, and pixels.
+    ///
+    /// The hash is the same on every platform and in every version of this
+    /// crate, so it can be saved and compared later to tell whether an image
+    /// has changed.
+    ///
+    /// ```
+    /// use resize_xcursor::Image;
+    ///
+    /// let image = Image::new(1, 1, 1, 0, 0, 0, vec![0xFF00FF00])?;
+    /// let mut other = image.clone();
+    /// assert_eq!(image.content_hash(), other.content_hash());
+    ///
+    /// other.set_delay(50);
+    /// assert_ne!(image.content_hash(), other.content_hash());
+    /// # Ok::<(), resize_xcursor::Error>(())
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+
+        for field in [
+            self.size,
+            self.width,
+            self.height,
+            self.xhot,
+            self.yhot,
+            self.delay,
+        ] {
+            hasher.write_u32(field);
+        }
+
+        for &pixel in &self.pixels {
+            hasher.write_u32(pixel);
+        }
+
+        hasher.finish()
+    }
+
+    fn byte_length(&self) -> Result<u32> {
+        self.layout().byte_length()
+    }
+}
+
+/// The nominal size and dimensions of an image. This is enough to lay out
+/// an Xcursor's table of contents before any pixels exist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageLayout {
+    /// The image's nominal size.
+    pub size: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageLayout {
+    fn byte_length(&self) -> Result<u32> {
         // Images have nine `u32` fields, including `header`, `type`, and `version`.
-        Ok(SIZE_OF_U32 * 9 + u32::try_from(self.pixels.len())? * SIZE_OF_U32)
+        self.width
+            .checked_mul(self.height)
+            .and_then(|len| len.checked_mul(SIZE_OF_U32))
+            .and_then(|len| len.checked_add(Image::HEADER_SIZE))
+            .ok_or(Error::FileTooLarge)
+    }
+}
+
+/// A chunk other than an image, such as a comment, which is written out
+/// byte for byte.
+///
+/// Chunks that this crate doesn't understand are kept this way, so that
+/// resizing a cursor doesn't throw away data that other tools put into it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawChunk {
+    /// The type of this chunk, which is also used for its table of
+    /// contents entry.
+    pub entry_type: u32,
+    pub subtype: u32,
+    /// The whole chunk, including its header.
+    pub bytes: Vec<u8>,
+}
+
+impl RawChunk {
+    /// Creates a comment chunk, such as a copyright notice or a license.
+    ///
+    /// ```
+    /// use resize_xcursor::{read_xcursor::CommentKind, RawChunk};
+    ///
+    /// let comment = RawChunk::comment(CommentKind::License, "CC0-1.0")?;
+    /// assert_eq!(comment.subtype, CommentKind::License.subtype());
+    /// # Ok::<(), resize_xcursor::Error>(())
+    /// ```
+    ///
+    /// This fails if the text is too long to be represented.
+    pub fn comment(kind: CommentKind, text: &str) -> Result<Self> {
+        let length = u32::try_from(text.len())
+            .ok()
+            .filter(|length| length.checked_add(ParsedComment::HEADER_SIZE).is_some())
+            .ok_or(Error::FileTooLarge)?;
+
+        let mut bytes = Vec::with_capacity(ParsedComment::HEADER_SIZE as usize + text.len());
+
+        for field in [
+            ParsedComment::HEADER_SIZE,
+            ParsedComment::TYPE,
+            kind.subtype(),
+            ParsedComment::VERSION,
+            length,
+        ] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(text.as_bytes());
+
+        Ok(Self {
+            entry_type: ParsedComment::TYPE,
+            subtype: kind.subtype(),
+            bytes,
+        })
     }
+
+    fn byte_length(&self) -> Result<u32> {
+        u32::try_from(self.bytes.len()).map_err(|_| Error::FileTooLarge)
+    }
+}
+
+/// What [`XcursorBuilder::add_image`] should do when given an image with
+/// the same nominal size as an image that was already added.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateSizePolicy {
+    /// Allow any number of images with the same nominal size.
+    ///
+    /// This is necessary for animated cursors, since each frame of an
+    /// animation is a separate image with the same nominal size.
+    #[default]
+    Allow,
+    /// Return an error when adding an image whose nominal size was
+    /// already used.
+    Reject,
 }
 
+/// Builds an Xcursor file one image at a time.
+///
+/// ```no_run
+/// use resize_xcursor::{Image, XcursorBuilder};
+/// use std::fs::File;
+///
+/// let image = Image::new(1, 1, 1, 0, 0, 0, vec![0xFF000000])?;
+///
+/// XcursorBuilder::new()
+///     .add_image(image)?
+///     .write_to(File::create("tiny-cursor")?)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 #[derive(Clone)]
-pub(crate) struct Xcursor {
-    table_of_contents: Vec<TocEntry>,
-    chunks: Vec<Image>,
-    /// The next `TocEntry::position` for a given chunk.
-    next_chunk_position: u32,
+pub struct XcursorBuilder {
+    images: Vec<Image>,
+    /// Chunks other than images, in the order they're written.
+    raw_chunks: Vec<RawChunk>,
+    /// How many images are written before each of `raw_chunks`. These never
+    /// decrease, since the raw chunks are in order.
+    raw_chunk_positions: Vec<usize>,
+    /// The total length of every chunk in `images` and `raw_chunks`, in bytes.
+    chunks_byte_length: u32,
+    duplicate_size_policy: DuplicateSizePolicy,
+    file_header: FileHeader,
+}
+
+impl XcursorBuilder {
+    /// Creates a builder with no images in it.
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            raw_chunks: Vec::new(),
+            raw_chunk_positions: Vec::new(),
+            chunks_byte_length: 0,
+            duplicate_size_policy: DuplicateSizePolicy::default(),
+            file_header: FileHeader::default(),
+        }
+    }
+
+    /// Parses an Xcursor file into a builder, so that its images can be
+    /// edited and written out again. The file header is kept, and chunks
+    /// other than images, such as comments, are kept as [`RawChunk`]s in
+    /// their places among the images.
+    ///
+    /// ```no_run
+    /// use resize_xcursor::XcursorBuilder;
+    /// use std::fs::{self, File};
+    ///
+    /// let mut cursor = XcursorBuilder::parse(&fs::read("left_ptr")?)?;
+    /// cursor.remove_size(24);
+    /// cursor.write_to(File::create("left_ptr-edited")?)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let reader = XcursorReader::new(bytes)?;
+        let mut cursor = Self::new();
+        cursor.file_header(reader.header())?;
+
+        for chunk in reader.chunks() {
+            match chunk? {
+                Chunk::Image(image) => cursor.add_image(image.to_image()?)?,
+                Chunk::Comment(comment) => cursor.add_raw_chunk(comment.to_raw_chunk())?,
+                Chunk::Unknown(chunk) => cursor.add_raw_chunk(chunk.to_raw_chunk())?,
+            };
+        }
+
+        Ok(cursor)
+    }
+
+    /// Sets what should happen when adding an image with the same nominal size
+    /// as an existing image. Defaults to [`DuplicateSizePolicy::Allow`].
+    pub fn duplicate_size_policy(&mut self, policy: DuplicateSizePolicy) -> &mut Self {
+        self.duplicate_size_policy = policy;
+        self
+    }
+
+    /// Sets the file header to write. Defaults to [`FileHeader::default`],
+    /// which is what `xcursorgen` writes.
+    ///
+    /// If `header_size` is larger than 16 bytes, the rest of the header is
+    /// filled with zeroes. This fails if `header_size` is smaller than 16
+    /// bytes, or if it would make the file too large to be represented.
+    ///
+    /// ```
+    /// use resize_xcursor::{FileHeader, Image, XcursorBuilder, XcursorReader};
+    ///
+    /// let header = FileHeader {
+    ///     header_size: 24,
+    ///     version: 0x00020000,
+    /// };
+    ///
+    /// let mut cursor = XcursorBuilder::new();
+    /// cursor.file_header(header)?;
+    /// cursor.add_image(Image::new(1, 1, 1, 0, 0, 0, vec![0xFF000000])?)?;
+    ///
+    /// let mut bytes = Vec::new();
+    /// cursor.write_to(&mut bytes)?;
+    /// assert_eq!(bytes.len(), cursor.byte_length() as usize);
+    ///
+    /// let reader = XcursorReader::new(&bytes)?;
+    /// assert_eq!(reader.header(), header);
+    /// assert_eq!(reader.images().count(), 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn file_header(&mut self, header: FileHeader) -> Result<&mut Self> {
+        check_file_header(header)?;
+        file_byte_length(header, self.chunk_count(), self.chunks_byte_length)
+            .ok_or(Error::FileTooLarge)?;
+
+        self.file_header = header;
+        Ok(self)
+    }
+
+    /// Adds an image to the end of this Xcursor.
+    ///
+    /// This fails if the image would make the file too large to be
+    /// represented, or if the image breaks the current
+    /// [`DuplicateSizePolicy`].
+    pub fn add_image(&mut self, image: Image) -> Result<&mut Self> {
+        if self.duplicate_size_policy == DuplicateSizePolicy::Reject
+            && self.images.iter().any(|chunk| chunk.size == image.size)
+        {
+            return Err(Error::DuplicateSize(image.size));
+        }
+
+        let chunks_byte_length = self
+            .chunks_byte_length
+            .checked_add(image.byte_length()?)
+            .ok_or(Error::FileTooLarge)?;
+
+        // Every chunk position has to fit in a `u32`, so make sure that the
+        // whole file (including this image's `TocEntry`) still fits
+        file_byte_length(self.file_header, self.chunk_count() + 1, chunks_byte_length)
+            .ok_or(Error::FileTooLarge)?;
+
+        self.chunks_byte_length = chunks_byte_length;
+        self.images.push(image);
+        Ok(self)
+    }
+
+    /// Adds a chunk other than an image to the end of this Xcursor, after
+    /// every image added so far. It will be written out byte for byte.
+    ///
+    /// ```
+    /// use resize_xcursor::{read_xcursor::CommentKind, Chunk, Image, XcursorBuilder};
+    ///
+    /// let mut cursor = XcursorBuilder::new();
+    /// cursor.add_image(Image::new(1, 1, 1, 0, 0, 0, vec![0xFF000000])?)?;
+    /// cursor.add_comment(CommentKind::Other, "after the image")?;
+    ///
+    /// let mut bytes = Vec::new();
+    /// cursor.write_to(&mut bytes)?;
+    ///
+    /// let chunks = resize_xcursor::parse_chunks(&bytes)?;
+    /// assert!(matches!(chunks[..], [Chunk::Image(_), Chunk::Comment(_)]));
+    /// # Ok::<(), resize_xcursor::Error>(())
+    /// ```
+    ///
+    /// This fails if the chunk would make the file too large to be represented.
+    pub fn add_raw_chunk(&mut self, chunk: RawChunk) -> Result<&mut Self> {
+        let chunks_byte_length = self
+            .chunks_byte_length
+            .checked_add(chunk.byte_length()?)
+            .ok_or(Error::FileTooLarge)?;
+
+        file_byte_length(self.file_header, self.chunk_count() + 1, chunks_byte_length)
+            .ok_or(Error::FileTooLarge)?;
+
+        self.chunks_byte_length = chunks_byte_length;
+        self.raw_chunks.push(chunk);
+        self.raw_chunk_positions.push(self.images.len());
+        Ok(self)
+    }
+
+    /// Adds a comment, such as a copyright notice or a license. See
+    /// [`RawChunk::comment`].
+    pub fn add_comment(&mut self, kind: CommentKind, text: &str) -> Result<&mut Self> {
+        self.add_raw_chunk(RawChunk::comment(kind, text)?)
+    }
+
+    /// Removes every image with the given nominal size (every frame, for an
+    /// animated cursor), returning them in their original order.
+    pub fn remove_size(&mut self, size: u32) -> Vec<Image> {
+        // Raw chunks stay after the images that are kept
+        let kept_before = self.kept_before(size);
+
+        for position in &mut self.raw_chunk_positions {
+            *position = kept_before[*position];
+        }
+
+        let (removed, kept): (Vec<_>, Vec<_>) = mem::take(&mut self.images)
+            .into_iter()
+            .partition(|chunk| chunk.size == size);
+
+        self.images = kept;
+        self.chunks_byte_length -= removed
+            .iter()
+            .map(|image| {
+                image
+                    .byte_length()
+                    .expect("`add_image` should have checked every image's length")
+            })
+            .sum::<u32>();
+
+        removed
+    }
+
+    /// Replaces every image with the given nominal size with `images`,
+    /// returning the images that were replaced.
+    ///
+    /// The new images take the place of the first replaced image, or are
+    /// added to the end if there weren't any images with this nominal size.
+    /// Raw chunks that came after the first replaced image come after the
+    /// new images. On failure (see [`XcursorBuilder::add_image`]), this builder is left
+    /// unchanged.
+    pub fn replace_size(
+        &mut self,
+        size: u32,
+        images: impl IntoIterator<Item = Image>,
+    ) -> Result<Vec<Image>> {
+        let images = images.into_iter().collect::<Vec<_>>();
+        let kept = || self.images.iter().filter(|chunk| chunk.size != size);
+
+        if self.duplicate_size_policy == DuplicateSizePolicy::Reject {
+            let mut sizes = kept().map(|chunk| chunk.size).collect::<Vec<_>>();
+
+            for image in &images {
+                if sizes.contains(&image.size) {
+                    return Err(Error::DuplicateSize(image.size));
+                }
+
+                sizes.push(image.size);
+            }
+        }
+
+        let removed_byte_length = self
+            .images
+            .iter()
+            .filter(|chunk| chunk.size == size)
+            .map(Image::byte_length)
+            .sum::<Result<u32>>()?;
+        let mut chunks_byte_length = self.chunks_byte_length - removed_byte_length;
+
+        for image in &images {
+            chunks_byte_length = chunks_byte_length
+                .checked_add(image.byte_length()?)
+                .ok_or(Error::FileTooLarge)?;
+        }
+
+        let chunk_count = self.raw_chunks.len() + kept().count() + images.len();
+        file_byte_length(self.file_header, chunk_count, chunks_byte_length)
+            .ok_or(Error::FileTooLarge)?;
+
+        // Nothing before the first replaced image is removed, so its index
+        // stays the same
+        let position = self
+            .images
+            .iter()
+            .position(|chunk| chunk.size == size)
+            .unwrap_or(self.images.len());
+
+        let kept_before = self.kept_before(size);
+        let raw_chunk_positions = self
+            .raw_chunk_positions
+            .iter()
+            .map(|&raw_position| match raw_position <= position {
+                true => raw_position,
+                false => kept_before[raw_position] + images.len(),
+            })
+            .collect();
+
+        let removed = self.remove_size(size);
+        self.images.splice(position..position, images);
+        self.raw_chunk_positions = raw_chunk_positions;
+        self.chunks_byte_length = chunks_byte_length;
+        Ok(removed)
+    }
+
+    /// Returns every image in this Xcursor, in order.
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    /// Returns every image in this Xcursor, in order, so that they can be
+    /// edited in place.
+    ///
+    /// Only the parts of an image that don't change the file's layout (such
+    /// as its hotspot or delay) can be edited this way. To replace images, use
+    /// [`XcursorBuilder::replace_size`] instead.
+    pub fn images_mut(&mut self) -> &mut [Image] {
+        &mut self.images
+    }
+
+    /// Consumes this builder, returning its images.
+    pub fn into_images(self) -> Vec<Image> {
+        self.images
+    }
+
+    /// Returns every chunk in this Xcursor that isn't an image, in order.
+    pub fn raw_chunks(&self) -> &[RawChunk] {
+        &self.raw_chunks
+    }
+
+    /// Removes every chunk that isn't an image, returning them.
+    pub fn take_raw_chunks(&mut self) -> Vec<RawChunk> {
+        self.raw_chunk_positions.clear();
+        let raw_chunks = mem::take(&mut self.raw_chunks);
+        self.chunks_byte_length -= raw_chunks
+            .iter()
+            .map(|chunk| {
+                chunk
+                    .byte_length()
+                    .expect("`add_raw_chunk` should have checked every chunk's length")
+            })
+            .sum::<u32>();
+
+        raw_chunks
+    }
+
+    /// Returns the nominal sizes used in this Xcursor, in the order they
+    /// first appear.
+    pub fn sizes(&self) -> Vec<u32> {
+        let mut sizes = Vec::new();
+
+        for chunk in &self.images {
+            if !sizes.contains(&chunk.size) {
+                sizes.push(chunk.size);
+            }
+        }
+
+        sizes
+    }
+
+    /// Returns a hash of every image and raw chunk in this Xcursor, in order.
+    ///
+    /// Like [`Image::content_hash`], this is stable across platforms and
+    /// versions. The file header and the positions of chunks aren't
+    /// included, so rewriting a file without changing its contents keeps
+    /// its hash.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+
+        for image in &self.images {
+            hasher.write_u32(Image::TYPE);
+            hasher.write_u64(image.content_hash());
+        }
+
+        for chunk in &self.raw_chunks {
+            hasher.write_u32(chunk.entry_type);
+            hasher.write_u32(chunk.subtype);
+            hasher.write_u64(chunk.bytes.len() as u64);
+            hasher.write(&chunk.bytes);
+        }
+
+        hasher.finish()
+    }
+
+    /// Returns the length of the file that [`XcursorBuilder::write_to`]
+    /// would write, in bytes.
+    pub fn byte_length(&self) -> u32 {
+        file_byte_length(
+            self.file_header,
+            self.chunk_count(),
+            self.chunks_byte_length,
+        )
+        .expect("`add_image` should have checked the file's length")
+    }
+
+    /// Writes this Xcursor to `writer`.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<()> {
+        let mut writer = StreamingWriter::with_interleaved_chunks(
+            &mut writer,
+            self.file_header,
+            &self.raw_chunks,
+            &self.raw_chunk_positions,
+            self.images.iter().map(Image::layout),
+        )?;
+
+        for image in &self.images {
+            writer.write_image(image)?;
+        }
+
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Writes this Xcursor to `sink`, as the output called `name`.
+    pub fn write_to_sink(&self, sink: &dyn OutputSink, name: &Path) -> Result<()> {
+        let mut output = sink.create(name)?;
+        self.write_to(&mut output)?;
+        output.commit()
+    }
+
+    fn chunk_count(&self) -> usize {
+        self.raw_chunks.len() + self.images.len()
+    }
+
+    /// Returns how many images without the given nominal size come before
+    /// each position among the images, from 0 to the number of images.
+    fn kept_before(&self, size: u32) -> Vec<usize> {
+        let mut kept = 0;
+        let mut res = Vec::with_capacity(self.images.len() + 1);
+        res.push(0);
+
+        for image in &self.images {
+            kept += usize::from(image.size != size);
+            res.push(kept);
+        }
+
+        res
+    }
 }
 
-impl Xcursor {
-    pub(crate) fn new() -> Self {
+/// Writes an Xcursor one image at a time, without holding every image in
+/// memory at once.
+///
+/// Since the table of contents comes before any images, the nominal size
+/// and dimensions of every image have to be given upfront. Images must then
+/// be written in the same order.
+///
+/// ```no_run
+/// use resize_xcursor::{Image, StreamingWriter};
+/// use std::{fs::File, io::BufWriter};
+///
+/// let image = Image::new(1, 1, 1, 0, 0, 0, vec![0xFF000000])?;
+/// let output = BufWriter::new(File::create("tiny-cursor")?);
+///
+/// let mut writer = StreamingWriter::new(output, [image.layout()])?;
+/// writer.write_image(&image)?;
+/// writer.finish()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct StreamingWriter<W> {
+    writer: W,
+    layout: Vec<ImageLayout>,
+    /// The raw chunks that haven't been written yet, and how many images
+    /// come before each of them.
+    raw_chunks: VecDeque<(usize, RawChunk)>,
+    /// The number of images written so far.
+    written: usize,
+    /// Reused to encode each image.
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> StreamingWriter<W> {
+    /// Creates a streaming writer and writes the file header and table of
+    /// contents for the given images to `writer`.
+    ///
+    /// This fails if any image is larger than [`Image::MAX_DIMENSION`], or if
+    /// the images would make the file too large to be represented. These
+    /// errors are wrapped in an [`Error::InImage`] naming the image.
+    pub fn new(writer: W, layout: impl IntoIterator<Item = ImageLayout>) -> Result<Self> {
+        Self::with_header(writer, FileHeader::default(), &[], layout)
+    }
+
+    /// Like [`StreamingWriter::new`], but writes the given file header (see
+    /// [`XcursorBuilder::file_header`]), followed by `raw_chunks` before any
+    /// images.
+    pub fn with_header(
+        writer: W,
+        header: FileHeader,
+        raw_chunks: &[RawChunk],
+        layout: impl IntoIterator<Item = ImageLayout>,
+    ) -> Result<Self> {
+        let positions = vec![0; raw_chunks.len()];
+        Self::with_interleaved_chunks(writer, header, raw_chunks, &positions, layout)
+    }
+
+    /// Like [`StreamingWriter::with_header`], but writes each raw chunk
+    /// after the number of images given by `positions`, so that a file's
+    /// chunks can be written out in their original order. Chunks are
+    /// written in the order given, so `positions` should never decrease.
+    /// Positions past the last image place chunks after every image.
+    ///
+    /// # Panics
+    /// Panics if `positions` isn't as long as `raw_chunks`.
+    pub fn with_interleaved_chunks(
+        mut writer: W,
+        header: FileHeader,
+        raw_chunks: &[RawChunk],
+        positions: &[usize],
+        layout: impl IntoIterator<Item = ImageLayout>,
+    ) -> Result<Self> {
+        assert_eq!(
+            raw_chunks.len(),
+            positions.len(),
+            "every raw chunk should have a position"
+        );
+
+        let layout = layout.into_iter().collect::<Vec<_>>();
+        check_layout(header, raw_chunks, &layout)?;
+
+        // A stable sort keeps chunks with the same position in order
+        let mut raw_chunks = positions
+            .iter()
+            .map(|&position| position.min(layout.len()))
+            .zip(raw_chunks.iter().cloned())
+            .collect::<Vec<_>>();
+        raw_chunks.sort_by_key(|&(position, _)| position);
+
+        write_file_start(&mut writer, header, &raw_chunks, &layout)?;
+
         let mut res = Self {
-            table_of_contents: Vec::new(),
-            chunks: Vec::new(),
-            next_chunk_position: 0,
+            writer,
+            layout,
+            raw_chunks: raw_chunks.into(),
+            written: 0,
+            buffer: Vec::new(),
         };
 
-        res.next_chunk_position = HEADER_BYTE_LENGTH + TocEntry::BYTE_LENGTH;
-        res
+        res.write_raw_chunks()?;
+        Ok(res)
     }
 
-    pub(crate) fn add_chunk(&mut self, image: Image) -> anyhow::Result<()> {
-        // Unfortunately, to account for this new image's `TocEntry`, we have to update
-        // the position in *every existing `TocEntry`*
-        for toc_entry in &mut self.table_of_contents {
-            toc_entry.position += TocEntry::BYTE_LENGTH;
+    /// Writes the next image.
+    ///
+    /// This fails if the image's nominal size or dimensions don't match the
+    /// layout this writer was created with.
+    pub fn write_image(&mut self, image: &Image) -> Result<()> {
+        if self.layout.get(self.written) != Some(&image.layout()) {
+            return Err(Error::LayoutMismatch {
+                index: self.written,
+            });
         }
 
-        self.table_of_contents.push(TocEntry {
-            entry_type: Image::TYPE,
-            subtype: image.size,
-            position: self.next_chunk_position,
-        });
+        image.write_to(&mut self.writer, &mut self.buffer)?;
+        self.written += 1;
+        self.write_raw_chunks()
+    }
+
+    /// Flushes the underlying writer and returns it.
+    ///
+    /// This fails if any of the images given to [`StreamingWriter::new`]
+    /// haven't been written yet.
+    pub fn finish(mut self) -> Result<W> {
+        if self.written != self.layout.len() {
+            return Err(Error::IncompleteStream {
+                written: self.written,
+                expected: self.layout.len(),
+            });
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Writes the raw chunks that come directly after the images written
+    /// so far.
+    fn write_raw_chunks(&mut self) -> Result<()> {
+        while self
+            .raw_chunks
+            .front()
+            .is_some_and(|&(position, _)| position <= self.written)
+        {
+            let (_, chunk) = self
+                .raw_chunks
+                .pop_front()
+                .expect("there should be a chunk");
+            self.writer.write_all(&chunk.bytes)?;
+        }
 
-        self.next_chunk_position += TocEntry::BYTE_LENGTH + image.byte_length()?;
-        self.chunks.push(image);
         Ok(())
     }
+}
+
+/// Writes the file header, followed by a table of contents with each chunk
+/// placed directly after the previous one. Each raw chunk comes after the
+/// number of images it's paired with, which never decreases.
+///
+/// The caller must have already checked that the header and every chunk's
+/// length are valid.
+fn write_file_start(
+    mut writer: impl Write,
+    header: FileHeader,
+    raw_chunks: &[(usize, RawChunk)],
+    layout: &[ImageLayout],
+) -> Result<()> {
+    let chunk_count = (raw_chunks.len() + layout.len()) as u32;
+    let toc_byte_length = chunk_count * TocEntry::BYTE_LENGTH;
+
+    // Encode everything first, so that it can be written all at once
+    let mut buffer = Vec::with_capacity((header.header_size + toc_byte_length) as usize);
+    buffer.extend_from_slice(XCURSOR_MAGIC);
+    buffer.write_u32::<LittleEndian>(header.header_size)?;
+    buffer.write_u32::<LittleEndian>(header.version)?;
+    buffer.write_u32::<LittleEndian>(chunk_count)?;
+    // Any extra header space is left empty
+    buffer.resize(header.header_size as usize, 0);
+
+    let mut position = header.header_size + toc_byte_length;
+    let mut raw_chunks = raw_chunks.iter().peekable();
 
-    pub(crate) fn write_to(&self, mut writer: impl Write) -> anyhow::Result<()> {
-        writer.write_all(XCURSOR_MAGIC)?;
-        writer.write_u32::<LittleEndian>(HEADER_BYTE_LENGTH)?;
-        // File version, taken from a random Xcursor (perhaps it's 2 bytes for major, 2 bytes for minor?)
-        writer.write_u32::<LittleEndian>(0x00010000)?;
-        writer.write_u32::<LittleEndian>(self.table_of_contents.len().try_into()?)?;
+    for index in 0..=layout.len() {
+        while let Some((_, chunk)) = raw_chunks.next_if(|(before, _)| *before <= index) {
+            TocEntry {
+                entry_type: chunk.entry_type,
+                subtype: chunk.subtype,
+                position,
+            }
+            .encode_into(&mut buffer)?;
 
-        for toc_entry in &self.table_of_contents {
-            toc_entry.write_to(&mut writer)?;
+            position += chunk.byte_length().unwrap_or_default();
         }
 
-        for chunk in &self.chunks {
-            chunk.write_to(&mut writer)?;
+        if let Some(image) = layout.get(index) {
+            TocEntry {
+                entry_type: Image::TYPE,
+                subtype: image.size,
+                position,
+            }
+            .encode_into(&mut buffer)?;
+
+            position += image.byte_length().unwrap_or_default();
         }
+    }
 
-        Ok(())
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+/// Returns the length of an Xcursor file with `chunk_count` chunks,
+/// or `None` if it doesn't fit in a `u32`.
+fn file_byte_length(
+    header: FileHeader,
+    chunk_count: usize,
+    chunks_byte_length: u32,
+) -> Option<u32> {
+    u32::try_from(chunk_count)
+        .ok()?
+        .checked_mul(TocEntry::BYTE_LENGTH)?
+        .checked_add(header.header_size)?
+        .checked_add(chunks_byte_length)
+}
+
+/// Checks that a file with the given header, raw chunks, and image layouts
+/// can be written. Errors caused by an image are wrapped in an
+/// [`Error::InImage`], naming the first image that's too large or that
+/// doesn't fit in the file.
+pub(crate) fn check_layout(
+    header: FileHeader,
+    raw_chunks: &[RawChunk],
+    layout: &[ImageLayout],
+) -> Result<()> {
+    check_file_header(header)?;
+    let mut chunks_byte_length = 0u32;
+
+    for chunk in raw_chunks {
+        chunks_byte_length = chunks_byte_length
+            .checked_add(chunk.byte_length()?)
+            .ok_or(Error::FileTooLarge)?;
+    }
+
+    // The table of contents only depends on the number of chunks, so the
+    // image that pushes the file past 4 GiB can be found
+    let mut file_length =
+        file_byte_length(header, raw_chunks.len() + layout.len(), chunks_byte_length)
+            .ok_or(Error::FileTooLarge)?;
+
+    for (index, image) in layout.iter().enumerate() {
+        let res = if image.width > Image::MAX_DIMENSION || image.height > Image::MAX_DIMENSION {
+            Err(Error::ImageTooLarge {
+                width: image.width,
+                height: image.height,
+            })
+        } else {
+            image
+                .byte_length()
+                .and_then(|length| file_length.checked_add(length).ok_or(Error::FileTooLarge))
+        };
+
+        file_length = res.map_err(|err| Error::in_image(index, image.size, err))?;
+    }
+
+    Ok(())
+}
+
+/// Checks that a file header is long enough to hold its own fields.
+fn check_file_header(header: FileHeader) -> Result<()> {
+    if header.header_size < HEADER_BYTE_LENGTH {
+        return Err(Error::HeaderTooSmall(header.header_size));
+    }
+
+    Ok(())
+}
+
+/// A 64-bit FNV-1a hasher. Unlike the standard library's hashers, this
+/// gives the same results everywhere, since integers are always hashed as
+/// little-endian bytes.
+struct ContentHasher(u64);
+
+impl ContentHasher {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for XcursorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_chunks, validation::round_trip, Chunk};
+
+    fn image(size: u32) -> Image {
+        let pixels = size * size;
+        Image::new(size, size, size, 0, 0, 0, vec![0xFF000000; pixels as usize]).unwrap()
+    }
+
+    /// Describes a file's chunks in order, using each image's nominal size
+    /// and each comment's text.
+    fn chunk_order(bytes: &[u8]) -> Vec<String> {
+        parse_chunks(bytes)
+            .unwrap()
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Image(image) => image.info.size().to_string(),
+                Chunk::Comment(comment) => comment.text_lossy().into_owned(),
+                Chunk::Unknown(_) => "unknown".to_owned(),
+            })
+            .collect()
+    }
+
+    fn write(cursor: &XcursorBuilder) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        cursor.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// A cursor with a comment between its two sizes.
+    fn interleaved() -> XcursorBuilder {
+        let mut cursor = XcursorBuilder::new();
+        cursor
+            .add_image(image(1))
+            .unwrap()
+            .add_comment(CommentKind::Other, "between")
+            .unwrap()
+            .add_image(image(2))
+            .unwrap();
+        cursor
+    }
+
+    #[test]
+    fn chunk_order_round_trip() {
+        let bytes = write(&interleaved());
+        assert_eq!(chunk_order(&bytes), ["1", "between", "2"]);
+
+        let parsed = XcursorBuilder::parse(&bytes).unwrap();
+        assert_eq!(write(&parsed), bytes);
+        assert_eq!(round_trip(&bytes).unwrap(), None);
+    }
+
+    #[test]
+    fn remove_size_keeps_chunk_order() {
+        let mut cursor = interleaved();
+        cursor.remove_size(1);
+        assert_eq!(chunk_order(&write(&cursor)), ["between", "2"]);
+
+        let mut cursor = interleaved();
+        cursor.remove_size(2);
+        assert_eq!(chunk_order(&write(&cursor)), ["1", "between"]);
+    }
+
+    #[test]
+    fn replace_size_keeps_chunk_order() {
+        let mut cursor = interleaved();
+        cursor.replace_size(1, [image(3), image(4)]).unwrap();
+        assert_eq!(chunk_order(&write(&cursor)), ["3", "4", "between", "2"]);
+
+        let mut cursor = interleaved();
+        cursor.replace_size(2, [image(3)]).unwrap();
+        assert_eq!(chunk_order(&write(&cursor)), ["1", "between", "3"]);
+
+        // Sizes that weren't there are added after every other chunk
+        let mut cursor = interleaved();
+        cursor.replace_size(5, [image(5)]).unwrap();
+        assert_eq!(chunk_order(&write(&cursor)), ["1", "between", "2", "5"]);
+    }
+
+    #[test]
+    fn streaming_writer_interleaves_chunks() {
+        let cursor = interleaved();
+        let mut writer = StreamingWriter::with_interleaved_chunks(
+            Vec::new(),
+            FileHeader::default(),
+            cursor.raw_chunks(),
+            &[1],
+            cursor.images().iter().map(Image::layout),
+        )
+        .unwrap();
+
+        for image in cursor.images() {
+            writer.write_image(image).unwrap();
+        }
+
+        assert_eq!(writer.finish().unwrap(), write(&cursor));
     }
 }